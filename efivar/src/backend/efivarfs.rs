@@ -1,88 +1,656 @@
+use std::future::Future;
 use std::io;
-use std::io::Cursor;
+use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 use async_trait::async_trait;
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, WriteBytesExt};
 use enumflags2::BitFlags;
-use futures::{stream, StreamExt, TryStreamExt};
-use gio::{Cancellable, File, FileQueryInfoFlags, glib, MountMountFlags, MountOperation};
+use futures::{pin_mut, stream, StreamExt, TryStreamExt};
+use futures::future::{Either, select};
+use gio::{Cancellable, File, FileCreateFlags, FileInfo, FileQueryInfoFlags, glib, IOErrorEnum, MountMountFlags, MountOperation, OutputStream};
 use gio::glib::Priority;
+use log::warn;
 use thiserror::Error;
-use crate::backend::EFIVars;
-use crate::efivar::{EFIVariable, VariableName, VariableNameFromStrError};
+use crate::backend::{BackendCapabilities, EFIVars, trace_elapsed, trace_start};
+use crate::efivar::{EFIVAR_ATTRIBUTES_SIZE, EFIVariable, EFIVariableAttribute, parse_efivar_payload, PayloadTooShortError, VariableName, VariableNameFromStrError};
 use gio::prelude::*;
 
+/// Where the kernel exposes the firmware interfaces, including efivarfs.
+const EFI_FIRMWARE_DIR: &str = "/sys/firmware/efi";
+
 pub struct EFIVarFS {
     root: File,
+    concurrency: usize,
+    cancellable: Option<Cancellable>,
 }
 
 impl EFIVarFS {
-    pub async fn new_gvfs_admin() -> Result<Self, glib::Error> {
-        let root = File::for_uri("admin:///sys/firmware/efi/efivars");
-        root.mount_enclosing_volume_future(MountMountFlags::empty(), None::<&MountOperation>).await?;
-        Ok(Self { root })
+    /// How long to wait for the admin volume to be mounted, which includes the user responding to
+    /// the polkit authentication prompt.
+    pub const DEFAULT_MOUNT_TIMEOUT: Duration = Duration::from_secs(30);
+    /// How many gvfs operations bulk reads keep in flight. Each one is a separate request to the
+    /// gvfsd-admin daemon, too many at once can make it fail requests spuriously.
+    pub const DEFAULT_CONCURRENCY: usize = 8;
+
+    pub async fn new_gvfs_admin() -> Result<Self, MountError> {
+        Self::new_gvfs_admin_with_timeout(Self::DEFAULT_MOUNT_TIMEOUT).await
+    }
+
+    /// Fails with [MountError::NotAnEfiSystem] without asking for authentication if the system
+    /// didn't boot via UEFI.
+    pub async fn new_gvfs_admin_with_timeout(timeout: Duration) -> Result<Self, MountError> {
+        // only exists when booted via UEFI, and can be checked without privileges
+        if !Path::new(EFI_FIRMWARE_DIR).is_dir() {
+            return Err(MountError::NotAnEfiSystem);
+        }
+
+        let root = File::for_uri(&format!("admin://{}/efivars", EFI_FIRMWARE_DIR));
+        let start = trace_start();
+        // dropping the mount future on timeout cancels the pending mount operation
+        let mount = glib::future_with_timeout(timeout, root.mount_enclosing_volume_future(MountMountFlags::empty(), None::<&MountOperation>))
+            .await
+            .map_err(|_| MountError::MountTimeout(timeout))?;
+        trace_elapsed!(start, "mounted {}: {:?}", root.uri(), mount);
+        match mount {
+            Ok(()) => {}
+            // mounted by another instance or application, which is just as good
+            Err(err) if err.matches(IOErrorEnum::AlreadyMounted) => {}
+            Err(err) => return Err(err.into()),
+        }
+        ensure_efi_system(&root).await?;
+
+        Ok(Self::new(root))
+    }
+
+    /// Uses efivarfs directly instead of through gvfs, for systems without gvfs. Fails with
+    /// [MountError::NotWritable] unless the process may write to it, as when running as root.
+    pub async fn new_direct() -> Result<Self, MountError> {
+        if !Path::new(EFI_FIRMWARE_DIR).is_dir() {
+            return Err(MountError::NotAnEfiSystem);
+        }
+
+        Self::open_direct(File::for_path(Path::new(EFI_FIRMWARE_DIR).join("efivars"))).await
+    }
+
+    async fn open_direct(root: File) -> Result<Self, MountError> {
+        ensure_efi_system(&root).await?;
+        let info = root.query_info_future("access::can-write", FileQueryInfoFlags::empty(), Priority::default()).await?;
+        if !info.boolean("access::can-write") {
+            return Err(MountError::NotWritable);
+        }
+
+        Ok(Self::new(root))
+    }
+
+    fn new(root: File) -> Self {
+        Self { root, concurrency: Self::DEFAULT_CONCURRENCY, cancellable: None }
+    }
+
+    /// Limits how many gvfs operations are in flight at once during bulk reads like
+    /// [EFIVars::read_variables] and [EFIVars::list_variables]. Lower limits make listing slower
+    /// but help on setups where the admin mount fails under load, down to 1 for strictly one
+    /// operation at a time. Zero is treated as 1.
+    pub fn with_concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = limit.max(1);
+        self
+    }
+
+    /// Aborts enumerating and reading variables once the cancellable is cancelled, so a hung
+    /// admin mount can be given up on. Those operations then fail with a `Cancelled` error, as do
+    /// any started later on. Writes aren't cancelled, so they aren't left half done.
+    pub fn with_cancellable(mut self, cancellable: Cancellable) -> Self {
+        self.cancellable = Some(cancellable);
+        self
+    }
+
+    /// Lists the files in the efivars directory with only the file attributes asked for, like
+    /// `standard::name`. Every other attribute is another lookup for gvfs, for each variable.
+    async fn enumerate_children(&self, attributes: &str) -> Result<Vec<FileInfo>, ListVariablesError> {
+        let start = trace_start();
+        self.cancellable(async {
+            self.root
+                .enumerate_children_future(attributes, FileQueryInfoFlags::empty(), Priority::default())
+                .await?
+                .into_stream(10, Priority::default())
+                .inspect_ok(|files| trace_elapsed!(start, "enumerated chunk of {} variables", files.len()))
+                .map_ok(|files| stream::iter(files).map(Ok::<_, glib::Error>))
+                .try_flatten()
+                .try_collect::<Vec<_>>()
+                .await
+        }).await.map_err(ListVariablesError::from)
+    }
+
+    /// Runs the gio operation until it completes or the cancellable is cancelled. Dropping the
+    /// operation's future cancels the operation itself.
+    async fn cancellable<T>(&self, future: impl Future<Output=Result<T, glib::Error>>) -> Result<T, glib::Error> {
+        let Some(cancellable) = &self.cancellable else {
+            return future.await;
+        };
+        cancellable.set_error_if_cancelled()?;
+
+        pin_mut!(future);
+        match select(future, cancellable.future()).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(glib::Error::new(IOErrorEnum::Cancelled, "operation was cancelled")),
+        }
     }
 }
 
+/// Fails with [MountError::NotAnEfiSystem] if the efivars directory is missing or holds no
+/// variables, as with legacy BIOS boots and WSL, instead of listing nothing later on.
+async fn ensure_efi_system(root: &File) -> Result<(), MountError> {
+    let children = match root.enumerate_children_future("standard::name", FileQueryInfoFlags::empty(), Priority::default()).await {
+        Ok(children) => children,
+        Err(err) if err.matches(IOErrorEnum::NotFound) => return Err(MountError::NotAnEfiSystem),
+        Err(err) => return Err(err.into()),
+    };
+    if children.next_files_future(1, Priority::default()).await?.is_empty() {
+        return Err(MountError::NotAnEfiSystem);
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum MountError {
+    #[error("this system did not boot via UEFI")]
+    NotAnEfiSystem,
+    #[error("glib produced an error while mounting efivars directory: {0}")]
+    GLibError(#[from] glib::Error),
+    #[error("timed out after {0:?} waiting for efivars directory to be mounted")]
+    MountTimeout(Duration),
+    #[error("no write access to efivars directory, try running as root")]
+    NotWritable,
+}
+
 #[derive(Debug, Error)]
 pub enum ListVariablesError {
     #[error("glib produced an error while enumerating efivars directory")]
-    GLibError(#[from] glib::Error),
-    #[error("error while parsing efi variable name")]
-    NameError(#[from] VariableNameFromStrError),
+    GLibError(glib::Error),
+    #[error("enumerating efivars directory was cancelled")]
+    Cancelled,
+}
+
+impl From<glib::Error> for ListVariablesError {
+    fn from(err: glib::Error) -> Self {
+        match err.matches(IOErrorEnum::Cancelled) {
+            true => ListVariablesError::Cancelled,
+            false => ListVariablesError::GLibError(err),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
 pub enum ReadVariableError {
     #[error("glib produced an error while reading efi variable")]
+    GLibError(glib::Error),
+    #[error("error reading efi variable attributes: {0}")]
+    PayloadError(#[from] PayloadTooShortError),
+    #[error("reading efi variable was cancelled")]
+    Cancelled,
+}
+
+impl From<glib::Error> for ReadVariableError {
+    fn from(err: glib::Error) -> Self {
+        match err.matches(IOErrorEnum::Cancelled) {
+            true => ReadVariableError::Cancelled,
+            false => ReadVariableError::GLibError(err),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WriteVariableError {
+    #[error("glib produced an error while writing efi variable")]
     GLibError(#[from] glib::Error),
-    #[error("error reading efi variable attributes")]
+    #[error("error serializing efi variable attributes")]
     IoError(#[from] io::Error),
+    #[error("{0} is immutable, clear the flag with `chattr -i` on its efivarfs file to change it")]
+    ImmutableError(VariableName),
+}
+
+/// efivarfs marks most variables immutable so they aren't removed by accident, writing or deleting
+/// them then fails with a permission error, even as root. Permission errors are only reported as
+/// [WriteVariableError::ImmutableError] if the file's flags confirm it, as they also come from
+/// the user declining authentication or the admin mount going away.
+fn immutable_error(file: &File, name: &VariableName, err: WriteVariableError) -> WriteVariableError {
+    match err {
+        WriteVariableError::GLibError(err) if err.matches(IOErrorEnum::PermissionDenied) && is_immutable(file) == Some(true) => {
+            WriteVariableError::ImmutableError(name.clone())
+        }
+        err => err,
+    }
+}
+
+/// Whether the variable's efivarfs file has the immutable flag set, `None` if the flags can't be
+/// queried or the file has no local path. Checks the local file directly, as gio doesn't expose
+/// the flags; opening it for reading doesn't need privileges for most variables.
+#[cfg(target_os = "linux")]
+fn is_immutable(file: &File) -> Option<bool> {
+    use std::os::fd::AsRawFd;
+
+    const FS_IMMUTABLE_FL: libc::c_int = 0x00000010;
+
+    let file = std::fs::File::open(file.path()?).ok()?;
+    // the kernel writes an int, even though the ioctl is declared with a long
+    let mut flags: libc::c_int = 0;
+    match unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) } {
+        0 => Some(flags & FS_IMMUTABLE_FL != 0),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_immutable(_file: &File) -> Option<bool> {
+    None
 }
 
 #[async_trait(? Send)]
 impl EFIVars for EFIVarFS {
     type ListError = ListVariablesError;
     type ReadError = ReadVariableError;
+    type WriteError = WriteVariableError;
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::READ_WRITE
+    }
+
+    fn max_concurrency(&self) -> usize {
+        self.concurrency
+    }
 
     async fn enumerate_variables(&self) -> Result<Vec<VariableName>, Self::ListError> {
-        self.root
-            .enumerate_children_future("standard::name", FileQueryInfoFlags::empty(), Priority::default())
-            .await?
-            .into_stream(10, Priority::default())
-            .map_ok(|files| stream::iter(files).map(Ok::<_, glib::Error>))
-            .try_flatten()
-            .map(|file| {
-                let name = file?.name();
-                let name = name.to_str().ok_or(ListVariablesError::from(VariableNameFromStrError::InvalidFormat))?;
-                Ok(VariableName::from_str(name)?)
-            })
-            .try_collect::<Vec<_>>()
-            .await
+        let start = trace_start();
+        let names = self.enumerate_children("standard::name").await
+            .map(|files| files.iter().filter_map(|file| parse_file_name(&file.name())).collect::<Vec<_>>());
+        trace_elapsed!(start, "enumerated variables: {:?}", names.as_ref().map(Vec::len));
+        names
+    }
+
+    /// The sizes come with the directory listing, instead of a query per variable.
+    async fn enumerate_variable_sizes(&self) -> Result<Vec<(VariableName, Result<u64, Self::ReadError>)>, Self::ListError> {
+        let start = trace_start();
+        let sizes = self.enumerate_children("standard::name,standard::size").await
+            .map(|files| files.iter()
+                .filter_map(|file| Some((parse_file_name(&file.name())?, data_size(file.size()))))
+                .collect::<Vec<_>>());
+        trace_elapsed!(start, "enumerated variables with sizes: {:?}", sizes.as_ref().map(Vec::len));
+        sizes
     }
 
     async fn read_variable(&self, name: &VariableName) -> Option<Result<EFIVariable, Self::ReadError>> {
-        let file = self.root.resolve_relative_path(format!("{}-{:x}", name.key(), name.vendor()).as_str());
-        if !file.query_exists(None::<&Cancellable>) {
-            return None;
+        let file = self.root.resolve_relative_path(name.file_name().as_str());
+
+        // load in a single request instead of checking for existence and querying the size first.
+        // this reads until EOF, so runtime variables like BootCurrent that change size in between
+        // calls are never truncated to a stale size
+        let start = trace_start();
+        let contents = self.cancellable(file.load_contents_future()).await;
+        trace_elapsed!(start, "read {}-{}: {:?}", name.key(), name.vendor(), contents.as_ref().map(|(buffer, _)| buffer.len()));
+        let buffer = match contents {
+            Ok((buffer, _)) => buffer,
+            Err(err) if err.matches(IOErrorEnum::NotFound) => return None,
+            Err(err) => return Some(Err(err.into())),
+        };
+
+        Some(EFIVariable::from_raw(name.clone(), &buffer).map_err(ReadVariableError::from))
+    }
+
+    async fn variable_exists(&self, name: &VariableName) -> bool {
+        self.root.resolve_relative_path(name.file_name().as_str()).query_exists(None::<&Cancellable>)
+    }
+
+    async fn variable_size(&self, name: &VariableName) -> Option<Result<u64, Self::ReadError>> {
+        let file = self.root.resolve_relative_path(name.file_name().as_str());
+
+        let start = trace_start();
+        let info = self.cancellable(file.query_info_future("standard::size", FileQueryInfoFlags::empty(), Priority::default())).await;
+        trace_elapsed!(start, "queried size of {}-{}: {:?}", name.key(), name.vendor(), info.as_ref().map(|info| info.size()));
+        match info {
+            Ok(info) => Some(data_size(info.size())),
+            Err(err) if err.matches(IOErrorEnum::NotFound) => None,
+            Err(err) => Some(Err(err.into())),
         }
+    }
 
-        async fn read_existing_variable(file: File, name: &VariableName) -> Result<EFIVariable, ReadVariableError> {
-            let size = file.query_info_future("standard::size", FileQueryInfoFlags::empty(), Priority::default())
-                .await?
-                .size() as usize;
-            let buffer = vec![0u8; size];
-            match file.read_future(Priority::default()).await?
-                .read_all_future(buffer, Priority::default()).await.map_err(|(_, err)| err)? {
+    /// Reads only the attributes at the start of the file. The kernel still fetches the whole
+    /// variable from the firmware, but its data isn't passed through gvfs.
+    async fn variable_attributes(&self, name: &VariableName) -> Option<Result<BitFlags<EFIVariableAttribute>, Self::ReadError>> {
+        let file = self.root.resolve_relative_path(name.file_name().as_str());
+
+        let start = trace_start();
+        let header = self.cancellable(async {
+            let stream = file.read_future(Priority::default()).await?;
+            let (buffer, length, err) = stream.read_all_future(vec![0u8; EFIVAR_ATTRIBUTES_SIZE], Priority::default()).await
+                .map_err(|(_, err)| err)?;
+            if let Some(err) = err {
+                return Err(err);
+            }
+            stream.close_future(Priority::default()).await?;
+            Ok(buffer[..length].to_vec())
+        }).await;
+        trace_elapsed!(start, "read attributes of {}-{}: {:?}", name.key(), name.vendor(), header.as_ref().map(Vec::len));
+        match header {
+            Ok(header) => Some(parse_efivar_payload(&header).map(|(attributes, _)| attributes).map_err(ReadVariableError::from)),
+            Err(err) if err.matches(IOErrorEnum::NotFound) => None,
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+
+    async fn write_variable(&self, variable: &EFIVariable) -> Result<(), Self::WriteError> {
+        let name = variable.name();
+        let file = self.root.resolve_relative_path(name.file_name().as_str());
+
+        // efivarfs expects the attributes and data in a single write
+        let mut buffer = Vec::with_capacity(EFIVAR_ATTRIBUTES_SIZE + variable.data().len());
+        buffer.write_u32::<LittleEndian>(variable.attributes().bits())?;
+        buffer.extend_from_slice(variable.data());
+
+        async fn write_buffer(stream: &OutputStream, buffer: Vec<u8>) -> Result<(), WriteVariableError> {
+            match stream.write_all_future(buffer, Priority::default()).await.map_err(|(_, err)| err)? {
                 (_, _, Some(err)) => Err(err.into()),
-                (buffer, _, None) => {
-                    let attributes = Cursor::new(&buffer[0..4]).read_u32::<LittleEndian>()?;
-                    let attributes = BitFlags::from_bits_truncate(attributes);
-                    Ok(EFIVariable::new(name.clone(), attributes, buffer[4..].to_vec()))
+                (_, _, None) => Ok(()),
+            }
+        }
+
+        let start = trace_start();
+        let length = buffer.len();
+        let result = async {
+            // existing variables are overwritten in place, efivarfs doesn't support replacing the file.
+            // creating first saves new variables an extra request to check whether they exist
+            match file.create_future(FileCreateFlags::NONE, Priority::default()).await {
+                Ok(stream) => {
+                    write_buffer(stream.upcast_ref(), buffer).await?;
+                    stream.close_future(Priority::default()).await?;
                 }
+                Err(err) if err.matches(IOErrorEnum::Exists) => {
+                    let stream = file.open_readwrite_future(Priority::default()).await?;
+                    write_buffer(&stream.output_stream(), buffer).await?;
+                    // the file keeps the tail of a longer old value otherwise
+                    if stream.can_truncate() {
+                        stream.truncate(length as i64, None::<&Cancellable>)?;
+                    }
+                    stream.close_future(Priority::default()).await?;
+                }
+                Err(err) => return Err(err.into()),
             }
+            Ok(())
+        }.await.map_err(|err| immutable_error(&file, name, err));
+        trace_elapsed!(start, "wrote {} bytes to {}-{}: {:?}", length, name.key(), name.vendor(), result);
+
+        result
+    }
+
+    async fn delete_variable(&self, name: &VariableName) -> Result<bool, Self::WriteError> {
+        let file = self.root.resolve_relative_path(name.file_name().as_str());
+
+        let start = trace_start();
+        let result = match file.delete_future(Priority::default()).await {
+            Ok(()) => Ok(true),
+            Err(err) if err.matches(IOErrorEnum::NotFound) => Ok(false),
+            Err(err) => Err(immutable_error(&file, name, err.into())),
+        };
+        trace_elapsed!(start, "deleted {}-{}: {:?}", name.key(), name.vendor(), result);
+
+        result
+    }
+}
+
+/// Size of the variable data in an efivarfs file of `file_size` bytes, which starts with the
+/// attributes that aren't part of the data.
+fn data_size(file_size: i64) -> Result<u64, ReadVariableError> {
+    let size = usize::try_from(file_size).unwrap_or_default();
+    size.checked_sub(EFIVAR_ATTRIBUTES_SIZE)
+        .map(|size| size as u64)
+        .ok_or(ReadVariableError::PayloadError(PayloadTooShortError(size)))
+}
+
+/// Parses an efivarfs file name of the form `<key>-<vendor>`. Files that don't match are skipped
+/// with a warning instead of failing the whole enumeration, as some firmware writes odd names.
+fn parse_file_name(name: &Path) -> Option<VariableName> {
+    let parsed = name.to_str()
+        .ok_or(VariableNameFromStrError::InvalidFormat)
+        .and_then(VariableName::from_str);
+    match parsed {
+        Ok(name) => Some(name),
+        Err(err) => {
+            warn!("Skipping efi variable {:?}: {}", name, err);
+            None
+        }
+    }
+}
+
+// non UTF-8 names can only be constructed on unix
+#[cfg(all(test, unix))]
+mod tests {
+    use std::ffi::OsStr;
+    use std::ops::Deref;
+    use std::os::unix::ffi::OsStrExt;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use gio::{Cancellable, File};
+    use gio::glib::MainContext;
+    use gio::prelude::*;
+    use crate::backend::EFIVars;
+    use crate::backend::efivarfs::{EFIVarFS, ensure_efi_system, ListVariablesError, MountError, parse_file_name, ReadVariableError};
+    use crate::efiboot::BOOT_VARIABLE_ATTRIBUTES;
+    use crate::efivar::{EFIVariable, VariableName};
+
+    /// A scratch directory standing in for efivarfs, removed again when dropped so failing tests
+    /// don't leave it behind.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("efivarfs-{name}-test-{}", std::process::id()));
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
         }
+    }
+
+    impl Deref for TempDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_parse_file_name_skips_invalid() {
+        let names = [
+            OsStr::new("BootOrder-8be4df61-93ca-11d2-aa0d-00e098032b8c"),
+            OsStr::from_bytes(b"Boot\xFF-8be4df61-93ca-11d2-aa0d-00e098032b8c"),
+            OsStr::new("Garbage"),
+            OsStr::new("Boot0001-8be4df61-93ca-11d2-aa0d-00e098032b8c"),
+        ];
+
+        let keys = names.iter()
+            .filter_map(|name| parse_file_name(Path::new(name)))
+            .map(|name| name.key().to_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec!["BootOrder", "Boot0001"], keys);
+    }
+
+    #[test]
+    fn test_enumerate_variable_sizes() {
+        let dir = TempDir::new("sizes");
+        let order = VariableName::global_vendor_new("BootOrder".to_owned());
+        let truncated = VariableName::global_vendor_new("Boot0001".to_owned());
+        fs::write(dir.join(order.file_name()), [0x07, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00]).unwrap();
+        fs::write(dir.join(truncated.file_name()), [0x07, 0x00]).unwrap();
+        fs::write(dir.join("Garbage"), [0x07, 0x00, 0x00, 0x00]).unwrap();
+        let efivars = EFIVarFS::new(File::for_path(&*dir));
+        let context = MainContext::new();
+
+        let mut sizes = context.with_thread_default(|| context.block_on(efivars.enumerate_variable_sizes())).unwrap().unwrap();
+
+        sizes.sort_by(|(a, _), (b, _)| a.key().cmp(b.key()));
+        assert_eq!(2, sizes.len());
+        assert_eq!(&truncated, &sizes[0].0);
+        assert!(matches!(sizes[0].1, Err(ReadVariableError::PayloadError(_))));
+        assert_eq!(&order, &sizes[1].0);
+        assert_eq!(4, *sizes[1].1.as_ref().unwrap());
+    }
+
+    #[test]
+    fn test_ensure_efi_system() {
+        let dir = TempDir::new("ensure");
+        let context = MainContext::new();
+        let check = |path: &Path| context.with_thread_default(|| context.block_on(ensure_efi_system(&File::for_path(path)))).unwrap();
+
+        let empty = check(&dir);
+        let missing = check(&dir.join("missing"));
+        fs::write(dir.join("BootOrder-8be4df61-93ca-11d2-aa0d-00e098032b8c"), [0x07, 0x00, 0x00, 0x00]).unwrap();
+        let populated = check(&dir);
+
+        assert!(matches!(empty, Err(MountError::NotAnEfiSystem)));
+        assert!(matches!(missing, Err(MountError::NotAnEfiSystem)));
+        assert!(populated.is_ok());
+    }
+
+    #[test]
+    fn test_open_direct() {
+        let dir = TempDir::new("direct");
+        let context = MainContext::new();
+        let open = |path: &Path| context.with_thread_default(|| context.block_on(EFIVarFS::open_direct(File::for_path(path)))).unwrap();
+
+        let empty = open(&dir);
+        fs::write(dir.join("BootOrder-8be4df61-93ca-11d2-aa0d-00e098032b8c"), [0x07, 0x00, 0x00, 0x00, 0x01, 0x00]).unwrap();
+        let efivars = open(&dir).unwrap();
+        let order = context.with_thread_default(|| context.block_on(efivars.read_variable(&VariableName::global_vendor_new("BootOrder".to_owned())))).unwrap();
+
+        assert!(matches!(empty, Err(MountError::NotAnEfiSystem)));
+        assert_eq!(&[0x01, 0x00], order.unwrap().unwrap().data());
+    }
+
+    #[test]
+    fn test_write_variable_round_trip() {
+        let dir = TempDir::new("write");
+        let efivars = EFIVarFS::new(File::for_path(&*dir));
+        let context = MainContext::new();
+        let variable = EFIVariable::new(VariableName::global_vendor_new("Boot0001".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x02, 0x03]);
+
+        context.with_thread_default(|| context.block_on(efivars.write_variable(&variable))).unwrap().unwrap();
+        let read = context.with_thread_default(|| context.block_on(efivars.read_variable(variable.name()))).unwrap().unwrap().unwrap();
+        let raw = fs::read(dir.join(variable.name().file_name())).unwrap();
+
+        assert_eq!(variable.attributes(), read.attributes());
+        assert_eq!(variable.data(), read.data());
+        assert_eq!(vec![0x07, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03], raw);
+    }
+
+    #[test]
+    fn test_write_variable_shrinks() {
+        let dir = TempDir::new("shrink");
+        let efivars = EFIVarFS::new(File::for_path(&*dir));
+        let context = MainContext::new();
+        let name = VariableName::global_vendor_new("Boot0001".to_owned());
+        let write = |data: Vec<u8>| {
+            let variable = EFIVariable::new(name.clone(), BOOT_VARIABLE_ATTRIBUTES, data);
+            context.with_thread_default(|| context.block_on(efivars.write_variable(&variable))).unwrap().unwrap()
+        };
+
+        write(vec![0x01, 0x02, 0x03]);
+        write(vec![0x04]);
+
+        assert_eq!(vec![0x07, 0x00, 0x00, 0x00, 0x04], fs::read(dir.join(name.file_name())).unwrap());
+    }
+
+    #[test]
+    fn test_append_variable_attributes() {
+        let dir = TempDir::new("append");
+        let efivars = EFIVarFS::new(File::for_path(&*dir));
+        let context = MainContext::new();
+        let name = VariableName::global_vendor_new("dbx".to_owned());
+
+        context.with_thread_default(|| context.block_on(efivars.append_variable(&name, BOOT_VARIABLE_ATTRIBUTES, &[0x01, 0x02]))).unwrap().unwrap();
+        let raw = fs::read(dir.join(name.file_name())).unwrap();
+
+        // efivarfs appends when the attributes written along with the data have AppendWrite set
+        assert_eq!(vec![0x47, 0x00, 0x00, 0x00, 0x01, 0x02], raw);
+    }
+
+    #[test]
+    fn test_delete_variable() {
+        let dir = TempDir::new("delete");
+        let efivars = EFIVarFS::new(File::for_path(&*dir));
+        let context = MainContext::new();
+        let variable = EFIVariable::new(VariableName::global_vendor_new("Boot0001".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01]);
+
+        context.with_thread_default(|| context.block_on(efivars.write_variable(&variable))).unwrap().unwrap();
+        let deleted = context.with_thread_default(|| context.block_on(efivars.delete_variable(variable.name()))).unwrap();
+        let read = context.with_thread_default(|| context.block_on(efivars.read_variable(variable.name()))).unwrap();
+        let deleted_again = context.with_thread_default(|| context.block_on(efivars.delete_variable(variable.name()))).unwrap();
+
+        assert!(deleted.unwrap());
+        assert!(read.is_none());
+        assert!(!deleted_again.unwrap());
+    }
+
+    #[test]
+    fn test_read_variable_after_size_change() {
+        let dir = TempDir::new("size");
+        let name = VariableName::global_vendor_new("BootCurrent".to_owned());
+        let path = dir.join(name.file_name());
+        let efivars = EFIVarFS::new(File::for_path(&*dir));
+        let context = MainContext::new();
+
+        fs::write(&path, [0x06, 0x00, 0x00, 0x00, 0x01, 0x00]).unwrap();
+        let size = context.with_thread_default(|| context.block_on(efivars.variable_size(&name))).unwrap();
+        fs::write(&path, [0x06, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00]).unwrap();
+        let variable = context.with_thread_default(|| context.block_on(efivars.read_variable(&name))).unwrap();
+
+        assert_eq!(2, size.unwrap().unwrap());
+        assert_eq!(&[0x01, 0x00, 0x02, 0x00, 0x03, 0x00], variable.unwrap().unwrap().data());
+    }
+
+    #[test]
+    fn test_variable_attributes() {
+        let dir = TempDir::new("attributes");
+        let order = VariableName::global_vendor_new("BootOrder".to_owned());
+        let truncated = VariableName::global_vendor_new("Boot0001".to_owned());
+        fs::write(dir.join(order.file_name()), [0x07, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00]).unwrap();
+        fs::write(dir.join(truncated.file_name()), [0x07, 0x00]).unwrap();
+        let efivars = EFIVarFS::new(File::for_path(&*dir));
+        let context = MainContext::new();
+        let attributes = |name: &VariableName| context.with_thread_default(|| context.block_on(efivars.variable_attributes(name))).unwrap();
+
+        let order_attributes = attributes(&order);
+        let truncated_attributes = attributes(&truncated);
+        let missing_attributes = attributes(&VariableName::global_vendor_new("Missing".to_owned()));
+
+        assert_eq!(BOOT_VARIABLE_ATTRIBUTES, order_attributes.unwrap().unwrap());
+        assert!(matches!(truncated_attributes, Some(Err(ReadVariableError::PayloadError(_)))), "{truncated_attributes:?}");
+        assert!(missing_attributes.is_none());
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let dir = TempDir::new("cancel");
+        let name = VariableName::global_vendor_new("BootOrder".to_owned());
+        fs::write(dir.join(name.file_name()), [0x07, 0x00, 0x00, 0x00, 0x01, 0x00]).unwrap();
+        let cancellable = Cancellable::new();
+        let efivars = EFIVarFS::new(File::for_path(&*dir)).with_cancellable(cancellable.clone());
+        let context = MainContext::new();
+
+        let read = context.with_thread_default(|| context.block_on(efivars.read_variable(&name))).unwrap();
+        cancellable.cancel();
+        let cancelled_read = context.with_thread_default(|| context.block_on(efivars.read_variable(&name))).unwrap();
+        let cancelled_size = context.with_thread_default(|| context.block_on(efivars.variable_size(&name))).unwrap();
+        let cancelled_enumerate = context.with_thread_default(|| context.block_on(efivars.enumerate_variables())).unwrap();
 
-        Some(read_existing_variable(file, name).await)
+        assert_eq!(&[0x01, 0x00], read.unwrap().unwrap().data());
+        assert!(matches!(cancelled_read, Some(Err(ReadVariableError::Cancelled))), "{cancelled_read:?}");
+        assert!(matches!(cancelled_size, Some(Err(ReadVariableError::Cancelled))), "{cancelled_size:?}");
+        assert!(matches!(cancelled_enumerate, Err(ListVariablesError::Cancelled)), "{cancelled_enumerate:?}");
     }
 }
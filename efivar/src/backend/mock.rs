@@ -0,0 +1,355 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use async_trait::async_trait;
+use crate::backend::{BackendCapabilities, EFIVars};
+use crate::efivar::{EFIVariable, EFIVariableAttribute, VariableName};
+
+/// In-memory [EFIVars] implementation, useful for testing without access to real firmware.
+/// Variables are enumerated sorted by their `Name-GUID` file name, so results are deterministic.
+#[derive(Debug, Default)]
+pub struct MemoryEFIVars {
+    variables: RefCell<HashMap<String, EFIVariable>>,
+}
+
+impl MemoryEFIVars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_variables(variables: impl IntoIterator<Item=EFIVariable>) -> Self {
+        let backend = Self::new();
+        for variable in variables {
+            backend.insert(variable);
+        }
+        backend
+    }
+
+    pub fn insert(&self, variable: EFIVariable) {
+        self.variables.borrow_mut().insert(Self::key(variable.name()), variable);
+    }
+
+    fn key(name: &VariableName) -> String {
+        name.file_name()
+    }
+}
+
+#[async_trait(? Send)]
+impl EFIVars for MemoryEFIVars {
+    type ListError = Infallible;
+    type ReadError = Infallible;
+    type WriteError = Infallible;
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::READ_WRITE
+    }
+
+    async fn enumerate_variables(&self) -> Result<Vec<VariableName>, Self::ListError> {
+        let variables = self.variables.borrow();
+        let mut keys = variables.keys().collect::<Vec<_>>();
+        keys.sort_unstable();
+        Ok(keys.into_iter().map(|key| variables[key].name().clone()).collect())
+    }
+
+    async fn read_variable(&self, name: &VariableName) -> Option<Result<EFIVariable, Self::ReadError>> {
+        self.variables.borrow().get(&Self::key(name)).cloned().map(Ok)
+    }
+
+    async fn variable_exists(&self, name: &VariableName) -> bool {
+        self.variables.borrow().contains_key(&Self::key(name))
+    }
+
+    async fn write_variable(&self, variable: &EFIVariable) -> Result<(), Self::WriteError> {
+        let mut variables = self.variables.borrow_mut();
+        let key = Self::key(variable.name());
+        let data = match variables.get(&key) {
+            Some(existing) if variable.attributes().contains(EFIVariableAttribute::AppendWrite) =>
+                [existing.data(), variable.data()].concat(),
+            _ => variable.data().to_vec(),
+        };
+        // AppendWrite only applies to the write, firmware doesn't store it with the variable
+        let mut attributes = variable.attributes();
+        attributes.remove(EFIVariableAttribute::AppendWrite);
+        variables.insert(key, EFIVariable::new(variable.name().clone(), attributes, data));
+        Ok(())
+    }
+
+    async fn delete_variable(&self, name: &VariableName) -> Result<bool, Self::WriteError> {
+        Ok(self.variables.borrow_mut().remove(&Self::key(name)).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::convert::Infallible;
+    use std::str::FromStr;
+    use std::task::Poll;
+    use async_trait::async_trait;
+    use enumflags2::{make_bitflags, BitFlags};
+    use futures::executor::block_on;
+    use futures::future::poll_fn;
+    use uuid::Uuid;
+    use crate::backend::EFIVars;
+    use crate::backend::mock::MemoryEFIVars;
+    use crate::efiboot::{boot_entry_name, BootOrder, BOOT_VARIABLE_ATTRIBUTES, ListBootEntriesExt};
+    use crate::efidevicepath::EFIDevicePathProtocol;
+    use crate::efiloadoption::{EFILoadOption, LoadOptionAttributeFlag, LoadOptionAttributes, LoadOptionCategory};
+    use crate::efivar::{EFIVariable, EFIVariableAttribute, VariableName};
+
+    #[test]
+    fn test_append_variable() {
+        let name = VariableName::global_vendor_new("dbx".to_owned());
+        let attributes = make_bitflags!(EFIVariableAttribute::{NonVolatile | BootServiceAccess | RuntimeAccess});
+        let efivars = MemoryEFIVars::with_variables([EFIVariable::new(name.clone(), attributes, vec![0x01, 0x02])]);
+
+        block_on(efivars.append_variable(&name, attributes, &[0x03])).unwrap();
+
+        let variable = block_on(efivars.read_variable(&name)).unwrap().unwrap();
+        assert_eq!(attributes, variable.attributes());
+        assert!(!variable.attributes().contains(EFIVariableAttribute::AppendWrite));
+        assert_eq!(&[0x01, 0x02, 0x03], variable.data());
+
+        let new_name = VariableName::global_vendor_new("New".to_owned());
+        block_on(efivars.append_variable(&new_name, BitFlags::empty(), &[0x04])).unwrap();
+        assert_eq!(&[0x04], block_on(efivars.read_variable(&new_name)).unwrap().unwrap().data());
+    }
+
+    #[test]
+    fn test_enumerate_variables_sorted() {
+        let variable = |key: &str| EFIVariable::new(VariableName::global_vendor_new(key.to_owned()), BitFlags::empty(), vec![]);
+        let efivars = MemoryEFIVars::with_variables(["Timeout", "Boot0002", "BootOrder", "Boot0001"].map(variable));
+
+        let names = block_on(efivars.enumerate_variables()).unwrap();
+        assert_eq!(vec!["Boot0001", "Boot0002", "BootOrder", "Timeout"], names.iter().map(VariableName::key).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_enumerate_variable_sizes() {
+        let variable = |key: &str, data: Vec<u8>| EFIVariable::new(VariableName::global_vendor_new(key.to_owned()), BitFlags::empty(), data);
+        let efivars = MemoryEFIVars::with_variables([variable("BootOrder", vec![0x01, 0x00, 0x02, 0x00]), variable("Timeout", vec![0x05, 0x00])]);
+
+        let sizes = block_on(efivars.enumerate_variable_sizes()).unwrap().into_iter()
+            .map(|(name, size)| (name.key().to_owned(), size.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![("BootOrder".to_owned(), 4), ("Timeout".to_owned(), 2)], sizes);
+    }
+
+    #[test]
+    fn test_progress() {
+        let variable = |key: &str| EFIVariable::new(VariableName::global_vendor_new(key.to_owned()), BitFlags::empty(), vec![]);
+        let efivars = MemoryEFIVars::with_variables(["Boot0001", "Boot0002", "BootOrder", "Timeout"].map(variable));
+        let calls = RefCell::new(vec![]);
+        let progress = |current, total| calls.borrow_mut().push((current, total));
+
+        let variables = block_on(efivars.list_variables_with_progress(Some(&progress))).unwrap();
+        assert_eq!(4, variables.len());
+        assert_eq!(vec![(1, 4), (2, 4), (3, 4), (4, 4)], calls.take());
+
+        let names = [VariableName::global_vendor_new("Timeout".to_owned()), VariableName::global_vendor_new("Missing".to_owned())];
+        let variables = block_on(efivars.read_variables_with_progress(&names, Some(&progress)));
+        assert!(variables[0].is_some() && variables[1].is_none());
+        assert_eq!(vec![(1, 2), (2, 2)], calls.take());
+    }
+
+    #[test]
+    fn test_list_boot_entries() {
+        let load_option = |description: &str| {
+            let attributes = LoadOptionAttributes::new(LoadOptionAttributeFlag::Active.into(), LoadOptionCategory::BOOT);
+            let file_path_list = vec![EFIDevicePathProtocol::new_file_path("\\EFI\\BOOT\\BOOTX64.EFI")];
+            EFILoadOption::new(attributes, description, file_path_list, vec![]).unwrap()
+        };
+        let entry = |id: u16, description: &str| EFIVariable::new(boot_entry_name(id), BOOT_VARIABLE_ATTRIBUTES, load_option(description).to_bytes().unwrap());
+        let efivars = MemoryEFIVars::with_variables([
+            entry(1, "Linux"),
+            entry(2, "Windows"),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, BootOrder::new(vec![2, 1]).to_bytes()),
+        ]);
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(&BootOrder::new(vec![2, 1]), entries.order());
+        let listed = entries.iter().map(|entry| (entry.id(), entry.description())).collect::<Vec<_>>();
+        assert_eq!(vec![(2, "Windows"), (1, "Linux")], listed);
+    }
+
+    #[test]
+    fn test_read_variables_order() {
+        let variable = |key: &str, data: u8| EFIVariable::new(VariableName::global_vendor_new(key.to_owned()), BitFlags::empty(), vec![data]);
+        let efivars = MemoryEFIVars::with_variables([variable("A", 1), variable("B", 2), variable("C", 3)]);
+        let names = ["C", "Missing", "A", "B"].map(|key| VariableName::global_vendor_new(key.to_owned()));
+
+        let results = block_on(efivars.read_variables(&names));
+
+        let data = results.into_iter()
+            .map(|result| result.map(|variable| variable.unwrap().data()[0]))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![Some(3), None, Some(1), Some(2)], data);
+    }
+
+    #[test]
+    fn test_nvram_usage() {
+        let variable = |key: &str, attributes, size| EFIVariable::new(VariableName::global_vendor_new(key.to_owned()), attributes, vec![0; size]);
+        let non_volatile = make_bitflags!(EFIVariableAttribute::{NonVolatile | BootServiceAccess | RuntimeAccess});
+        let efivars = MemoryEFIVars::with_variables([
+            variable("Boot0001", non_volatile, 120),
+            variable("BootOrder", non_volatile, 6),
+            variable("BootCurrent", make_bitflags!(EFIVariableAttribute::{BootServiceAccess | RuntimeAccess}), 2),
+            variable("Timeout", non_volatile, 2),
+        ]);
+
+        assert_eq!(128, block_on(efivars.nvram_usage()).unwrap());
+        assert_eq!(0, block_on(MemoryEFIVars::new().nvram_usage()).unwrap());
+
+        // backends with cheap sizes and attributes don't have the data read
+        assert_eq!(128, block_on(MetadataOnlyEFIVars(efivars).nvram_usage()).unwrap());
+    }
+
+    /// Answers size and attribute queries, but fails the test on reading a variable's data.
+    struct MetadataOnlyEFIVars(MemoryEFIVars);
+
+    #[async_trait(? Send)]
+    impl EFIVars for MetadataOnlyEFIVars {
+        type ListError = Infallible;
+        type ReadError = Infallible;
+        type WriteError = Infallible;
+
+        async fn enumerate_variables(&self) -> Result<Vec<VariableName>, Self::ListError> {
+            self.0.enumerate_variables().await
+        }
+
+        async fn read_variable(&self, name: &VariableName) -> Option<Result<EFIVariable, Self::ReadError>> {
+            panic!("read {name:?}")
+        }
+
+        async fn variable_size(&self, name: &VariableName) -> Option<Result<u64, Self::ReadError>> {
+            self.0.variable_size(name).await
+        }
+
+        async fn variable_attributes(&self, name: &VariableName) -> Option<Result<BitFlags<EFIVariableAttribute>, Self::ReadError>> {
+            self.0.variable_attributes(name).await
+        }
+
+        async fn write_variable(&self, variable: &EFIVariable) -> Result<(), Self::WriteError> {
+            self.0.write_variable(variable).await
+        }
+
+        async fn delete_variable(&self, name: &VariableName) -> Result<bool, Self::WriteError> {
+            self.0.delete_variable(name).await
+        }
+    }
+
+    #[test]
+    fn test_variable_attributes() {
+        let name = VariableName::global_vendor_new("BootOrder".to_owned());
+        let attributes = make_bitflags!(EFIVariableAttribute::{NonVolatile | BootServiceAccess | RuntimeAccess});
+        let efivars = MemoryEFIVars::with_variables([EFIVariable::new(name.clone(), attributes, vec![0x01, 0x00])]);
+
+        assert_eq!(attributes, block_on(efivars.variable_attributes(&name)).unwrap().unwrap());
+        assert!(block_on(efivars.variable_attributes(&VariableName::global_vendor_new("Missing".to_owned()))).is_none());
+    }
+
+    #[test]
+    fn test_list_vendors() {
+        let vendors = ["3c6f5e2a-7b1d-4c8e-9f0a-1b2c3d4e5f60", "8be4df61-93ca-11d2-aa0d-00e098032b8c", "d719b2cb-3d3a-4596-a3bc-dad00e67656f"]
+            .map(|vendor| Uuid::from_str(vendor).unwrap());
+        let variable = |key: &str, vendor: Uuid| EFIVariable::new(VariableName::new(key.to_owned(), vendor), BitFlags::empty(), vec![]);
+        let efivars = MemoryEFIVars::with_variables([
+            variable("BootOrder", vendors[1]),
+            variable("db", vendors[2]),
+            variable("Boot0001", vendors[1]),
+            variable("Custom", vendors[0]),
+            variable("dbx", vendors[2]),
+        ]);
+
+        assert_eq!(vendors.to_vec(), block_on(efivars.list_vendors()).unwrap());
+        assert!(block_on(MemoryEFIVars::new().list_vendors()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_variable_size() {
+        let name = VariableName::global_vendor_new("BootOrder".to_owned());
+        let efivars = MemoryEFIVars::with_variables([EFIVariable::new(name.clone(), BitFlags::empty(), vec![0x01, 0x00, 0x02, 0x00])]);
+
+        assert_eq!(4, block_on(efivars.variable_size(&name)).unwrap().unwrap());
+        assert!(block_on(efivars.variable_size(&VariableName::global_vendor_new("Missing".to_owned()))).is_none());
+    }
+
+    #[test]
+    fn test_variable_exists() {
+        let name = VariableName::global_vendor_new("BootOrder".to_owned());
+        let efivars = MemoryEFIVars::with_variables([EFIVariable::new(name.clone(), BitFlags::empty(), vec![0x01, 0x00])]);
+
+        assert!(block_on(efivars.variable_exists(&name)));
+        assert!(!block_on(efivars.variable_exists(&VariableName::global_vendor_new("Missing".to_owned()))));
+
+        block_on(efivars.delete_variable(&name)).unwrap();
+        assert!(!block_on(efivars.variable_exists(&name)));
+    }
+
+    /// Counts the reads in flight, each of which yields once before completing so the bulk
+    /// methods get a chance to overlap them.
+    struct CountingEFIVars {
+        inner: MemoryEFIVars,
+        limit: usize,
+        in_flight: Cell<usize>,
+        peak: Cell<usize>,
+    }
+
+    #[async_trait(? Send)]
+    impl EFIVars for CountingEFIVars {
+        type ListError = Infallible;
+        type ReadError = Infallible;
+        type WriteError = Infallible;
+
+        fn max_concurrency(&self) -> usize {
+            self.limit
+        }
+
+        async fn enumerate_variables(&self) -> Result<Vec<VariableName>, Self::ListError> {
+            self.inner.enumerate_variables().await
+        }
+
+        async fn read_variable(&self, name: &VariableName) -> Option<Result<EFIVariable, Self::ReadError>> {
+            self.in_flight.set(self.in_flight.get() + 1);
+            self.peak.set(self.peak.get().max(self.in_flight.get()));
+            let mut yielded = false;
+            poll_fn(|cx| if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }).await;
+            self.in_flight.set(self.in_flight.get() - 1);
+            self.inner.read_variable(name).await
+        }
+
+        async fn write_variable(&self, variable: &EFIVariable) -> Result<(), Self::WriteError> {
+            self.inner.write_variable(variable).await
+        }
+
+        async fn delete_variable(&self, name: &VariableName) -> Result<bool, Self::WriteError> {
+            self.inner.delete_variable(name).await
+        }
+    }
+
+    #[test]
+    fn test_max_concurrency() {
+        let names = (0..10).map(|index| VariableName::global_vendor_new(format!("Var{}", index))).collect::<Vec<_>>();
+        for limit in [1, 3] {
+            let efivars = CountingEFIVars {
+                inner: MemoryEFIVars::with_variables(names.iter().map(|name| EFIVariable::new(name.clone(), BitFlags::empty(), vec![]))),
+                limit,
+                in_flight: Cell::new(0),
+                peak: Cell::new(0),
+            };
+
+            assert_eq!(10, block_on(efivars.read_variables(&names)).len());
+            assert_eq!(limit, efivars.peak.get());
+
+            efivars.peak.set(0);
+            assert_eq!(10, block_on(efivars.list_variables()).unwrap().len());
+            assert_eq!(limit, efivars.peak.get());
+        }
+    }
+}
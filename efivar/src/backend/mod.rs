@@ -1,35 +1,302 @@
 use std::error::Error;
+#[cfg(all(target_os = "linux", feature = "gvfs"))]
+use std::future::Future;
+#[cfg(feature = "gvfs")]
+use std::time::Instant;
 use async_trait::async_trait;
+use enumflags2::BitFlags;
 use futures::{stream, StreamExt};
-use crate::backend::efivarfs::EFIVarFS;
-use crate::efivar::{EFIVariable, VariableName};
+#[cfg(feature = "gvfs")]
+use log::{Level, log_enabled};
+use log::debug;
+#[cfg(all(target_os = "linux", feature = "gvfs"))]
+use log::warn;
+use uuid::Uuid;
+#[cfg(all(target_os = "linux", feature = "gvfs"))]
+use crate::backend::efivarfs::{EFIVarFS, MountError};
+use crate::efivar::{EFIVariable, EFIVariableAttribute, VariableName};
 
+#[cfg(feature = "gvfs")]
 pub mod efivarfs;
+pub mod mock;
+#[cfg(target_os = "macos")]
+pub mod nvram;
+pub mod readonly;
+pub mod vendor;
+#[cfg(target_os = "windows")]
+pub mod windows;
+
+/// Log target of backend operations, enable with `RUST_LOG=efivar::backend=trace`.
+#[cfg(feature = "gvfs")]
+pub(crate) const LOG_TARGET: &str = "efivar::backend";
+
+/// Starts timing a backend operation, only when tracing is enabled for [LOG_TARGET].
+#[cfg(feature = "gvfs")]
+pub(crate) fn trace_start() -> Option<Instant> {
+    log_enabled!(target: LOG_TARGET, Level::Trace).then(Instant::now)
+}
+
+/// Logs a backend operation along with the time elapsed since [trace_start].
+#[cfg(feature = "gvfs")]
+macro_rules! trace_elapsed {
+    ($start:expr, $($arg:tt)+) => {
+        if let Some(start) = $start {
+            log::trace!(target: $crate::backend::LOG_TARGET, "{} in {:?}", format_args!($($arg)+), start.elapsed());
+        }
+    };
+}
+#[cfg(feature = "gvfs")]
+pub(crate) use trace_elapsed;
+
+/// Operations a backend supports, so callers can disable controls up front instead of failing
+/// deep in the write path.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BackendCapabilities {
+    pub read: bool,
+    pub write: bool,
+    pub delete: bool,
+    pub append: bool,
+}
+
+impl BackendCapabilities {
+    pub const READ_ONLY: Self = Self { read: true, write: false, delete: false, append: false };
+    pub const READ_WRITE: Self = Self { read: true, write: true, delete: true, append: true };
+}
+
+/// Default for [EFIVars::max_concurrency].
+const DEFAULT_CONCURRENCY: usize = 16;
 
 #[async_trait(? Send)]
 pub trait EFIVars {
     type ListError: 'static + Error;
     type ReadError: 'static + Error;
+    type WriteError: 'static + Error;
+
+    /// What the backend supports. Defaults to [BackendCapabilities::READ_ONLY].
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::READ_ONLY
+    }
+
+    /// Maximum number of operations the bulk methods like [EFIVars::read_variables] keep in
+    /// flight at once. Backends where overlapping requests are cheap raise it, fragile ones lower
+    /// it. Always at least 1.
+    fn max_concurrency(&self) -> usize {
+        DEFAULT_CONCURRENCY
+    }
 
     async fn enumerate_variables(&self) -> Result<Vec<VariableName>, Self::ListError>;
 
+    /// The distinct vendor GUIDs of all variables, sorted, for grouping variables by vendor.
+    async fn list_vendors(&self) -> Result<Vec<Uuid>, Self::ListError> {
+        let mut vendors = self.enumerate_variables().await?
+            .iter()
+            .map(|name| *name.vendor())
+            .collect::<Vec<_>>();
+        vendors.sort_unstable();
+        vendors.dedup();
+        Ok(vendors)
+    }
+
     async fn read_variable(&self, name: &VariableName) -> Option<Result<EFIVariable, Self::ReadError>>;
 
-    async fn list_variables(&self) -> Result<Vec<Result<EFIVariable, (VariableName, Self::ReadError)>>, Self::ListError> {
+    /// Whether the variable exists, without reading it where the backend allows. Defaults to
+    /// reading the variable, which counts as existing even if the read fails.
+    async fn variable_exists(&self, name: &VariableName) -> bool {
+        self.read_variable(name).await.is_some()
+    }
+
+    /// Size of the variable's data in bytes, without reading it where the backend allows. Defaults
+    /// to reading the variable and measuring its data.
+    async fn variable_size(&self, name: &VariableName) -> Option<Result<u64, Self::ReadError>> {
+        self.read_variable(name).await
+            .map(|result| result.map(|variable| variable.data().len() as u64))
+    }
+
+    /// Attributes of the variable, without reading its data where the backend allows. Defaults to
+    /// reading the variable.
+    async fn variable_attributes(&self, name: &VariableName) -> Option<Result<BitFlags<EFIVariableAttribute>, Self::ReadError>> {
+        self.read_variable(name).await
+            .map(|result| result.map(|variable| variable.attributes()))
+    }
+
+    /// Lists the variables along with the size of their data, like [EFIVars::variable_size] for
+    /// each of them. Defaults to querying the sizes one by one, backends that get them along with
+    /// the names, like efivarfs, override it. Variables that vanish in between are left out.
+    async fn enumerate_variable_sizes(&self) -> Result<Vec<(VariableName, Result<u64, Self::ReadError>)>, Self::ListError> {
         let names = self.enumerate_variables().await?;
 
-        let variables = stream::iter(names.into_iter())
-            .filter_map(|name| async move {
-                self.read_variable(&name).await
-                    .map(|result| result.map_err(|err| (name, err)))
+        let sizes = stream::iter(names)
+            .map(|name| async move {
+                let size = self.variable_size(&name).await?;
+                Some((name, size))
             })
-            .collect::<Vec<_>>().await;
+            .buffered(self.max_concurrency().max(1))
+            .filter_map(|result| async move { result })
+            .collect().await;
+
+        Ok(sizes)
+    }
+
+    /// Writes the variable, creating it if it doesn't already exist.
+    async fn write_variable(&self, variable: &EFIVariable) -> Result<(), Self::WriteError>;
+
+    /// Deletes the variable. Returns `false` if it didn't exist.
+    async fn delete_variable(&self, name: &VariableName) -> Result<bool, Self::WriteError>;
+
+    /// Appends the data to the variable by writing it with the
+    /// [EFIVariableAttribute::AppendWrite] attribute set, as used for `dbx` updates. The firmware
+    /// keeps the existing data and concatenates the new bytes, creating the variable if it doesn't
+    /// exist yet.
+    async fn append_variable(&self, name: &VariableName, attributes: BitFlags<EFIVariableAttribute>, data: &[u8]) -> Result<(), Self::WriteError> {
+        let variable = EFIVariable::new(name.clone(), attributes | EFIVariableAttribute::AppendWrite, data.to_vec());
+        self.write_variable(&variable).await
+    }
+
+    /// Reads the variables with overlapping requests. Results are in the same order as the names.
+    async fn read_variables(&self, names: &[VariableName]) -> Vec<Option<Result<EFIVariable, Self::ReadError>>> {
+        self.read_variables_with_progress(names, None).await
+    }
+
+    /// Like [EFIVars::read_variables], calling `progress` with the number of variables read so far
+    /// and the total after each one, for showing progress of slow backends like gvfs.
+    async fn read_variables_with_progress(&self, names: &[VariableName], progress: Option<&dyn Fn(usize, usize)>) -> Vec<Option<Result<EFIVariable, Self::ReadError>>> {
+        let total = names.len();
+        stream::iter(names)
+            .map(|name| self.read_variable(name))
+            .buffered(self.max_concurrency().max(1))
+            .enumerate()
+            .map(|(index, result)| {
+                if let Some(progress) = progress {
+                    progress(index + 1, total);
+                }
+                result
+            })
+            .collect().await
+    }
+
+    async fn list_variables(&self) -> Result<Vec<Result<EFIVariable, (VariableName, Self::ReadError)>>, Self::ListError> {
+        self.list_variables_with_progress(None).await
+    }
+
+    /// Like [EFIVars::list_variables], calling `progress` like
+    /// [EFIVars::read_variables_with_progress] once the variables are enumerated. Variables that
+    /// vanish in between count towards the total as well.
+    async fn list_variables_with_progress(&self, progress: Option<&dyn Fn(usize, usize)>) -> Result<Vec<Result<EFIVariable, (VariableName, Self::ReadError)>>, Self::ListError> {
+        let names = self.enumerate_variables().await?;
+
+        let variables = self.read_variables_with_progress(&names, progress).await
+            .into_iter()
+            .zip(names)
+            .filter_map(|(result, name)| Some(result?.map_err(|err| (name, err))))
+            .collect();
 
         Ok(variables)
     }
+
+    /// Like [EFIVars::list_variables], but separates the variables that could be read from the
+    /// ones that failed.
+    async fn list_variables_lossy(&self) -> Result<(Vec<EFIVariable>, Vec<(VariableName, Self::ReadError)>), Self::ListError> {
+        let mut variables = vec![];
+        let mut errors = vec![];
+        for result in self.list_variables().await? {
+            match result {
+                Ok(variable) => variables.push(variable),
+                Err(err) => errors.push(err),
+            }
+        }
+
+        Ok((variables, errors))
+    }
+
+    /// Total data size in bytes of all [EFIVariableAttribute::NonVolatile] variables, the ones
+    /// taking up space in the firmware's limited variable store. Volatile variables don't count.
+    /// Per-variable overhead of the store isn't included, and variables that fail to read are
+    /// skipped, so the real usage is somewhat higher.
+    ///
+    /// Built on [EFIVars::enumerate_variable_sizes] and [EFIVars::variable_attributes], so the data
+    /// of the variables is only read by backends that don't override those.
+    async fn nvram_usage(&self) -> Result<usize, Self::ListError> {
+        let sizes = self.enumerate_variable_sizes().await?;
+
+        let usage = stream::iter(sizes)
+            .map(|(name, size)| async move {
+                let non_volatile_size = async {
+                    let size = size?;
+                    // vanished since enumerating
+                    let Some(attributes) = self.variable_attributes(&name).await else { return Ok(0) };
+                    Ok::<_, Self::ReadError>(match attributes?.contains(EFIVariableAttribute::NonVolatile) {
+                        true => size,
+                        false => 0,
+                    })
+                };
+                non_volatile_size.await.unwrap_or_else(|err| {
+                    debug!("Skipping unreadable variable {:?} in NVRAM usage: {}", name, err);
+                    0
+                })
+            })
+            .buffered(self.max_concurrency().max(1))
+            .fold(0, |usage, size| async move { usage + size })
+            .await;
+
+        Ok(usage as usize)
+    }
+}
+
+/// Mounts efivarfs through the gvfs admin backend, which asks the user to authenticate. Where that
+/// fails, like on systems without gvfs, falls back to [EFIVarFS::new_direct] when the process may
+/// write to efivarfs itself.
+#[cfg(all(target_os = "linux", feature = "gvfs"))]
+pub async fn platform_backend() -> Result<EFIVarFS, MountError> {
+    with_direct_fallback(EFIVarFS::new_gvfs_admin().await, EFIVarFS::new_direct()).await
+}
+
+/// Only awaits `direct` if `gvfs` failed for a reason it might not fail for. When both fail, the
+/// gvfs error is returned as the one to show the user.
+#[cfg(all(target_os = "linux", feature = "gvfs"))]
+async fn with_direct_fallback<B>(gvfs: Result<B, MountError>, direct: impl Future<Output=Result<B, MountError>>) -> Result<B, MountError> {
+    match gvfs {
+        Err(MountError::NotAnEfiSystem) => Err(MountError::NotAnEfiSystem),
+        Err(err) => {
+            debug!("gvfs admin backend unavailable, trying efivarfs directly: {}", err);
+            direct.await.map_err(|direct_err| {
+                warn!("Failed to use efivarfs directly: {}", direct_err);
+                err
+            })
+        }
+        backend => backend,
+    }
 }
 
-#[cfg(target_os = "linux")]
-pub async fn platform_backend() -> Result<EFIVarFS, gio::glib::Error> {
-    EFIVarFS::new_gvfs_admin().await
+#[cfg(target_os = "windows")]
+pub async fn platform_backend() -> Result<windows::WindowsBackend, windows::WindowsError> {
+    windows::WindowsBackend::new()
+}
+
+#[cfg(target_os = "macos")]
+pub async fn platform_backend() -> Result<nvram::NvramBackend, std::convert::Infallible> {
+    Ok(nvram::NvramBackend::new())
+}
+
+#[cfg(all(test, target_os = "linux", feature = "gvfs"))]
+mod tests {
+    use std::time::Duration;
+    use futures::executor::block_on;
+    use futures::future::{pending, ready};
+    use crate::backend::efivarfs::MountError;
+    use crate::backend::with_direct_fallback;
+
+    #[test]
+    fn test_with_direct_fallback() {
+        // the direct backend is never awaited once gvfs worked or the system isn't EFI
+        assert_eq!(1, block_on(with_direct_fallback(Ok(1), pending())).unwrap());
+        let result = block_on(with_direct_fallback::<()>(Err(MountError::NotAnEfiSystem), pending()));
+        assert!(matches!(result, Err(MountError::NotAnEfiSystem)));
+
+        fn timeout<B>() -> Result<B, MountError> {
+            Err(MountError::MountTimeout(Duration::from_secs(1)))
+        }
+        assert_eq!(2, block_on(with_direct_fallback(timeout(), ready(Ok(2)))).unwrap());
+        let result = block_on(with_direct_fallback::<()>(timeout(), ready(Err(MountError::NotWritable))));
+        assert!(matches!(result, Err(MountError::MountTimeout(_))));
+    }
 }
@@ -0,0 +1,205 @@
+use std::io;
+use std::panic::resume_unwind;
+use std::process::Command;
+use std::str::FromStr;
+use async_trait::async_trait;
+use enumflags2::BitFlags;
+use log::warn;
+use thiserror::Error;
+use uuid::Uuid;
+use crate::backend::{BackendCapabilities, EFIVars};
+use crate::blocking::spawn_blocking;
+use crate::efivar::{EFIVariable, VariableName};
+
+/// Vendor of Apple's own variables, which `nvram` prints without a GUID prefix.
+const APPLE_VENDOR_GUID: &str = "7c436110-ab2a-4bbb-a880-fe41995c9f82";
+
+/// Accesses EFI variables on macOS through the `nvram` command.
+///
+/// `nvram` doesn't expose variable attributes, so variables are read without any. Writing is not
+/// supported yet.
+#[derive(Debug, Default)]
+pub struct NvramBackend;
+
+impl NvramBackend {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Runs `nvram -p`, printing every variable as `[<guid>:]<name>\t<value>`.
+    async fn print_variables(&self) -> Result<Vec<(VariableName, Vec<u8>)>, NvramError> {
+        Ok(parse_variables(&self.run(vec!["-p".to_owned()]).await?))
+    }
+
+    /// Runs `nvram [<guid>:]<name>`, printing only that variable like `nvram -p` does.
+    async fn print_variable(&self, name: &VariableName) -> Option<Result<Vec<u8>, NvramError>> {
+        let stdout = match self.run(vec![variable_argument(name)]).await {
+            Ok(stdout) => stdout,
+            Err(NvramError::CommandFailed(message)) if is_not_found(&message) => return None,
+            Err(err) => return Some(Err(err)),
+        };
+        let line = stdout.lines().next().unwrap_or_default();
+        Some(parse_line(line).map(|(_, data)| data))
+    }
+
+    async fn run(&self, args: Vec<String>) -> Result<String, NvramError> {
+        let output = spawn_blocking(move || Command::new("nvram").args(args).output()).await
+            .unwrap_or_else(|panic| resume_unwind(panic))?;
+        if !output.status.success() {
+            return Err(NvramError::CommandFailed(String::from_utf8_lossy(&output.stderr).trim().to_owned()));
+        }
+
+        String::from_utf8(output.stdout).map_err(|err| NvramError::InvalidOutput(err.to_string()))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum NvramError {
+    #[error("error running nvram: {0}")]
+    IoError(#[from] io::Error),
+    #[error("nvram failed: {0}")]
+    CommandFailed(String),
+    #[error("unexpected nvram output: {0}")]
+    InvalidOutput(String),
+}
+
+#[derive(Debug, Error)]
+pub enum NvramWriteError {
+    #[error("writing efi variables is not supported by the nvram backend")]
+    NotSupported,
+}
+
+#[async_trait(? Send)]
+impl EFIVars for NvramBackend {
+    type ListError = NvramError;
+    type ReadError = NvramError;
+    type WriteError = NvramWriteError;
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::READ_ONLY
+    }
+
+    async fn enumerate_variables(&self) -> Result<Vec<VariableName>, Self::ListError> {
+        Ok(self.print_variables().await?.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// Queries just the variable, instead of printing all of them for every read.
+    async fn read_variable(&self, name: &VariableName) -> Option<Result<EFIVariable, Self::ReadError>> {
+        let data = self.print_variable(name).await?;
+        Some(data.map(|data| EFIVariable::new(name.clone(), BitFlags::empty(), data)))
+    }
+
+    async fn write_variable(&self, _variable: &EFIVariable) -> Result<(), Self::WriteError> {
+        Err(NvramWriteError::NotSupported)
+    }
+
+    async fn delete_variable(&self, _name: &VariableName) -> Result<bool, Self::WriteError> {
+        Err(NvramWriteError::NotSupported)
+    }
+}
+
+/// Parses the output of `nvram -p`. Lines that don't parse are skipped with a warning, so a single
+/// odd variable doesn't fail the whole listing.
+fn parse_variables(stdout: &str) -> Vec<(VariableName, Vec<u8>)> {
+    stdout
+        .lines()
+        .filter_map(|line| match parse_line(line) {
+            Ok(variable) => Some(variable),
+            Err(err) => {
+                warn!("Skipping nvram variable: {}", err);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The variable as `nvram` takes it, Apple's own variables without a GUID prefix.
+fn variable_argument(name: &VariableName) -> String {
+    match name.vendor() == &Uuid::from_str(APPLE_VENDOR_GUID).unwrap() {
+        true => name.key().to_owned(),
+        false => format!("{}:{}", name.vendor(), name.key()),
+    }
+}
+
+/// Whether `nvram` failed because the variable doesn't exist, as in
+/// `nvram: Error getting variable - 'foo': (iokit/common) data was not found`.
+fn is_not_found(message: &str) -> bool {
+    message.contains("data was not found")
+}
+
+fn parse_line(line: &str) -> Result<(VariableName, Vec<u8>), NvramError> {
+    let invalid_output = || NvramError::InvalidOutput(line.to_owned());
+
+    let (name, value) = line.split_once('\t').ok_or_else(invalid_output)?;
+    let name = match name.split_once(':') {
+        Some((vendor, key)) => VariableName::new(key.to_owned(), Uuid::from_str(vendor).map_err(|_| invalid_output())?),
+        None => VariableName::new(name.to_owned(), Uuid::from_str(APPLE_VENDOR_GUID).unwrap()),
+    };
+
+    Ok((name, unescape_value(value).ok_or_else(invalid_output)?))
+}
+
+/// `nvram -p` prints printable ASCII as is and escapes every other byte as `%xx`.
+fn unescape_value(value: &str) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut chars = value.bytes();
+    while let Some(byte) = chars.next() {
+        if byte == b'%' {
+            let hex = [chars.next()?, chars.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use uuid::Uuid;
+    use crate::backend::nvram::{is_not_found, parse_line, parse_variables, unescape_value, variable_argument};
+    use crate::efivar::VariableName;
+
+    #[test]
+    fn test_unescape_value() {
+        assert_eq!(Some(vec![0x01, 0x00, b'a', b'b']), unescape_value("%01%00ab"));
+        assert_eq!(None, unescape_value("%0"));
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let (name, data) = parse_line("8be4df61-93ca-11d2-aa0d-00e098032b8c:BootOrder\t%01%00%00%00").unwrap();
+        assert_eq!("BootOrder", name.key());
+        assert_eq!(VariableName::global_vendor_new(String::new()).vendor(), name.vendor());
+        assert_eq!(vec![0x01, 0x00, 0x00, 0x00], data);
+
+        let (name, data) = parse_line("boot-args\t-v").unwrap();
+        assert_eq!("boot-args", name.key());
+        assert_eq!(&Uuid::from_str("7c436110-ab2a-4bbb-a880-fe41995c9f82").unwrap(), name.vendor());
+        assert_eq!(b"-v".to_vec(), data);
+    }
+
+    #[test]
+    fn test_parse_variables_skips_invalid() {
+        let stdout = "boot-args\t-v\nno tab here\nnot-a-guid:BootOrder\t%01%00\n8be4df61-93ca-11d2-aa0d-00e098032b8c:Timeout\t%05%00\n";
+
+        let keys = parse_variables(stdout).into_iter()
+            .map(|(name, _)| name.key().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["boot-args", "Timeout"], keys);
+    }
+
+    #[test]
+    fn test_variable_argument() {
+        assert_eq!("8be4df61-93ca-11d2-aa0d-00e098032b8c:BootOrder", variable_argument(&VariableName::global_vendor_new("BootOrder".to_owned())));
+        let apple = VariableName::new("boot-args".to_owned(), Uuid::from_str("7c436110-ab2a-4bbb-a880-fe41995c9f82").unwrap());
+        assert_eq!("boot-args", variable_argument(&apple));
+    }
+
+    #[test]
+    fn test_is_not_found() {
+        assert!(is_not_found("nvram: Error getting variable - 'Missing': (iokit/common) data was not found"));
+        assert!(!is_not_found("nvram: Error getting variable - 'BootOrder': (iokit/common) not permitted"));
+    }
+}
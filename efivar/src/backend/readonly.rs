@@ -0,0 +1,151 @@
+use std::cell::Cell;
+use std::error::Error;
+use async_trait::async_trait;
+use enumflags2::BitFlags;
+use thiserror::Error;
+use crate::backend::{BackendCapabilities, EFIVars};
+use crate::efivar::{EFIVariable, EFIVariableAttribute, VariableName};
+
+/// Wraps a backend, rejecting all writes while in read-only mode. Reads are always passed through.
+#[derive(Debug)]
+pub struct ReadOnlyBackend<E: EFIVars> {
+    inner: E,
+    read_only: Cell<bool>,
+}
+
+impl<E: EFIVars> ReadOnlyBackend<E> {
+    /// Wraps the backend, starting out in read-only mode.
+    pub fn new(inner: E) -> Self {
+        Self { inner, read_only: Cell::new(true) }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.get()
+    }
+
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.set(read_only);
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReadOnlyWriteError<W: Error> {
+    #[error("backend is in read-only mode")]
+    ReadOnly,
+    #[error("error writing efi variable: {0}")]
+    WriteError(#[source] W),
+}
+
+#[async_trait(? Send)]
+impl<E: EFIVars> EFIVars for ReadOnlyBackend<E> {
+    type ListError = E::ListError;
+    type ReadError = E::ReadError;
+    type WriteError = ReadOnlyWriteError<E::WriteError>;
+
+    fn capabilities(&self) -> BackendCapabilities {
+        if self.is_read_only() {
+            BackendCapabilities::READ_ONLY
+        } else {
+            self.inner.capabilities()
+        }
+    }
+
+    fn max_concurrency(&self) -> usize {
+        self.inner.max_concurrency()
+    }
+
+    async fn enumerate_variables(&self) -> Result<Vec<VariableName>, Self::ListError> {
+        self.inner.enumerate_variables().await
+    }
+
+    async fn read_variable(&self, name: &VariableName) -> Option<Result<EFIVariable, Self::ReadError>> {
+        self.inner.read_variable(name).await
+    }
+
+    async fn read_variables_with_progress(&self, names: &[VariableName], progress: Option<&dyn Fn(usize, usize)>) -> Vec<Option<Result<EFIVariable, Self::ReadError>>> {
+        self.inner.read_variables_with_progress(names, progress).await
+    }
+
+    async fn variable_exists(&self, name: &VariableName) -> bool {
+        self.inner.variable_exists(name).await
+    }
+
+    async fn variable_size(&self, name: &VariableName) -> Option<Result<u64, Self::ReadError>> {
+        self.inner.variable_size(name).await
+    }
+
+    async fn variable_attributes(&self, name: &VariableName) -> Option<Result<BitFlags<EFIVariableAttribute>, Self::ReadError>> {
+        self.inner.variable_attributes(name).await
+    }
+
+    async fn enumerate_variable_sizes(&self) -> Result<Vec<(VariableName, Result<u64, Self::ReadError>)>, Self::ListError> {
+        self.inner.enumerate_variable_sizes().await
+    }
+
+    async fn write_variable(&self, variable: &EFIVariable) -> Result<(), Self::WriteError> {
+        if self.is_read_only() {
+            return Err(ReadOnlyWriteError::ReadOnly);
+        }
+        self.inner.write_variable(variable).await.map_err(ReadOnlyWriteError::WriteError)
+    }
+
+    async fn delete_variable(&self, name: &VariableName) -> Result<bool, Self::WriteError> {
+        if self.is_read_only() {
+            return Err(ReadOnlyWriteError::ReadOnly);
+        }
+        self.inner.delete_variable(name).await.map_err(ReadOnlyWriteError::WriteError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use enumflags2::BitFlags;
+    use futures::executor::block_on;
+    use crate::backend::{BackendCapabilities, EFIVars};
+    use crate::backend::mock::MemoryEFIVars;
+    use crate::backend::readonly::{ReadOnlyBackend, ReadOnlyWriteError};
+    use crate::efivar::{EFIVariable, VariableName};
+
+    fn variable(data: Vec<u8>) -> EFIVariable {
+        EFIVariable::new(VariableName::global_vendor_new("Timeout".to_owned()), BitFlags::empty(), data)
+    }
+
+    #[test]
+    fn test_read_only_rejects_writes() {
+        let efivars = ReadOnlyBackend::new(MemoryEFIVars::with_variables([variable(vec![0x05, 0x00])]));
+
+        let result = block_on(efivars.write_variable(&variable(vec![0x0A, 0x00])));
+        assert!(matches!(result, Err(ReadOnlyWriteError::ReadOnly)));
+
+        let read = block_on(efivars.read_variable(variable(vec![]).name())).unwrap().unwrap();
+        assert_eq!(&[0x05, 0x00], read.data());
+    }
+
+    #[test]
+    fn test_unlocked_writes_pass_through() {
+        let efivars = ReadOnlyBackend::new(MemoryEFIVars::new());
+        efivars.set_read_only(false);
+
+        block_on(efivars.write_variable(&variable(vec![0x0A, 0x00]))).unwrap();
+
+        let read = block_on(efivars.inner().read_variable(variable(vec![]).name())).unwrap().unwrap();
+        assert_eq!(&[0x0A, 0x00], read.data());
+    }
+
+    #[test]
+    fn test_capabilities_follow_read_only() {
+        let efivars = ReadOnlyBackend::new(MemoryEFIVars::new());
+        assert_eq!(BackendCapabilities::READ_ONLY, efivars.capabilities());
+
+        efivars.set_read_only(false);
+        assert_eq!(BackendCapabilities::READ_WRITE, efivars.capabilities());
+    }
+}
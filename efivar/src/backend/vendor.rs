@@ -0,0 +1,137 @@
+use async_trait::async_trait;
+use enumflags2::BitFlags;
+use uuid::Uuid;
+use crate::backend::{BackendCapabilities, EFIVars};
+use crate::efivar::{EFI_GLOBAL_VARIABLE_GUID, EFIVariable, EFIVariableAttribute, VariableName};
+
+/// Wraps a backend, redirecting all global vendor variables to another vendor. For firmware that
+/// stores `BootOrder` and `Boot####` under the wrong GUID, and for tests. Every helper working on
+/// the global namespace, like [ListBootEntriesExt](crate::efiboot::ListBootEntriesExt), then
+/// works on the vendor instead. Variables actually stored under the global vendor are hidden.
+#[derive(Debug)]
+pub struct VendorOverride<E: EFIVars> {
+    inner: E,
+    vendor: Uuid,
+}
+
+impl<E: EFIVars> VendorOverride<E> {
+    pub fn new(inner: E, vendor: Uuid) -> Self {
+        Self { inner, vendor }
+    }
+
+    pub fn vendor(&self) -> &Uuid {
+        &self.vendor
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    /// The name in the wrapped backend.
+    fn to_inner(&self, name: &VariableName) -> VariableName {
+        if name.vendor() == &EFI_GLOBAL_VARIABLE_GUID {
+            VariableName::new(name.key().to_owned(), self.vendor)
+        } else {
+            name.clone()
+        }
+    }
+
+    /// The name as seen through the override, `None` for hidden global vendor variables.
+    fn to_outer(&self, name: VariableName) -> Option<VariableName> {
+        if name.vendor() == &self.vendor {
+            Some(VariableName::global_vendor_new(name.key().to_owned()))
+        } else if name.vendor() == &EFI_GLOBAL_VARIABLE_GUID {
+            None
+        } else {
+            Some(name)
+        }
+    }
+}
+
+#[async_trait(? Send)]
+impl<E: EFIVars> EFIVars for VendorOverride<E> {
+    type ListError = E::ListError;
+    type ReadError = E::ReadError;
+    type WriteError = E::WriteError;
+
+    fn capabilities(&self) -> BackendCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_concurrency(&self) -> usize {
+        self.inner.max_concurrency()
+    }
+
+    async fn enumerate_variables(&self) -> Result<Vec<VariableName>, Self::ListError> {
+        Ok(self.inner.enumerate_variables().await?
+            .into_iter()
+            .filter_map(|name| self.to_outer(name))
+            .collect())
+    }
+
+    async fn read_variable(&self, name: &VariableName) -> Option<Result<EFIVariable, Self::ReadError>> {
+        let variable = self.inner.read_variable(&self.to_inner(name)).await?;
+        Some(variable.map(|variable| EFIVariable::new(name.clone(), variable.attributes(), variable.data().to_vec())))
+    }
+
+    async fn variable_exists(&self, name: &VariableName) -> bool {
+        self.inner.variable_exists(&self.to_inner(name)).await
+    }
+
+    async fn variable_size(&self, name: &VariableName) -> Option<Result<u64, Self::ReadError>> {
+        self.inner.variable_size(&self.to_inner(name)).await
+    }
+
+    async fn variable_attributes(&self, name: &VariableName) -> Option<Result<BitFlags<EFIVariableAttribute>, Self::ReadError>> {
+        self.inner.variable_attributes(&self.to_inner(name)).await
+    }
+
+    async fn write_variable(&self, variable: &EFIVariable) -> Result<(), Self::WriteError> {
+        let variable = EFIVariable::new(self.to_inner(variable.name()), variable.attributes(), variable.data().to_vec());
+        self.inner.write_variable(&variable).await
+    }
+
+    async fn delete_variable(&self, name: &VariableName) -> Result<bool, Self::WriteError> {
+        self.inner.delete_variable(&self.to_inner(name)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use enumflags2::BitFlags;
+    use futures::executor::block_on;
+    use uuid::{Uuid, uuid};
+    use crate::backend::EFIVars;
+    use crate::backend::mock::MemoryEFIVars;
+    use crate::backend::vendor::VendorOverride;
+    use crate::efiboot::{BOOT_VARIABLE_ATTRIBUTES, boot_entry_name, BootEntry, ListBootEntriesExt};
+    use crate::efiloadoption::EFILoadOption;
+    use crate::efivar::{EFIVariable, VariableName};
+    use crate::fixtures::{test_variable, test_variable_as};
+
+    const VENDOR: Uuid = uuid!("8be4df61-93ca-11d2-aa0d-00e098032b8d");
+
+    #[test]
+    fn test_boot_entries_under_vendor() {
+        let boot_entry = test_variable(boot_entry_name(1));
+        let efivars = VendorOverride::new(MemoryEFIVars::with_variables([
+            test_variable_as(boot_entry.name(), VariableName::new("Boot0001".to_owned(), VENDOR)),
+            EFIVariable::new(VariableName::new("BootOrder".to_owned(), VENDOR), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00]),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x02, 0x00]),
+        ]), VENDOR);
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(vec![1], entries.order().iter().copied().collect::<Vec<_>>());
+        let load_option = EFILoadOption::parse(&mut Cursor::new(boot_entry.data())).unwrap();
+        assert_eq!(Some(&load_option), entries.iter().next().map(BootEntry::load_option));
+
+        block_on(efivars.write_variable(&EFIVariable::new(VariableName::global_vendor_new("Timeout".to_owned()), BitFlags::empty(), vec![0x05, 0x00]))).unwrap();
+        let timeout = block_on(efivars.inner().read_variable(&VariableName::new("Timeout".to_owned(), VENDOR))).unwrap().unwrap();
+        assert_eq!(&[0x05, 0x00], timeout.data());
+    }
+}
@@ -0,0 +1,254 @@
+use std::ffi::c_void;
+use std::io;
+use std::ptr;
+use async_trait::async_trait;
+use enumflags2::BitFlags;
+use thiserror::Error;
+use uuid::Uuid;
+use windows_sys::Win32::Foundation::{CloseHandle, ERROR_ENVVAR_NOT_FOUND, ERROR_INSUFFICIENT_BUFFER, ERROR_NOT_ALL_ASSIGNED, ERROR_PRIVILEGE_NOT_HELD, GetLastError, HANDLE, LUID};
+use windows_sys::Win32::Security::{AdjustTokenPrivileges, LookupPrivilegeValueW, LUID_AND_ATTRIBUTES, SE_PRIVILEGE_ENABLED, SE_SYSTEM_ENVIRONMENT_NAME, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES, TOKEN_QUERY};
+use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+use windows_sys::Win32::System::WindowsProgramming::{GetFirmwareEnvironmentVariableExW, SetFirmwareEnvironmentVariableExW};
+use crate::backend::{BackendCapabilities, EFIVars};
+use crate::efivar::{EFIVariable, encode_utf16_nul, VariableName};
+
+/// `SystemEnvironmentNameInformation`, enumerating variable names without their values.
+const SYSTEM_ENVIRONMENT_NAME_INFORMATION: u32 = 1;
+const STATUS_BUFFER_TOO_SMALL: i32 = 0xC0000023u32 as i32;
+const STATUS_NOT_IMPLEMENTED: i32 = 0xC0000002u32 as i32;
+const STATUS_NOT_SUPPORTED: i32 = 0xC00000BBu32 as i32;
+/// Size of the `NextEntryOffset` and `VendorGuid` fields preceding the name in `VARIABLE_NAME`.
+const VARIABLE_NAME_HEADER_SIZE: usize = 4 + 16;
+/// Upper bound for a single variable, well above what firmware accepts in practice.
+const MAX_VARIABLE_SIZE: usize = 1024 * 1024;
+
+#[link(name = "ntdll")]
+extern "system" {
+    fn NtEnumerateSystemEnvironmentValuesEx(information_class: u32, buffer: *mut c_void, buffer_length: *mut u32) -> i32;
+}
+
+/// Accesses EFI variables through the Windows firmware environment variable APIs.
+pub struct WindowsBackend {
+    _private: (),
+}
+
+impl WindowsBackend {
+    /// Enables `SeSystemEnvironmentPrivilege` for the process, which every firmware environment
+    /// variable call requires. Fails with [WindowsError::PrivilegeNotHeld] unless running as
+    /// administrator.
+    pub fn new() -> Result<Self, WindowsError> {
+        enable_system_environment_privilege()?;
+        Ok(Self { _private: () })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WindowsError {
+    #[error("SeSystemEnvironmentPrivilege is required to access efi variables, try running as administrator")]
+    PrivilegeNotHeld,
+    #[error("windows produced an error while accessing efi variables: {0}")]
+    OsError(#[source] io::Error),
+    #[error("NtEnumerateSystemEnvironmentValuesEx failed with status {0:#010x}")]
+    NtStatus(i32),
+    /// Variables can't be listed, as on systems booted in legacy BIOS mode and some older Windows
+    /// versions. Reading and writing known variables by name may still work.
+    #[error("enumerating efi variables is not supported on this system")]
+    EnumerationUnsupported,
+    #[error("efi variable is larger than {MAX_VARIABLE_SIZE} bytes")]
+    VariableTooLarge,
+    #[error("malformed variable name list returned by NtEnumerateSystemEnvironmentValuesEx")]
+    InvalidNameList,
+}
+
+impl WindowsError {
+    fn last_os_error() -> Self {
+        match unsafe { GetLastError() } {
+            ERROR_PRIVILEGE_NOT_HELD | ERROR_NOT_ALL_ASSIGNED => WindowsError::PrivilegeNotHeld,
+            code => WindowsError::OsError(io::Error::from_raw_os_error(code as i32)),
+        }
+    }
+}
+
+#[async_trait(? Send)]
+impl EFIVars for WindowsBackend {
+    type ListError = WindowsError;
+    type ReadError = WindowsError;
+    type WriteError = WindowsError;
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::READ_WRITE
+    }
+
+    async fn enumerate_variables(&self) -> Result<Vec<VariableName>, Self::ListError> {
+        let mut buffer = vec![0u8; 4096];
+        loop {
+            let mut length = buffer.len() as u32;
+            let status = unsafe { NtEnumerateSystemEnvironmentValuesEx(SYSTEM_ENVIRONMENT_NAME_INFORMATION, buffer.as_mut_ptr().cast(), &mut length) };
+            match status {
+                0 => return parse_variable_names(&buffer[..length as usize]),
+                STATUS_BUFFER_TOO_SMALL if (length as usize) > buffer.len() => buffer.resize(length as usize, 0),
+                status => return Err(enumeration_error(status)),
+            }
+        }
+    }
+
+    async fn read_variable(&self, name: &VariableName) -> Option<Result<EFIVariable, Self::ReadError>> {
+        let key = to_wide(name.key());
+        let vendor = to_wide(&guid_string(name.vendor()));
+
+        let mut buffer = vec![0u8; 1024];
+        loop {
+            let mut attributes = 0u32;
+            let size = unsafe { GetFirmwareEnvironmentVariableExW(key.as_ptr(), vendor.as_ptr(), buffer.as_mut_ptr().cast(), buffer.len() as u32, &mut attributes) };
+            if size != 0 {
+                buffer.truncate(size as usize);
+                return Some(Ok(EFIVariable::new(name.clone(), BitFlags::from_bits_truncate(attributes), buffer)));
+            }
+
+            match unsafe { GetLastError() } {
+                ERROR_ENVVAR_NOT_FOUND => return None,
+                ERROR_INSUFFICIENT_BUFFER if buffer.len() < MAX_VARIABLE_SIZE => buffer.resize(buffer.len() * 2, 0),
+                ERROR_INSUFFICIENT_BUFFER => return Some(Err(WindowsError::VariableTooLarge)),
+                _ => return Some(Err(WindowsError::last_os_error())),
+            }
+        }
+    }
+
+    async fn write_variable(&self, variable: &EFIVariable) -> Result<(), Self::WriteError> {
+        let key = to_wide(variable.name().key());
+        let vendor = to_wide(&guid_string(variable.name().vendor()));
+
+        let data = variable.data();
+        let result = unsafe { SetFirmwareEnvironmentVariableExW(key.as_ptr(), vendor.as_ptr(), data.as_ptr().cast(), data.len() as u32, variable.attributes().bits()) };
+        if result == 0 {
+            return Err(WindowsError::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    async fn delete_variable(&self, name: &VariableName) -> Result<bool, Self::WriteError> {
+        let key = to_wide(name.key());
+        let vendor = to_wide(&guid_string(name.vendor()));
+
+        // writing zero bytes deletes the variable
+        let result = unsafe { SetFirmwareEnvironmentVariableExW(key.as_ptr(), vendor.as_ptr(), ptr::null(), 0, 0) };
+        if result == 0 {
+            return match unsafe { GetLastError() } {
+                ERROR_ENVVAR_NOT_FOUND => Ok(false),
+                _ => Err(WindowsError::last_os_error()),
+            };
+        }
+
+        Ok(true)
+    }
+}
+
+fn enable_system_environment_privilege() -> Result<(), WindowsError> {
+    unsafe {
+        let mut token: HANDLE = 0;
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY, &mut token) == 0 {
+            return Err(WindowsError::last_os_error());
+        }
+
+        let mut luid = LUID { LowPart: 0, HighPart: 0 };
+        let result = if LookupPrivilegeValueW(ptr::null(), SE_SYSTEM_ENVIRONMENT_NAME, &mut luid) == 0 {
+            Err(WindowsError::last_os_error())
+        } else {
+            let privileges = TOKEN_PRIVILEGES {
+                PrivilegeCount: 1,
+                Privileges: [LUID_AND_ATTRIBUTES { Luid: luid, Attributes: SE_PRIVILEGE_ENABLED }],
+            };
+            // succeeds with ERROR_NOT_ALL_ASSIGNED when the privilege isn't held at all
+            if AdjustTokenPrivileges(token, 0, &privileges, 0, ptr::null_mut(), ptr::null_mut()) == 0 || GetLastError() == ERROR_NOT_ALL_ASSIGNED {
+                Err(WindowsError::last_os_error())
+            } else {
+                Ok(())
+            }
+        };
+
+        CloseHandle(token);
+        result
+    }
+}
+
+fn enumeration_error(status: i32) -> WindowsError {
+    match status {
+        STATUS_NOT_IMPLEMENTED | STATUS_NOT_SUPPORTED => WindowsError::EnumerationUnsupported,
+        status => WindowsError::NtStatus(status),
+    }
+}
+
+/// Parses the `VARIABLE_NAME` entries returned by `NtEnumerateSystemEnvironmentValuesEx`, each
+/// consisting of the offset to the next entry, the vendor GUID and a NUL-terminated UTF-16 name.
+fn parse_variable_names(buffer: &[u8]) -> Result<Vec<VariableName>, WindowsError> {
+    let mut names = vec![];
+    let mut offset = 0;
+    while offset < buffer.len() {
+        let entry = &buffer[offset..];
+        if entry.len() < VARIABLE_NAME_HEADER_SIZE {
+            return Err(WindowsError::InvalidNameList);
+        }
+        let next_entry_offset = u32::from_le_bytes(entry[0..4].try_into().unwrap()) as usize;
+        let vendor = Uuid::from_bytes_le(entry[4..20].try_into().unwrap());
+
+        let name_end = if next_entry_offset == 0 { entry.len() } else { next_entry_offset.min(entry.len()) };
+        let key = entry[VARIABLE_NAME_HEADER_SIZE..name_end.max(VARIABLE_NAME_HEADER_SIZE)].chunks_exact(2)
+            .map(|char| u16::from_le_bytes([char[0], char[1]]))
+            .take_while(|char| *char != 0x0000)
+            .collect::<Vec<_>>();
+        let key = String::from_utf16(&key).map_err(|_| WindowsError::InvalidNameList)?;
+        names.push(VariableName::new(key, vendor));
+
+        if next_entry_offset == 0 {
+            break;
+        }
+        offset += next_entry_offset;
+    }
+
+    Ok(names)
+}
+
+fn guid_string(vendor: &Uuid) -> String {
+    format!("{{{}}}", vendor.hyphenated())
+}
+
+fn to_wide(value: &str) -> Vec<u16> {
+    encode_utf16_nul(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use uuid::Uuid;
+    use crate::backend::windows::{enumeration_error, guid_string, parse_variable_names, STATUS_BUFFER_TOO_SMALL, STATUS_NOT_SUPPORTED, WindowsError};
+
+    #[test]
+    fn test_parse_variable_names() {
+        let vendor = Uuid::from_str("8be4df61-93ca-11d2-aa0d-00e098032b8c").unwrap();
+        let entry = |key: &str, last: bool| {
+            let name = key.encode_utf16().chain([0x0000]).flat_map(u16::to_le_bytes).collect::<Vec<_>>();
+            let size = (4 + 16 + name.len()) as u32;
+            [&(if last { 0 } else { size }).to_le_bytes()[..], &vendor.to_bytes_le(), &name].concat()
+        };
+        let buffer = [entry("BootOrder", false), entry("Boot0001", true)].concat();
+
+        let names = parse_variable_names(&buffer).unwrap();
+
+        assert_eq!(vec!["BootOrder", "Boot0001"], names.iter().map(|name| name.key()).collect::<Vec<_>>());
+        assert!(names.iter().all(|name| name.vendor() == &vendor));
+    }
+
+    #[test]
+    fn test_enumeration_error() {
+        assert!(matches!(enumeration_error(STATUS_NOT_SUPPORTED), WindowsError::EnumerationUnsupported));
+        assert!(matches!(enumeration_error(STATUS_BUFFER_TOO_SMALL), WindowsError::NtStatus(STATUS_BUFFER_TOO_SMALL)));
+    }
+
+    #[test]
+    fn test_guid_string() {
+        let vendor = Uuid::from_str("8be4df61-93ca-11d2-aa0d-00e098032b8c").unwrap();
+
+        assert_eq!("{8be4df61-93ca-11d2-aa0d-00e098032b8c}", guid_string(&vendor));
+    }
+}
@@ -0,0 +1,35 @@
+use std::thread;
+
+/// Runs blocking work off the calling thread, resolving to its result or the panic payload. Uses
+/// gio's thread pool when gio is available, and a dedicated thread otherwise.
+#[cfg(feature = "gvfs")]
+pub(crate) async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> thread::Result<T> {
+    gio::spawn_blocking(f).await
+}
+
+/// Runs blocking work off the calling thread, resolving to its result or the panic payload. Uses
+/// gio's thread pool when gio is available, and a dedicated thread otherwise.
+#[cfg(not(feature = "gvfs"))]
+pub(crate) async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> thread::Result<T> {
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+    use futures::channel::oneshot;
+
+    let (sender, receiver) = oneshot::channel();
+    thread::spawn(move || {
+        // the receiver is gone if the caller stopped waiting, nobody is left to tell
+        let _ = sender.send(catch_unwind(AssertUnwindSafe(f)));
+    });
+    receiver.await.expect("blocking thread exited without sending its result")
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use crate::blocking::spawn_blocking;
+
+    #[test]
+    fn test_spawn_blocking() {
+        assert_eq!(4, block_on(spawn_blocking(|| 2 + 2)).unwrap());
+        assert!(block_on(spawn_blocking(|| panic!("expected"))).is_err());
+    }
+}
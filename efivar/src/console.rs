@@ -0,0 +1,137 @@
+use std::io::Cursor;
+use async_trait::async_trait;
+use thiserror::Error;
+use crate::backend::EFIVars;
+use crate::efidevicepath::{DevicePathProtocolParseError, EFIDevicePathProtocol};
+use crate::efivar::{VariableName, WellKnownVariable};
+
+/// The device path instances of a console variable, one per console device.
+pub type ConsoleDevicePaths = Vec<Vec<EFIDevicePathProtocol>>;
+
+/// The consoles currently selected by the firmware, or all the consoles it knows about, see
+/// [ConsoleExt::console_paths] and [ConsoleExt::console_devices]. Each is `None` if the variable
+/// is missing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsolePaths {
+    con_in: Option<ConsoleDevicePaths>,
+    con_out: Option<ConsoleDevicePaths>,
+    err_out: Option<ConsoleDevicePaths>,
+}
+
+impl ConsolePaths {
+    pub fn con_in(&self) -> Option<&ConsoleDevicePaths> {
+        self.con_in.as_ref()
+    }
+
+    pub fn con_out(&self) -> Option<&ConsoleDevicePaths> {
+        self.con_out.as_ref()
+    }
+
+    pub fn err_out(&self) -> Option<&ConsoleDevicePaths> {
+        self.err_out.as_ref()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReadConsolePathsError<E: EFIVars> {
+    #[error("error reading {name} variable: {source}")]
+    ReadVariableError { name: String, #[source] source: E::ReadError },
+    #[error("error parsing {name} variable: {source}")]
+    ParseError { name: String, #[source] source: DevicePathProtocolParseError },
+}
+
+#[async_trait(? Send)]
+pub trait ConsoleExt: EFIVars + Sized {
+    /// Reads and parses a global console variable, such as `ConOut`.
+    async fn read_console_device_paths(&self, key: &str) -> Option<Result<ConsoleDevicePaths, ReadConsolePathsError<Self>>>;
+
+    /// The consoles in use, from `ConIn`, `ConOut` and `ErrOut`.
+    async fn console_paths(&self) -> Result<ConsolePaths, ReadConsolePathsError<Self>>;
+
+    /// Every console device the firmware found, whether in use or not, from `ConInDev`,
+    /// `ConOutDev` and `ErrOutDev`.
+    async fn console_devices(&self) -> Result<ConsolePaths, ReadConsolePathsError<Self>>;
+}
+
+#[async_trait(? Send)]
+impl<E> ConsoleExt for E
+    where E: EFIVars {
+    async fn read_console_device_paths(&self, key: &str) -> Option<Result<ConsoleDevicePaths, ReadConsolePathsError<E>>> {
+        use ReadConsolePathsError::*;
+
+        Some(match self.read_variable(&VariableName::global_vendor_new(key.to_owned())).await? {
+            Ok(variable) => EFIDevicePathProtocol::parse_instances(&mut Cursor::new(variable.data()))
+                .map_err(|source| ParseError { name: key.to_owned(), source }),
+            Err(source) => Err(ReadVariableError { name: key.to_owned(), source }),
+        })
+    }
+
+    async fn console_paths(&self) -> Result<ConsolePaths, ReadConsolePathsError<E>> {
+        Ok(ConsolePaths {
+            con_in: self.read_console_device_paths(WellKnownVariable::ConIn.key()).await.transpose()?,
+            con_out: self.read_console_device_paths(WellKnownVariable::ConOut.key()).await.transpose()?,
+            err_out: self.read_console_device_paths(WellKnownVariable::ErrOut.key()).await.transpose()?,
+        })
+    }
+
+    async fn console_devices(&self) -> Result<ConsolePaths, ReadConsolePathsError<E>> {
+        Ok(ConsolePaths {
+            con_in: self.read_console_device_paths(WellKnownVariable::ConInDev.key()).await.transpose()?,
+            con_out: self.read_console_device_paths(WellKnownVariable::ConOutDev.key()).await.transpose()?,
+            err_out: self.read_console_device_paths(WellKnownVariable::ErrOutDev.key()).await.transpose()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use crate::backend::mock::MemoryEFIVars;
+    use crate::console::ConsoleExt;
+    use crate::efidevicepath::{AcpiDevicePath, EFIDevicePathProtocol, HardwareDevicePath, MessagingDevicePath};
+    use crate::efivar::VariableName;
+    use crate::fixtures::test_variable;
+
+    #[test]
+    fn test_console_paths() {
+        let efivars = MemoryEFIVars::with_variables([
+            test_variable(VariableName::global_vendor_new("ConOut".to_owned())),
+        ]);
+
+        let paths = block_on(efivars.console_paths()).unwrap();
+
+        let root = EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Acpi { hid: 0x0A0341D0, uid: 0 });
+        let expected = vec![
+            vec![root.clone(), EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Adr(vec![0x80010100]))],
+            vec![root, EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Adr(vec![0x80010200]))],
+        ];
+        assert_eq!(Some(&expected), paths.con_out());
+        assert_eq!(None, paths.con_in());
+        assert_eq!(None, paths.err_out());
+    }
+
+    #[test]
+    fn test_console_devices() {
+        let efivars = MemoryEFIVars::with_variables([
+            test_variable(VariableName::global_vendor_new("ConOutDev".to_owned())),
+        ]);
+
+        let devices = block_on(efivars.console_devices()).unwrap();
+
+        let root = EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Acpi { hid: 0x0A0341D0, uid: 0 });
+        let pci = |device| EFIDevicePathProtocol::HardwareDevicePath(HardwareDevicePath::Pci { function: 0, device });
+        let con_out = devices.con_out().unwrap();
+        assert_eq!(2, con_out.len());
+        assert_eq!(vec![root.clone(), pci(0x02), EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Adr(vec![0x80010100]))], con_out[0]);
+        assert_eq!(vec![
+            root,
+            pci(0x1F),
+            EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Acpi { hid: 0x050141D0, uid: 0 }),
+            EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::Uart { reserved: 0, baud_rate: 115200, data_bits: 8, parity: 1, stop_bits: 1 }),
+        ], con_out[1][..4]);
+        // the VT100 terminal type is a vendor node, kept as is
+        assert!(matches!(&con_out[1][4], EFIDevicePathProtocol::Unknown(vendor) if (vendor.typ(), vendor.sub_type(), vendor.data().len()) == (0x03, 0x0A, 16)));
+        assert_eq!(None, devices.con_in());
+        assert!(block_on(efivars.console_paths()).unwrap().con_out().is_none());
+    }
+}
@@ -0,0 +1,20 @@
+/// CRC32 (IEEE 802.3, reflected) as computed by the EFI boot services `CalculateCrc32`, used for
+/// the GPT checksums as well as the `BootOptionCrc` of `Key####` variables.
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    const POLYNOMIAL: u32 = 0xEDB88320;
+
+    !bytes.iter().fold(!0u32, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| if crc & 1 == 1 { (crc >> 1) ^ POLYNOMIAL } else { crc >> 1 })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crc32::crc32;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(0xCBF43926, crc32(b"123456789"));
+        assert_eq!(0, crc32(b""));
+    }
+}
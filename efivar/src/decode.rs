@@ -0,0 +1,115 @@
+use std::io::Cursor;
+use async_trait::async_trait;
+use log::debug;
+use crate::backend::EFIVars;
+use crate::efiboot::{boot_entry_id, BootEntry, BootOrder};
+use crate::efiloadoption::EFILoadOption;
+use crate::efivar::{EFIVariable, VariableName, WellKnownVariable};
+
+/// A variable decoded according to its name, for displaying variables generically.
+#[derive(Clone, Debug)]
+pub enum DecodedVariable {
+    /// A `Boot####` variable.
+    BootOption(BootEntry),
+    BootOrder(BootOrder),
+    /// `BootNext` or `BootCurrent`, holding a single boot entry id.
+    BootTarget(WellKnownVariable, u16),
+    /// `Timeout`, in seconds.
+    Timeout(u16),
+    /// `SecureBoot` or `SetupMode`.
+    Flag(WellKnownVariable, bool),
+    /// A variable that isn't recognized, or failed to decode.
+    Raw(EFIVariable),
+}
+
+impl DecodedVariable {
+    /// Decodes the variable if its name is recognized, falling back to [DecodedVariable::Raw] if
+    /// it isn't or the data is malformed.
+    pub fn decode(variable: EFIVariable) -> Self {
+        match Self::try_decode(&variable) {
+            Some(decoded) => decoded,
+            None => DecodedVariable::Raw(variable),
+        }
+    }
+
+    fn try_decode(variable: &EFIVariable) -> Option<Self> {
+        let data = variable.data();
+
+        if let Some(id) = boot_entry_id(variable.name()) {
+            return match EFILoadOption::parse(&mut Cursor::new(data)) {
+                Ok(load_option) => Some(DecodedVariable::BootOption(BootEntry::new(id, load_option))),
+                Err(err) => {
+                    debug!("Failed to decode {}: {}", variable.name(), err);
+                    None
+                }
+            };
+        }
+
+        let u16_data = || data.try_into().ok().map(u16::from_le_bytes);
+        match WellKnownVariable::from_name(variable.name())? {
+            WellKnownVariable::BootOrder => BootOrder::from_bytes(data).ok().map(DecodedVariable::BootOrder),
+            known @ (WellKnownVariable::BootNext | WellKnownVariable::BootCurrent) => u16_data().map(|id| DecodedVariable::BootTarget(known, id)),
+            WellKnownVariable::Timeout => u16_data().map(DecodedVariable::Timeout),
+            known @ (WellKnownVariable::SecureBoot | WellKnownVariable::SetupMode) => match data {
+                [value] => Some(DecodedVariable::Flag(known, *value == 1)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+#[async_trait(? Send)]
+pub trait DecodeVariableExt: EFIVars {
+    /// Reads the variable and decodes it, see [DecodedVariable::decode].
+    async fn read_variable_decoded(&self, name: &VariableName) -> Option<Result<DecodedVariable, Self::ReadError>>;
+}
+
+#[async_trait(? Send)]
+impl<E> DecodeVariableExt for E
+    where E: EFIVars {
+    async fn read_variable_decoded(&self, name: &VariableName) -> Option<Result<DecodedVariable, Self::ReadError>> {
+        Some(self.read_variable(name).await?.map(DecodedVariable::decode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use enumflags2::BitFlags;
+    use futures::executor::block_on;
+    use crate::backend::mock::MemoryEFIVars;
+    use crate::decode::{DecodedVariable, DecodeVariableExt};
+    use crate::efiboot::{BOOT_VARIABLE_ATTRIBUTES, boot_entry_name};
+    use crate::efivar::{EFIVariable, VariableName};
+    use crate::fixtures::test_variable;
+
+    #[test]
+    fn test_read_variable_decoded() {
+        let efivars = MemoryEFIVars::with_variables([
+            test_variable(boot_entry_name(1)),
+            EFIVariable::new(VariableName::global_vendor_new("Timeout".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x05, 0x00]),
+            EFIVariable::new(VariableName::global_vendor_new("Unknown".to_owned()), BitFlags::empty(), vec![0x01]),
+        ]);
+
+        match block_on(efivars.read_variable_decoded(&boot_entry_name(1))) {
+            Some(Ok(DecodedVariable::BootOption(entry))) => {
+                assert_eq!(1, entry.id());
+                assert_eq!("Arch Linux", entry.description());
+            }
+            decoded => panic!("unexpected decoded variable: {decoded:?}"),
+        }
+        assert!(matches!(block_on(efivars.read_variable_decoded(&VariableName::global_vendor_new("Timeout".to_owned()))), Some(Ok(DecodedVariable::Timeout(5)))));
+        match block_on(efivars.read_variable_decoded(&VariableName::global_vendor_new("Unknown".to_owned()))) {
+            Some(Ok(DecodedVariable::Raw(variable))) => assert_eq!(&[0x01], variable.data()),
+            decoded => panic!("unexpected decoded variable: {decoded:?}"),
+        }
+        assert!(block_on(efivars.read_variable_decoded(&VariableName::global_vendor_new("Missing".to_owned()))).is_none());
+    }
+
+    #[test]
+    fn test_decode_malformed_falls_back_to_raw() {
+        let variable = EFIVariable::new(boot_entry_name(2), BOOT_VARIABLE_ATTRIBUTES, vec![0x01]);
+
+        assert!(matches!(DecodedVariable::decode(variable), DecodedVariable::Raw(_)));
+    }
+}
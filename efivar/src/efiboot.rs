@@ -1,37 +1,162 @@
-use std::collections::HashMap;
-use std::fmt::{Debug, Formatter};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display, Formatter};
+use std::io;
 use std::io::Cursor;
+use std::str::FromStr;
 use std::sync::OnceLock;
 use async_trait::async_trait;
-use bytemuck::cast_slice;
+use enumflags2::{BitFlags, make_bitflags};
 use futures::{stream, StreamExt, TryStreamExt};
-use log::debug;
+use futures::stream::LocalBoxStream;
+use log::{debug, warn};
 use regex::Regex;
 use thiserror::Error;
+use uuid::Uuid;
 use crate::backend::EFIVars;
-use crate::efiloadoption::{EFILoadOption, LoadOptionAttributeFlag, LoadOptionParseError};
-use crate::efivar::{EFIVariable, VariableName};
+use crate::crc32::crc32;
+use crate::efidevicepath::{DeviceKind, EFIDevicePathProtocol, MediaDevicePath, NATIVE_FALLBACK_ARCHITECTURE};
+use crate::efiloadoption::{DEFAULT_MAX_DESCRIPTION_LENGTH, DescriptionTooLongError, EFILoadOption, encode_command_line, InvalidFilePathListError, LoadOptionAttributeFlag, LoadOptionAttributes, LoadOptionCategory, LoadOptionParseError};
+#[cfg(target_os = "linux")]
+use crate::esp::EspInfo;
+use crate::efivar::{EFI_GLOBAL_VARIABLE_GUID, EFIVariable, EFIVariableAttribute, VariableName, WellKnownVariable};
+use crate::resolve::{device_path_health, EntryHealth, FileSystemProbe, find_missing_partition};
+#[cfg(target_os = "linux")]
+use crate::resolve::SystemProbe;
 
-static BOOT_KEY_REGEX: OnceLock<Regex> = OnceLock::new();
+/// Boot menu timeout in seconds assumed by [ListBootEntriesExt::effective_timeout] when there is
+/// no `Timeout` variable. The firmware uses an unknown internal default then.
+pub const DEFAULT_TIMEOUT: u16 = 5;
 
-fn boot_key_regex() -> &'static Regex {
-    BOOT_KEY_REGEX.get_or_init(|| Regex::new(r"^Boot([0-9A-F]{4})$").unwrap())
+/// Attributes every `Boot####` variable must be written with. Without `NonVolatile` the entry is
+/// discarded by the firmware on the next reboot.
+pub const BOOT_VARIABLE_ATTRIBUTES: BitFlags<EFIVariableAttribute> = make_bitflags!(EFIVariableAttribute::{NonVolatile | BootServiceAccess | RuntimeAccess});
+
+static LOAD_OPTION_KEY_REGEX: OnceLock<Regex> = OnceLock::new();
+
+fn load_option_key_regex() -> &'static Regex {
+    LOAD_OPTION_KEY_REGEX.get_or_init(|| Regex::new(r"^(Boot|Driver|SysPrep)([0-9A-F]{4})$").unwrap())
+}
+
+/// The variables holding load options. They all use the [EFILoadOption] format and are ordered by
+/// their own order variable, only the names differ.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoadOptionType {
+    /// `Boot####` entries ordered by `BootOrder`, tried by the boot manager.
+    Boot,
+    /// `Driver####` entries ordered by `DriverOrder`, UEFI drivers loaded before booting.
+    Driver,
+    /// `SysPrep####` entries ordered by `SysPrepOrder`, run once before booting.
+    SysPrep,
+}
+
+impl LoadOptionType {
+    /// The prefix of the `####` variables, like `Boot`.
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            LoadOptionType::Boot => "Boot",
+            LoadOptionType::Driver => "Driver",
+            LoadOptionType::SysPrep => "SysPrep",
+        }
+    }
+
+    pub fn order_variable(&self) -> WellKnownVariable {
+        match self {
+            LoadOptionType::Boot => WellKnownVariable::BootOrder,
+            LoadOptionType::Driver => WellKnownVariable::DriverOrder,
+            LoadOptionType::SysPrep => WellKnownVariable::SysPrepOrder,
+        }
+    }
+
+    /// The id of a variable of this type, like `0x0001` for `Boot0001`. The hex digits have to be
+    /// upper case.
+    pub fn entry_id(&self, name: &VariableName) -> Option<u16> {
+        let captures = load_option_key_regex().captures(name.key())?;
+        if captures.get(1)?.as_str() != self.prefix() {
+            return None;
+        }
+        u16::from_str_radix(captures.get(2)?.as_str(), 16).ok()
+    }
+
+    pub fn entry_name(&self, vendor: &Uuid, id: u16) -> VariableName {
+        VariableName::new(format!("{}{:04X}", self.prefix(), id), *vendor)
+    }
+}
+
+impl Display for LoadOptionType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.prefix())
+    }
+}
+
+pub(crate) fn boot_entry_id(name: &VariableName) -> Option<u16> {
+    LoadOptionType::Boot.entry_id(name)
+}
+
+pub(crate) fn boot_entry_name(id: u16) -> VariableName {
+    vendor_boot_entry_name(&EFI_GLOBAL_VARIABLE_GUID, id)
+}
+
+fn vendor_boot_entry_name(vendor: &Uuid, id: u16) -> VariableName {
+    LoadOptionType::Boot.entry_name(vendor, id)
+}
+
+#[derive(Debug, Error)]
+#[error("invalid boot entry number {0:?}")]
+pub struct InvalidBootNumberError(pub String);
+
+/// Parses a boot entry number the way `efibootmgr --bootnum` takes it, as up to four hex digits
+/// like `0001` or `1A`, optionally as the variable name like `Boot0001`.
+pub fn parse_boot_number(number: &str) -> Result<u16, InvalidBootNumberError> {
+    let digits = number.trim();
+    let digits = match digits.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("Boot") => &digits[4..],
+        _ => digits,
+    };
+    if digits.is_empty() || digits.len() > 4 || !digits.chars().all(|char| char.is_ascii_hexdigit()) {
+        return Err(InvalidBootNumberError(number.to_owned()));
+    }
+    u16::from_str_radix(digits, 16).map_err(|_| InvalidBootNumberError(number.to_owned()))
+}
+
+/// The `Boot####` variable for an `efibootmgr` style boot entry number, see [parse_boot_number].
+pub fn parse_boot_entry_name(number: &str) -> Result<VariableName, InvalidBootNumberError> {
+    parse_boot_number(number).map(boot_entry_name)
+}
+
+/// CRC32 of a `Boot####` variable's data, as stored in the `BootOptionCrc` of the `Key####`
+/// variables referencing it. This is the same CRC32 (IEEE 802.3, reflected) as the EFI boot
+/// services `CalculateCrc32`.
+pub fn boot_option_crc(load_option_bytes: &[u8]) -> u32 {
+    crc32(load_option_bytes)
+}
+
+/// Serializes the load option into a `Boot####` variable, making sure the attributes required for
+/// boot entries are set.
+pub(crate) fn boot_entry_variable(name: VariableName, load_option: &EFILoadOption, attributes: BitFlags<EFIVariableAttribute>) -> io::Result<EFIVariable> {
+    Ok(EFIVariable::new(name, attributes | BOOT_VARIABLE_ATTRIBUTES, load_option.to_bytes()?))
 }
 
 #[derive(Debug, Error)]
-#[error("error parsing Boot{id:04X}: {source}")]
+#[error("error parsing {kind}{id:04X}: {source}")]
 pub struct BootEntryParseError {
+    kind: LoadOptionType,
     id: u16,
     source: LoadOptionParseError,
 }
 
 impl BootEntryParseError {
     pub fn new(id: u16, source: LoadOptionParseError) -> Self {
-        Self { id, source }
+        Self::for_type(LoadOptionType::Boot, id, source)
+    }
+
+    /// Like [BootEntryParseError::new], for a `Driver####` or `SysPrep####` variable.
+    pub fn for_type(kind: LoadOptionType, id: u16, source: LoadOptionParseError) -> Self {
+        Self { kind, id, source }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BootEntry {
     id: u16,
     load_option: EFILoadOption,
@@ -46,6 +171,10 @@ impl Debug for BootEntry {
 }
 
 impl BootEntry {
+    pub(crate) fn new(id: u16, load_option: EFILoadOption) -> Self {
+        Self { id, load_option }
+    }
+
     pub fn id(&self) -> u16 {
         self.id
     }
@@ -57,8 +186,59 @@ impl BootEntry {
     pub fn is_active(&self) -> bool {
         self.load_option.attributes().flags().contains(LoadOptionAttributeFlag::Active)
     }
+
+    pub fn is_hidden(&self) -> bool {
+        self.load_option.attributes().flags().contains(LoadOptionAttributeFlag::Hidden)
+    }
+
+    /// Whether the firmware reconnects all drivers before booting the entry.
+    pub fn force_reconnect(&self) -> bool {
+        self.load_option.attributes().flags().contains(LoadOptionAttributeFlag::ForceReconnect)
+    }
+
+    /// Suggests a freedesktop icon name for the entry from the kinds of devices along its device
+    /// path, falling back to a generic executable icon for firmware applications and the like.
+    pub fn suggested_icon(&self) -> &'static str {
+        match self.load_option.device_path_nodes().filter_map(EFIDevicePathProtocol::device_kind).min() {
+            Some(DeviceKind::Network) => "network-wired-symbolic",
+            Some(DeviceKind::Optical) => "media-optical-symbolic",
+            Some(DeviceKind::Usb) => "media-removable-symbolic",
+            Some(DeviceKind::Disk) => "drive-harddisk-symbolic",
+            None => "application-x-executable-symbolic",
+        }
+    }
+
+    pub fn load_option(&self) -> &EFILoadOption {
+        &self.load_option
+    }
+
+    pub fn load_option_mut(&mut self) -> &mut EFILoadOption {
+        &mut self.load_option
+    }
+
+    /// The entry as stored in efivarfs, the 4-byte `attributes` followed by the serialized load
+    /// option. The inverse of parsing a `Boot####` file. Fails like [EFILoadOption::write] if the
+    /// load option can't be serialized.
+    pub fn to_variable_bytes(&self, attributes: BitFlags<EFIVariableAttribute>) -> io::Result<Vec<u8>> {
+        self.load_option.to_bytes_with_attributes(attributes)
+    }
+
+    pub fn into_load_option(self) -> EFILoadOption {
+        self.load_option
+    }
+
+    /// Checks whether the file the entry boots exists on the running system.
+    #[cfg(target_os = "linux")]
+    pub fn validate(&self) -> EntryHealth {
+        self.validate_with(&SystemProbe)
+    }
+
+    pub fn validate_with(&self, probe: &impl FileSystemProbe) -> EntryHealth {
+        device_path_health(self.load_option.file_path_list(), probe)
+    }
 }
 
+#[derive(Clone, PartialEq)]
 pub struct BootOrder {
     order: Vec<u16>,
 }
@@ -71,20 +251,429 @@ impl Debug for BootOrder {
     }
 }
 
+/// Formats the order like `efibootmgr` prints it and takes it for `--bootorder`, as comma separated
+/// hex ids like `0001,0003,0002`.
+impl Display for BootOrder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ids = self.order.iter().map(|id| format!("{:04X}", id)).collect::<Vec<_>>();
+        f.write_str(&ids.join(","))
+    }
+}
+
+/// Parses the comma separated ids `efibootmgr` prints and takes, with or without the `BootOrder:`
+/// label of its output. Each id is parsed with [parse_boot_number].
+impl FromStr for BootOrder {
+    type Err = InvalidBootNumberError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix("BootOrder:").unwrap_or(s).trim();
+        if s.is_empty() {
+            return Ok(BootOrder::new(vec![]));
+        }
+        s.split(',').map(parse_boot_number).collect::<Result<_, _>>().map(BootOrder::new)
+    }
+}
+
 impl BootOrder {
+    pub fn new(order: Vec<u16>) -> Self {
+        Self { order }
+    }
+
+    /// Parses the little-endian ids of a `BootOrder` variable. Odd lengths are rejected rather than
+    /// dropping the trailing byte, so writing back an order this accepts reproduces it byte for
+    /// byte.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BootOrderError> {
+        if !bytes.len().is_multiple_of(2) {
+            return Err(BootOrderError::OddLength(bytes.len()));
+        }
+
+        Ok(Self { order: bytes.chunks_exact(2).map(|id| u16::from_le_bytes([id[0], id[1]])).collect() })
+    }
+
     pub fn iter(&self) -> impl Iterator<Item=&u16> {
         self.order.iter()
     }
+
+    /// Appends the id, making it the last entry the firmware tries.
+    pub fn push(&mut self, id: u16) {
+        self.order.push(id);
+    }
+
+    /// Removes every occurrence of the id. Returns `false` if it wasn't in the order.
+    pub fn remove(&mut self, id: u16) -> bool {
+        let len = self.order.len();
+        self.order.retain(|other| *other != id);
+        self.order.len() != len
+    }
+
+    /// Replaces the whole order.
+    pub fn set(&mut self, order: Vec<u16>) {
+        self.order = order;
+    }
+
+    /// Swaps the id with the one before it. Returns `false` if the id is first or not in the order.
+    pub fn move_up(&mut self, id: u16) -> bool {
+        match self.order.iter().position(|other| *other == id) {
+            Some(index) if index > 0 => {
+                self.order.swap(index, index - 1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Swaps the id with the one after it. Returns `false` if the id is last or not in the order.
+    pub fn move_down(&mut self, id: u16) -> bool {
+        match self.order.iter().position(|other| *other == id) {
+            Some(index) if index + 1 < self.order.len() => {
+                self.order.swap(index, index + 1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Inserts the id right after `after`, or at the end if `after` isn't in the order.
+    pub fn insert_after(&mut self, after: u16, id: u16) {
+        let index = self.order.iter().position(|other| *other == after)
+            .map_or(self.order.len(), |position| position + 1);
+        self.order.insert(index, id);
+    }
+
+    /// Moves the id to the index, clamped to the end of the order, keeping the relative order of
+    /// the other ids. Returns `false` if the id isn't in the order.
+    pub fn move_to(&mut self, id: u16, index: usize) -> bool {
+        match self.order.iter().position(|other| *other == id) {
+            Some(position) => {
+                self.order.remove(position);
+                self.order.insert(index.min(self.order.len()), id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        self.order.iter().flat_map(|id| id.to_le_bytes()).collect()
+    }
 }
 
-pub struct OrderedBootEntries {
+pub struct OrderedBootEntries<E: EFIVars> {
     entries: HashMap<u16, BootEntry>,
     order: BootOrder,
+    current: Option<u16>,
+    errors: Vec<(u16, ReadBootEntryError<E>)>,
+}
+
+/// The errors can't be compared, only the ids of the entries that failed are.
+impl<E: EFIVars> PartialEq for OrderedBootEntries<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+            && self.order == other.order
+            && self.current == other.current
+            && self.errors.iter().map(|(id, _)| id).eq(other.errors.iter().map(|(id, _)| id))
+    }
 }
 
-impl OrderedBootEntries {
+impl<E: EFIVars> OrderedBootEntries<E> {
+    /// Iterates the entries in `BootOrder` order. Ids listed more than once in `BootOrder` only
+    /// yield their entry the first time, ids that failed to read or parse are skipped.
     pub fn iter(&self) -> impl Iterator<Item=&BootEntry> {
-        self.order.iter().filter_map(move |id| self.entries.get(id))
+        self.iter_ordered().map(|(_, entry)| entry)
+    }
+
+    /// The entries in `BootOrder` with their index, skipping dangling and repeated ids.
+    fn iter_ordered(&self) -> impl Iterator<Item=(usize, &BootEntry)> {
+        let mut seen = HashSet::new();
+        self.order.iter()
+            .enumerate()
+            .filter(move |(_, id)| seen.insert(**id))
+            .filter_map(move |(index, id)| Some((index, self.entries.get(id)?)))
+    }
+
+    pub fn order(&self) -> &BootOrder {
+        &self.order
+    }
+
+    /// The entries that failed to read or parse with their id, in ascending order. They're left out
+    /// of the other methods, except that they're neither dangling nor dropped by
+    /// [OrderedBootEntries::repair].
+    pub fn errors(&self) -> &[(u16, ReadBootEntryError<E>)] {
+        &self.errors
+    }
+
+    /// Takes the entries that failed to read or parse, see [OrderedBootEntries::errors].
+    pub fn take_errors(&mut self) -> Vec<(u16, ReadBootEntryError<E>)> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn failed(&self, id: u16) -> bool {
+        self.errors.iter().any(|(failed, _)| *failed == id)
+    }
+
+    /// The entry the system was booted from, as reported by `BootCurrent`. `None` if the firmware
+    /// doesn't set it, or the entry no longer exists.
+    pub fn current(&self) -> Option<&BootEntry> {
+        self.entries.get(&self.current?)
+    }
+
+    /// Iterates the entries along with their index in `BootOrder`, followed by the entries missing
+    /// from `BootOrder` in ascending order, with a `None` index.
+    pub fn iter_with_index(&self) -> impl Iterator<Item=(Option<usize>, &BootEntry)> {
+        let ordered = self.iter_ordered().map(|(index, entry)| (Some(index), entry));
+        let orphaned = self.integrity().orphaned.into_iter()
+            .map(move |id| (None, &self.entries[&id]));
+        ordered.chain(orphaned)
+    }
+
+    /// Finds ids in `BootOrder` without a `Boot####` variable, ids listed more than once, and
+    /// entries missing from `BootOrder`.
+    pub fn integrity(&self) -> BootOrderIssues {
+        let dangling = self.order.iter()
+            .filter(|id| !self.entries.contains_key(id) && !self.failed(**id))
+            .copied()
+            .collect();
+        let mut seen = HashSet::new();
+        let mut duplicated = vec![];
+        for id in self.order.iter() {
+            if !seen.insert(*id) && !duplicated.contains(id) {
+                duplicated.push(*id);
+            }
+        }
+        let mut orphaned = self.entries.keys()
+            .filter(|id| !self.order.order.contains(id))
+            .copied()
+            .collect::<Vec<_>>();
+        orphaned.sort_unstable();
+
+        BootOrderIssues { dangling, duplicated, orphaned }
+    }
+
+    /// Builds a `BootOrder` without the dangling ids and repeated ids, and the orphaned entries
+    /// appended in ascending order. Entries that failed to read keep their place.
+    pub fn repair(&self) -> BootOrder {
+        let issues = self.integrity();
+        let mut seen = HashSet::new();
+        let order = self.order.iter()
+            .copied()
+            .filter(|id| seen.insert(*id) && !issues.dangling.contains(id))
+            .chain(issues.orphaned.iter().copied())
+            .collect();
+
+        BootOrder::new(order)
+    }
+
+    /// Groups the ids of entries with duplicate load options, see
+    /// [EFILoadOption::is_duplicate_of]. Only groups of two or more entries are returned, with ids
+    /// in ascending order.
+    pub fn duplicates(&self) -> Vec<Vec<u16>> {
+        let mut ids = self.entries.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+
+        let mut groups: Vec<Vec<u16>> = vec![];
+        for id in ids {
+            let load_option = &self.entries[&id].load_option;
+            match groups.iter_mut().find(|group| self.entries[&group[0]].load_option.is_duplicate_of(load_option)) {
+                Some(group) => group.push(id),
+                None => groups.push(vec![id]),
+            }
+        }
+
+        groups.retain(|group| group.len() > 1);
+        groups
+    }
+
+    /// Whether any entry boots the removable media fallback bootloader of the architecture this is
+    /// built for, which firmware also boots when there are no entries at all.
+    pub fn has_explicit_fallback(&self) -> bool {
+        NATIVE_FALLBACK_ARCHITECTURE.is_some_and(|architecture| self.has_explicit_fallback_for(architecture))
+    }
+
+    /// Like [OrderedBootEntries::has_explicit_fallback], for the architecture suffix of the
+    /// fallback path, like `X64` or `AA64`.
+    pub fn has_explicit_fallback_for(&self, architecture: &str) -> bool {
+        self.entries.values()
+            .flat_map(|entry| entry.load_option.device_path_nodes())
+            .any(|node| matches!(
+                node,
+                EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::FilePath(path)) if path.fallback_architecture() == Some(architecture)
+            ))
+    }
+
+    /// Whether `BootCurrent` is the first active entry in `BootOrder`, the one the firmware tries
+    /// first. If not, the preferred entry was likely skipped or failed and the firmware fell back
+    /// to a later one, unless `BootNext` or the boot menu picked it.
+    pub fn booted_as_expected(&self, boot_current: u16) -> bool {
+        self.iter().find(|entry| entry.is_active()).is_some_and(|entry| entry.id == boot_current)
+    }
+
+    /// Counts the entries, leaving the current and next boot targets unset. See
+    /// [ListBootEntriesExt::boot_summary] for a summary including those.
+    pub fn summary(&self) -> BootSummary {
+        BootSummary {
+            total: self.entries.len(),
+            active: self.entries.values().filter(|entry| entry.is_active()).count(),
+            hidden: self.entries.values().filter(|entry| entry.is_hidden()).count(),
+            current: None,
+            next: None,
+        }
+    }
+}
+
+/// Reorders the entries listed by [OrderedBootEntries::iter], like a UI showing a row per entry
+/// with buttons to move it up or down, without writing anything until [PendingBootOrder::order] is.
+/// Ids without an entry and repeated ids aren't listed and keep their place in `BootOrder`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingBootOrder {
+    original: BootOrder,
+    listed: HashSet<u16>,
+    /// Ids of the listed entries in their new order.
+    shown: Vec<u16>,
+}
+
+impl PendingBootOrder {
+    pub fn new<E: EFIVars>(entries: &OrderedBootEntries<E>) -> Self {
+        let shown = entries.iter().map(BootEntry::id).collect::<Vec<_>>();
+        Self { original: entries.order().clone(), listed: shown.iter().copied().collect(), shown }
+    }
+
+    /// Ids of the listed entries in their new order.
+    pub fn shown(&self) -> &[u16] {
+        &self.shown
+    }
+
+    /// Whether the entry can be moved up, or down, which it can't if it's already first or last.
+    pub fn can_move(&self, id: u16, up: bool) -> bool {
+        self.target(id, up).is_some()
+    }
+
+    /// Swaps the entry with the one shown above or below it. Returns its new index in
+    /// [PendingBootOrder::shown], `None` if it's already first or last or isn't listed.
+    pub fn move_entry(&mut self, id: u16, up: bool) -> Option<usize> {
+        let (index, target) = self.target(id, up)?;
+        self.shown.swap(index, target);
+        Some(target)
+    }
+
+    fn target(&self, id: u16, up: bool) -> Option<(usize, usize)> {
+        let index = self.shown.iter().position(|other| *other == id)?;
+        let target = match up {
+            true => index.checked_sub(1)?,
+            false => Some(index + 1).filter(|target| *target < self.shown.len())?,
+        };
+        Some((index, target))
+    }
+
+    /// `BootOrder` with the listed entries in their new order, in the places they were listed at.
+    pub fn order(&self) -> BootOrder {
+        let mut shown = self.shown.iter().copied();
+        let mut seen = HashSet::new();
+        BootOrder::new(self.original.iter()
+            .map(|id| match self.listed.contains(id) && seen.insert(*id) {
+                true => shown.next().unwrap_or(*id),
+                false => *id,
+            })
+            .collect())
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.order() != self.original
+    }
+}
+
+/// Compares two boot configurations, like before and after applying changes. Ids within each list
+/// are in ascending order, except for [BootConfigDiff::reordered].
+pub fn diff<E: EFIVars>(before: &OrderedBootEntries<E>, after: &OrderedBootEntries<E>) -> BootConfigDiff {
+    fn sorted(ids: impl Iterator<Item=u16>) -> Vec<u16> {
+        let mut ids = ids.collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    }
+
+    let added = sorted(after.entries.keys().filter(|id| !before.entries.contains_key(id)).copied());
+    let removed = sorted(before.entries.keys().filter(|id| !after.entries.contains_key(id)).copied());
+    let changed = sorted(after.entries.iter()
+        .filter(|(id, entry)| before.entries.get(id).is_some_and(|before| before.load_option != entry.load_option))
+        .map(|(id, _)| *id));
+
+    // only compare the ids in both orders, so adding or removing an id doesn't move the others
+    let common = |order: &BootOrder, other: &BootOrder| order.iter()
+        .filter(|id| other.order.contains(id))
+        .copied()
+        .collect::<Vec<_>>();
+    let reordered = common(&after.order, &before.order).into_iter()
+        .zip(common(&before.order, &after.order))
+        .filter(|(after, before)| after != before)
+        .map(|(after, _)| after)
+        .collect();
+
+    BootConfigDiff { added, removed, reordered, changed }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BootConfigDiff {
+    /// Ids of `Boot####` variables that only exist after.
+    pub added: Vec<u16>,
+    /// Ids of `Boot####` variables that only exist before.
+    pub removed: Vec<u16>,
+    /// Ids at another position among the ids in both `BootOrder`s, in their order after.
+    pub reordered: Vec<u16>,
+    /// Ids of entries whose load option changed.
+    pub changed: Vec<u16>,
+}
+
+impl BootConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.reordered.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BootSummary {
+    /// Number of `Boot####` variables, including those missing from `BootOrder`.
+    pub total: usize,
+    pub active: usize,
+    pub hidden: usize,
+    /// Entry the system was booted from, as reported by `BootCurrent`.
+    pub current: Option<u16>,
+    /// Entry the firmware boots on the next boot only, as set in `BootNext`.
+    pub next: Option<u16>,
+}
+
+/// Everything the UI shows on startup, see [ListBootEntriesExt::read_boot_config].
+pub struct BootConfig<E: EFIVars> {
+    pub entries: OrderedBootEntries<E>,
+    /// Entry the system was booted from, as reported by `BootCurrent`.
+    pub boot_current: Option<u16>,
+    /// Entry the firmware boots on the next boot only, as set in `BootNext`.
+    pub boot_next: Option<u16>,
+    /// Boot menu timeout in seconds from the `Timeout` variable, `None` if it isn't set.
+    pub timeout: Option<u16>,
+}
+
+impl<E: EFIVars> PartialEq for BootConfig<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+            && self.boot_current == other.boot_current
+            && self.boot_next == other.boot_next
+            && self.timeout == other.timeout
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BootOrderIssues {
+    /// Ids in `BootOrder` without a `Boot####` variable.
+    pub dangling: Vec<u16>,
+    /// Ids listed more than once in `BootOrder`, in order of their first repetition.
+    pub duplicated: Vec<u16>,
+    /// Ids of `Boot####` variables missing from `BootOrder`.
+    pub orphaned: Vec<u16>,
+}
+
+impl BootOrderIssues {
+    pub fn is_empty(&self) -> bool {
+        self.dangling.is_empty() && self.duplicated.is_empty() && self.orphaned.is_empty()
     }
 }
 
@@ -98,6 +687,16 @@ pub enum ListBootEntriesError<E: EFIVars> {
     NoBootOrderVariableError,
     #[error("error reading BootOrder variable: {0}")]
     ReadBootOrderVariableError(#[source] E::ReadError),
+    #[error("error parsing BootOrder variable: {0}")]
+    ParseBootOrderError(#[from] BootOrderError),
+    #[error("error reading {0} variable: {1}")]
+    ReadBootTargetVariableError(&'static str, #[source] E::ReadError),
+}
+
+#[derive(Debug, Error)]
+pub enum BootOrderError {
+    #[error("length {0} is not a multiple of 2")]
+    OddLength(usize),
 }
 
 #[derive(Debug, Error)]
@@ -108,56 +707,1735 @@ pub enum ReadBootEntryError<E: EFIVars> {
     ParseError(#[from] BootEntryParseError),
 }
 
+#[derive(Debug, Error)]
+pub enum WriteBootEntryError<E: EFIVars> {
+    #[error("error listing efi variables: {0}")]
+    ListVariablesError(#[source] E::ListError),
+    #[error("no unused boot entry id available")]
+    NoFreeIdError,
+    #[error("Boot{0:04X} does not exist")]
+    NoBootEntryError(u16),
+    #[error("no partition with GPT UUID {0} exists on this system")]
+    MissingPartitionError(Uuid),
+    #[error("boot entry has no device path to boot")]
+    NoDevicePathError,
+    #[error(transparent)]
+    InvalidFilePathListError(#[from] InvalidFilePathListError),
+    #[error("error reading efi boot entry variable: {0}")]
+    ReadVariableError(#[source] E::ReadError),
+    #[error(transparent)]
+    ParseError(#[from] BootEntryParseError),
+    #[error("error serializing efi boot entry: {0}")]
+    SerializeError(#[from] io::Error),
+    #[error(transparent)]
+    DescriptionTooLongError(#[from] DescriptionTooLongError),
+    #[error("error writing efi boot entry variable: {0}")]
+    WriteVariableError(#[source] E::WriteError),
+    #[error("error parsing BootOrder variable: {0}")]
+    ParseBootOrderError(#[from] BootOrderError),
+}
+
+impl<E: EFIVars> From<ReadBootEntryError<E>> for WriteBootEntryError<E> {
+    fn from(err: ReadBootEntryError<E>) -> Self {
+        match err {
+            ReadBootEntryError::ReadVariableError(err) => Self::ReadVariableError(err),
+            ReadBootEntryError::ParseError(err) => Self::ParseError(err),
+        }
+    }
+}
+
+/// Collects the parts of a new boot entry and creates it in one go, see
+/// [ListBootEntriesExt::new_boot_entry].
+pub struct NewBootEntry<'a, E: EFIVars> {
+    efivars: &'a E,
+    description: String,
+    device_path: Vec<EFIDevicePathProtocol>,
+    active: bool,
+    cmdline: Option<String>,
+}
+
+impl<'a, E: EFIVars> NewBootEntry<'a, E> {
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Boots the file on the ESP. `efi_path` is relative to the root of the ESP and normalized like
+    /// [FilePathDevicePath::from_esp_path](crate::efidevicepath::FilePathDevicePath::from_esp_path).
+    #[cfg(target_os = "linux")]
+    pub fn esp_file(mut self, esp: &EspInfo, efi_path: &str) -> Self {
+        self.device_path = vec![esp.device_path(), EFIDevicePathProtocol::new_esp_file_path(efi_path)];
+        self
+    }
+
+    /// Boots the device path, without the end node.
+    pub fn device_path(mut self, device_path: Vec<EFIDevicePathProtocol>) -> Self {
+        self.device_path = device_path;
+        self
+    }
+
+    /// Whether the firmware may boot the entry. New entries are active unless set otherwise.
+    pub fn active(mut self, active: bool) -> Self {
+        self.active = active;
+        self
+    }
+
+    /// Passes the command line to the booted file as UTF-16 optional data.
+    pub fn cmdline(mut self, cmdline: impl Into<String>) -> Self {
+        self.cmdline = Some(cmdline.into());
+        self
+    }
+
+    /// The load option the entry is created from.
+    pub fn load_option(&self) -> Result<EFILoadOption, InvalidFilePathListError> {
+        let flags = if self.active { LoadOptionAttributeFlag::Active.into() } else { BitFlags::empty() };
+        let optional_data = self.cmdline.as_deref().map(encode_command_line).unwrap_or_default();
+        EFILoadOption::new(LoadOptionAttributes::new(flags, LoadOptionCategory::BOOT), self.description.clone(), self.device_path.clone(), optional_data)
+    }
+
+    /// Writes the entry to the lowest unused id and appends it to `BootOrder`, see
+    /// [ListBootEntriesExt::create_boot_entry_and_append]. Returns the id of the new entry.
+    pub async fn create(self) -> Result<u16, WriteBootEntryError<E>> {
+        if self.device_path.is_empty() {
+            return Err(WriteBootEntryError::NoDevicePathError);
+        }
+        self.efivars.create_boot_entry_and_append(&self.load_option()?, None).await
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BatchDeleteError<E: EFIVars> {
+    /// The entries that couldn't be deleted. The others were deleted and removed from `BootOrder`,
+    /// unless updating `BootOrder` failed as well.
+    #[error("failed to delete {}{}",
+        .failures.iter().map(|(id, err)| format!("Boot{:04X} ({})", id, err)).collect::<Vec<_>>().join(", "),
+        .order_error.as_ref().map(|err| format!(", {}", err)).unwrap_or_default())]
+    DeleteError {
+        failures: Vec<(u16, E::WriteError)>,
+        order_error: Option<UpdateBootOrderError<E>>,
+    },
+    /// Every entry was deleted, but they couldn't be removed from `BootOrder`.
+    #[error(transparent)]
+    UpdateBootOrderError(#[from] UpdateBootOrderError<E>),
+}
+
+/// Error removing deleted entries from `BootOrder`, see [BatchDeleteError].
+#[derive(Debug, Error)]
+pub enum UpdateBootOrderError<E: EFIVars> {
+    #[error("error reading BootOrder variable: {0}")]
+    ReadBootOrderError(#[source] E::ReadError),
+    #[error("error parsing BootOrder variable: {0}")]
+    ParseBootOrderError(#[from] BootOrderError),
+    #[error("error writing BootOrder variable: {0}")]
+    WriteBootOrderError(#[source] E::WriteError),
+}
+
 #[async_trait(? Send)]
 pub trait ListBootEntriesExt: EFIVars + Sized {
     async fn read_boot_entry(&self, name: &VariableName) -> Option<Result<BootEntry, ReadBootEntryError<Self>>>;
 
-    async fn list_boot_entries(&self) -> Result<OrderedBootEntries, ListBootEntriesError<Self>>;
-}
+    /// Reads a single `Boot####` entry again, e.g. after toggling or renaming it, so the UI can
+    /// update that one row instead of listing every entry. `None` if the entry is gone.
+    async fn reread_boot_entry(&self, id: u16) -> Option<Result<BootEntry, ReadBootEntryError<Self>>> {
+        self.read_boot_entry(&boot_entry_name(id)).await
+    }
 
-#[async_trait(? Send)]
-impl<E> ListBootEntriesExt for E
-    where E: EFIVars {
-    async fn read_boot_entry(&self, name: &VariableName) -> Option<Result<BootEntry, ReadBootEntryError<E>>> {
-        use ReadBootEntryError::*;
+    /// Reads `Boot####` along with its variable attributes, for rewriting the entry without
+    /// losing attribute bits set by the firmware or other tools. Unlike
+    /// [ListBootEntriesExt::read_boot_entry], an invalid UTF-16 description fails to parse, so a
+    /// rewrite can't replace it with a lossy copy. `None` if the entry doesn't exist.
+    async fn read_boot_variable(&self, id: u16) -> Option<Result<(BitFlags<EFIVariableAttribute>, EFILoadOption), ReadBootEntryError<Self>>> {
+        Some(self.read_variable(&boot_entry_name(id)).await?
+            .map_err(ReadBootEntryError::ReadVariableError)
+            .and_then(|variable| {
+                let load_option = EFILoadOption::parse(&mut Cursor::new(variable.data()))
+                    .map_err(|err| BootEntryParseError::new(id, err))?;
+                Ok((variable.attributes(), load_option))
+            }))
+    }
 
-        let id = boot_key_regex()
-            .captures(name.key())?
-            .get(1)?
-            .as_str();
-        let id = u16::from_str_radix(id, 16).ok()?;
+    /// Lists the `Boot####` entries along with `BootOrder` and `BootCurrent`. Entries that fail to
+    /// read or parse don't fail the listing, they're in [OrderedBootEntries::errors] instead.
+    /// Failing to read `BootOrder` still fails.
+    async fn list_boot_entries(&self) -> Result<OrderedBootEntries<Self>, ListBootEntriesError<Self>> {
+        self.list_boot_entries_for_vendor(&EFI_GLOBAL_VARIABLE_GUID).await
+    }
 
-        debug!("Reading Boot{:04X} variable...", id);
+    /// Lists the `Boot####` entries and `BootOrder` of the vendor instead of the global namespace.
+    async fn list_boot_entries_for_vendor(&self, vendor: &Uuid) -> Result<OrderedBootEntries<Self>, ListBootEntriesError<Self>>;
 
-        fn parse_entry<E: EFIVars>(id: u16, variable: EFIVariable) -> Result<BootEntry, ReadBootEntryError<E>> {
-            let mut read = Cursor::new(variable.data());
+    /// Lists the `Driver####` entries in `DriverOrder` order, the UEFI drivers the firmware loads
+    /// before booting. Without a `DriverOrder` variable the order is empty.
+    async fn list_driver_entries(&self) -> Result<OrderedBootEntries<Self>, ListBootEntriesError<Self>> {
+        list_load_options_for_vendor(self, LoadOptionType::Driver, &EFI_GLOBAL_VARIABLE_GUID).await
+    }
 
-            EFILoadOption::parse(&mut read)
-                .map(|load_option| BootEntry { id, load_option })
-                .map_err(|err| BootEntryParseError::new(id, err).into())
-        }
+    /// Lists the `SysPrep####` entries in `SysPrepOrder` order. Without a `SysPrepOrder` variable
+    /// the order is empty.
+    async fn list_sysprep_entries(&self) -> Result<OrderedBootEntries<Self>, ListBootEntriesError<Self>> {
+        list_load_options_for_vendor(self, LoadOptionType::SysPrep, &EFI_GLOBAL_VARIABLE_GUID).await
+    }
 
-        Some(match self.read_variable(name).await? {
-            Ok(variable) => parse_entry(id, variable),
-            Err(err) => Err(ReadVariableError::<E>(err))
-        })
+    /// Like [ListBootEntriesExt::list_boot_entries], with the entries that failed to read or parse
+    /// taken out of the listing and returned alongside it.
+    async fn list_boot_entries_lossy(&self) -> Result<(OrderedBootEntries<Self>, Vec<(u16, ReadBootEntryError<Self>)>), ListBootEntriesError<Self>> {
+        let mut entries = self.list_boot_entries().await?;
+        let errors = entries.take_errors();
+        Ok((entries, errors))
+    }
+
+    /// Yields boot entries in `BootOrder` order as each one is read, instead of waiting for all of
+    /// them like [ListBootEntriesExt::list_boot_entries]. Ids in `BootOrder` without a matching
+    /// `Boot####` variable are skipped.
+    fn boot_entries_stream(&self) -> LocalBoxStream<'_, Result<BootEntry, ListBootEntriesError<Self>>>;
+
+    /// Lists all `Boot####` entries sorted by id, without reading `BootOrder`. Works on systems
+    /// without a `BootOrder` variable and includes entries missing from it. Like
+    /// [ListBootEntriesExt::list_boot_entries_lossy], entries that fail to read or parse are
+    /// returned alongside the others instead of failing the listing.
+    async fn list_boot_entries_by_id(&self) -> Result<(Vec<BootEntry>, Vec<(u16, ReadBootEntryError<Self>)>), ListBootEntriesError<Self>>;
+
+    /// Writes the load option to the lowest unused `Boot####` id and returns that id. Like renaming
+    /// and cloning, this rejects descriptions longer than [DEFAULT_MAX_DESCRIPTION_LENGTH]. Write
+    /// the variable directly to use another limit.
+    ///
+    /// With a `validate` probe, like [SystemProbe](crate::resolve::SystemProbe), the entry is
+    /// refused if a GPT partition it references doesn't exist. Pass `None` when editing offline.
+    async fn create_boot_entry(&self, load_option: &EFILoadOption, validate: Option<&dyn FileSystemProbe>) -> Result<u16, WriteBootEntryError<Self>> {
+        self.create_boot_entry_for_vendor(&EFI_GLOBAL_VARIABLE_GUID, load_option, validate).await
+    }
+
+    /// Like [ListBootEntriesExt::create_boot_entry], in the vendor instead of the global namespace.
+    async fn create_boot_entry_for_vendor(&self, vendor: &Uuid, load_option: &EFILoadOption, validate: Option<&dyn FileSystemProbe>) -> Result<u16, WriteBootEntryError<Self>>;
+
+    /// Like [ListBootEntriesExt::create_boot_entry], and appends the new id to `BootOrder`,
+    /// creating it if it doesn't exist.
+    async fn create_boot_entry_and_append(&self, load_option: &EFILoadOption, validate: Option<&dyn FileSystemProbe>) -> Result<u16, WriteBootEntryError<Self>> {
+        use WriteBootEntryError::*;
+
+        let id = self.create_boot_entry(load_option, validate).await?;
+
+        let mut order = match self.read_variable(&WellKnownVariable::BootOrder.variable_name()).await {
+            Some(order) => BootOrder::from_bytes(order.map_err(ReadVariableError)?.data())?,
+            None => BootOrder::new(vec![]),
+        };
+        order.push(id);
+        self.write_boot_order(&order).await.map_err(WriteVariableError)?;
+
+        Ok(id)
+    }
+
+    /// Starts building a new active boot entry, created with [NewBootEntry::create]:
+    ///
+    /// ```ignore
+    /// let id = efivars.new_boot_entry()
+    ///     .description("Arch Linux")
+    ///     .esp_file(&esp, "EFI/Linux/arch-linux.efi")
+    ///     .cmdline("root=/dev/sda2 rw")
+    ///     .create().await?;
+    /// ```
+    fn new_boot_entry(&self) -> NewBootEntry<'_, Self> {
+        NewBootEntry { efivars: self, description: String::new(), device_path: vec![], active: true, cmdline: None }
+    }
+
+    /// Creates a copy of the entry under a new description, placed right after it in `BootOrder`.
+    /// Returns the id of the copy.
+    async fn clone_boot_entry(&self, id: u16, new_description: &str) -> Result<u16, WriteBootEntryError<Self>>;
+
+    /// Reads the entry, lets `edit` change its load option and writes it back with the attributes
+    /// the variable had, so bits set by the firmware or other tools survive the rewrite. Unlike
+    /// [ListBootEntriesExt::rename_boot_entry], the description length isn't checked.
+    async fn edit_boot_entry<F: FnOnce(&mut EFILoadOption)>(&self, id: u16, edit: F) -> Result<(), WriteBootEntryError<Self>> {
+        use WriteBootEntryError::*;
+
+        let (variable_attributes, mut load_option) = self.read_boot_variable(id).await
+            .ok_or(NoBootEntryError(id))??;
+        edit(&mut load_option);
+
+        let variable = boot_entry_variable(boot_entry_name(id), &load_option, variable_attributes)?;
+        self.write_variable(&variable).await.map_err(WriteVariableError)
+    }
+
+    /// Changes the description of the entry, keeping everything else including the variable
+    /// attributes. `validate` checks the partitions like [ListBootEntriesExt::create_boot_entry].
+    async fn rename_boot_entry(&self, id: u16, new_description: &str, validate: Option<&dyn FileSystemProbe>) -> Result<(), WriteBootEntryError<Self>>;
+
+    async fn set_boot_entry_active(&self, id: u16, active: bool) -> Result<(), WriteBootEntryError<Self>>;
+
+    /// Hides the entry from the firmware boot menu without removing it from `BootOrder`.
+    async fn set_boot_entry_hidden(&self, id: u16, hidden: bool) -> Result<(), WriteBootEntryError<Self>>;
+
+    async fn set_boot_entry_force_reconnect(&self, id: u16, force_reconnect: bool) -> Result<(), WriteBootEntryError<Self>>;
+
+    async fn write_boot_order(&self, order: &BootOrder) -> Result<(), Self::WriteError>;
+
+    /// Deletes the `Boot####` variables and removes their ids from `BootOrder`, which is written
+    /// once at the end. Ids without a variable are only removed from `BootOrder`. Entries that
+    /// fail to delete stay in `BootOrder` and are reported together.
+    async fn delete_boot_entries(&self, ids: &[u16]) -> Result<(), BatchDeleteError<Self>>;
+
+    /// Deletes the `Boot####` variable and removes its id from `BootOrder`, see
+    /// [ListBootEntriesExt::delete_boot_entries].
+    async fn delete_boot_entry(&self, id: u16) -> Result<(), BatchDeleteError<Self>> {
+        self.delete_boot_entries(&[id]).await
     }
 
-    async fn list_boot_entries(&self) -> Result<OrderedBootEntries, ListBootEntriesError<Self>> {
-        use ListBootEntriesError::*;
+    /// Discards the current `BootOrder` and writes every existing `Boot####` entry in ascending
+    /// id order instead. This changes which entry the firmware boots first, and puts entries back
+    /// in the order that were deliberately left out of it.
+    async fn reset_boot_order(&self) -> Result<(), WriteBootEntryError<Self>>;
 
-        let order = self.read_variable(&VariableName::global_vendor_new("BootOrder".to_owned())).await
-            .ok_or(NoBootOrderVariableError)?.map_err(ReadBootOrderVariableError)?;
-        let order = BootOrder { order: cast_slice(order.data()).to_vec() };
+    /// Sets the entry the firmware boots on the next boot only.
+    async fn set_boot_next(&self, id: u16) -> Result<(), Self::WriteError>;
+
+    /// The entry set in `BootNext`. `None` if it isn't set, or isn't a single u16 like firmware
+    /// expects.
+    async fn read_boot_next(&self) -> Result<Option<u16>, ListBootEntriesError<Self>> {
+        read_u16_variable(self, WellKnownVariable::BootNext).await
+    }
 
-        debug!("Read boot order: {:?}", order);
+    /// Deletes `BootNext`, so the next boot follows `BootOrder` again. Returns `false` if it
+    /// wasn't set.
+    async fn clear_boot_next(&self) -> Result<bool, Self::WriteError> {
+        debug!("Clearing BootNext");
+
+        self.delete_variable(&WellKnownVariable::BootNext.variable_name()).await
+    }
+
+    /// Lists the ids of the boot entries booting from the GPT partition, in ascending order.
+    async fn find_entries_for_partition(&self, uuid: &Uuid) -> Result<Vec<u16>, ListBootEntriesError<Self>> {
+        let entries = self.list_boot_entries().await?;
+        let mut ids = entries.entries.values()
+            .filter(|entry| entry.load_option.targets_partition(uuid))
+            .map(|entry| entry.id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    /// Like [OrderedBootEntries::summary], including the targets from `BootCurrent` and
+    /// `BootNext`.
+    async fn boot_summary(&self) -> Result<BootSummary, ListBootEntriesError<Self>>;
+
+    /// The boot menu timeout in seconds from the `Timeout` variable, or [DEFAULT_TIMEOUT] if it's
+    /// missing or can't be read. Meant as a starting value for the UI, not the raw variable.
+    async fn effective_timeout(&self) -> u16;
+
+    /// The boot menu timeout in seconds from the `Timeout` variable. `None` if it isn't set, or
+    /// isn't a single u16.
+    async fn read_timeout(&self) -> Result<Option<u16>, ListBootEntriesError<Self>> {
+        read_u16_variable(self, WellKnownVariable::Timeout).await
+    }
+
+    /// Sets how many seconds the boot menu waits before booting the first entry. `0` boots
+    /// immediately, `0xFFFF` waits for the user.
+    async fn set_timeout(&self, seconds: u16) -> Result<(), Self::WriteError> {
+        debug!("Setting Timeout to {} seconds", seconds);
+
+        let name = WellKnownVariable::Timeout.variable_name();
+        self.write_variable(&EFIVariable::new(name, BOOT_VARIABLE_ATTRIBUTES, seconds.to_le_bytes().to_vec())).await
+    }
+
+    /// Reads the boot entries, `BootCurrent`, `BootNext` and `Timeout` concurrently.
+    async fn read_boot_config(&self) -> Result<BootConfig<Self>, ListBootEntriesError<Self>>;
+}
+
+async fn read_boot_order<E: EFIVars>(efivars: &E, vendor: &Uuid) -> Result<BootOrder, ListBootEntriesError<E>> {
+    read_load_option_order(efivars, LoadOptionType::Boot, vendor).await
+}
+
+/// Reads the order variable of the load option type. Firmware only creates `DriverOrder` and
+/// `SysPrepOrder` once there are entries to order, so those are empty when missing, while a
+/// missing `BootOrder` is an error.
+async fn read_load_option_order<E: EFIVars>(efivars: &E, kind: LoadOptionType, vendor: &Uuid) -> Result<BootOrder, ListBootEntriesError<E>> {
+    use ListBootEntriesError::*;
+
+    let order = match efivars.read_variable(&VariableName::new(kind.order_variable().key().to_owned(), *vendor)).await {
+        Some(result) => result.map_err(ReadBootOrderVariableError)?,
+        None if kind != LoadOptionType::Boot => return Ok(BootOrder::new(vec![])),
+        None => return Err(NoBootOrderVariableError),
+    };
+    let order = BootOrder::from_bytes(order.data())?;
+
+    debug!("Read {}: {:?}", kind.order_variable().key(), order);
+
+    Ok(order)
+}
+
+/// Reads a `Boot####`, `Driver####` or `SysPrep####` variable. `None` if the name isn't one of the
+/// type, or the variable doesn't exist.
+async fn read_load_option_entry<E: EFIVars>(efivars: &E, kind: LoadOptionType, name: &VariableName) -> Option<Result<BootEntry, ReadBootEntryError<E>>> {
+    use ReadBootEntryError::*;
+
+    let id = kind.entry_id(name)?;
+
+    debug!("Reading {}{:04X} variable...", kind, id);
+
+    fn parse_entry<E: EFIVars>(kind: LoadOptionType, id: u16, variable: EFIVariable) -> Result<BootEntry, ReadBootEntryError<E>> {
+        // entries with a broken description or padded file path list are still listed,
+        // changing them fails to parse
+        let load_option = match EFILoadOption::parse(&mut Cursor::new(variable.data())) {
+            Err(LoadOptionParseError::FromUtf16Error(err)) => {
+                warn!("{}{:04X} description is not valid UTF-16, replacing invalid characters: {}", kind, id, err);
+                EFILoadOption::parse_lenient(&mut Cursor::new(variable.data()))
+            }
+            Err(err @ LoadOptionParseError::FilePathListLengthMismatch { .. }) => {
+                warn!("{}{:04X} {}, ignoring the bytes after it", kind, id, err);
+                EFILoadOption::parse_lenient(&mut Cursor::new(variable.data()))
+            }
+            result => result,
+        };
+
+        load_option
+            .map(|load_option| BootEntry { id, load_option })
+            .map_err(|err| BootEntryParseError::for_type(kind, id, err).into())
+    }
+
+    Some(match efivars.read_variable(name).await? {
+        Ok(variable) => parse_entry(kind, id, variable),
+        Err(err) => Err(ReadVariableError::<E>(err))
+    })
+}
+
+/// Lists the load options of the type in the vendor namespace, ordered by its order variable.
+/// Only boot entries have a current entry, from `BootCurrent`.
+async fn list_load_options_for_vendor<E: EFIVars>(efivars: &E, kind: LoadOptionType, vendor: &Uuid) -> Result<OrderedBootEntries<E>, ListBootEntriesError<E>> {
+    use ListBootEntriesError::*;
+
+    let order = read_load_option_order(efivars, kind, vendor).await?;
+    let current = match kind {
+        LoadOptionType::Boot => read_u16_variable_for_vendor(efivars, WellKnownVariable::BootCurrent, vendor).await?,
+        _ => None,
+    };
+
+    let variables = efivars.enumerate_variables().await.map_err(ListVariablesError)?
+        .into_iter()
+        .filter(|name| name.vendor() == vendor)
+        .filter_map(|name| Some((kind.entry_id(&name)?, name)));
+    // read concurrently, the order comes from the order variable and not from the order the reads
+    // finish
+    let results = stream::iter(variables)
+        .map(|(id, name)| async move {
+            Some(read_load_option_entry(efivars, kind, &name).await?.map_err(|err| (id, err)))
+        })
+        .buffer_unordered(efivars.max_concurrency().max(1))
+        .filter_map(|result| async move { result })
+        .collect::<Vec<_>>().await;
+
+    // a single broken entry doesn't hide all the others
+    let mut entries = HashMap::new();
+    let mut errors = vec![];
+    for result in results {
+        match result {
+            Ok(entry) => { entries.insert(entry.id, entry); }
+            Err(err) => errors.push(err),
+        }
+    }
+    errors.sort_by_key(|(id, _)| *id);
+
+    Ok(OrderedBootEntries { order, entries, current, errors })
+}
+
+/// Reads a variable holding a single little-endian u16, like `BootCurrent`, `BootNext` or
+/// `Timeout`. Missing or malformed variables are treated as unset.
+async fn read_u16_variable<E: EFIVars>(efivars: &E, variable: WellKnownVariable) -> Result<Option<u16>, ListBootEntriesError<E>> {
+    read_u16_variable_for_vendor(efivars, variable, &EFI_GLOBAL_VARIABLE_GUID).await
+}
+
+async fn read_u16_variable_for_vendor<E: EFIVars>(efivars: &E, variable: WellKnownVariable, vendor: &Uuid) -> Result<Option<u16>, ListBootEntriesError<E>> {
+    let variable = match efivars.read_variable(&VariableName::new(variable.key().to_owned(), *vendor)).await {
+        Some(result) => result.map_err(|err| ListBootEntriesError::ReadBootTargetVariableError(variable.key(), err))?,
+        None => return Ok(None),
+    };
+
+    Ok(variable.data().try_into().ok().map(u16::from_le_bytes))
+}
+
+fn check_partitions<E: EFIVars>(load_option: &EFILoadOption, validate: Option<&dyn FileSystemProbe>) -> Result<(), WriteBootEntryError<E>> {
+    match validate.and_then(|probe| find_missing_partition(load_option.file_path_list(), probe)) {
+        Some(uuid) => Err(WriteBootEntryError::MissingPartitionError(uuid)),
+        None => Ok(()),
+    }
+}
+
+/// Reads the boot entry, sets or clears the load option flag and writes it back.
+async fn set_boot_entry_flag<E: EFIVars>(efivars: &E, id: u16, flag: LoadOptionAttributeFlag, enabled: bool) -> Result<(), WriteBootEntryError<E>> {
+    debug!("Setting Boot{:04X} {:?}: {}", id, flag, enabled);
+
+    efivars.edit_boot_entry(id, |load_option| load_option.set_flag(flag, enabled)).await
+}
+
+#[async_trait(? Send)]
+impl<E> ListBootEntriesExt for E
+    where E: EFIVars {
+    async fn read_boot_entry(&self, name: &VariableName) -> Option<Result<BootEntry, ReadBootEntryError<E>>> {
+        read_load_option_entry(self, LoadOptionType::Boot, name).await
+    }
+
+    async fn list_boot_entries_for_vendor(&self, vendor: &Uuid) -> Result<OrderedBootEntries<Self>, ListBootEntriesError<Self>> {
+        list_load_options_for_vendor(self, LoadOptionType::Boot, vendor).await
+    }
+
+    fn boot_entries_stream(&self) -> LocalBoxStream<'_, Result<BootEntry, ListBootEntriesError<Self>>> {
+        stream::once(read_boot_order(self, &EFI_GLOBAL_VARIABLE_GUID))
+            .map_ok(move |order| {
+                let mut seen = HashSet::new();
+                stream::iter(order.order.into_iter().filter(move |id| seen.insert(*id)))
+                        .filter_map(move |id| async move { self.read_boot_entry(&boot_entry_name(id)).await })
+                    .map_err(ListBootEntriesError::from)
+            })
+            .try_flatten()
+            .boxed_local()
+    }
+
+    async fn list_boot_entries_by_id(&self) -> Result<(Vec<BootEntry>, Vec<(u16, ReadBootEntryError<Self>)>), ListBootEntriesError<Self>> {
+        let variables = self.enumerate_variables().await.map_err(ListBootEntriesError::ListVariablesError)?
+            .into_iter()
+            .filter(|name| name.vendor() == &EFI_GLOBAL_VARIABLE_GUID)
+            .filter_map(|name| Some((boot_entry_id(&name)?, name)));
+        let results = stream::iter(variables)
+            .map(|(id, name)| async move { Some((id, self.read_boot_entry(&name).await?)) })
+            .buffer_unordered(self.max_concurrency().max(1))
+            .filter_map(|result| async move { result })
+            .collect::<Vec<_>>().await;
+
+        let mut entries = vec![];
+        let mut errors = vec![];
+        for (id, result) in results {
+            match result {
+                Ok(entry) => entries.push(entry),
+                Err(err) => errors.push((id, err)),
+            }
+        }
+        entries.sort_by_key(BootEntry::id);
+        errors.sort_by_key(|(id, _)| *id);
+
+        Ok((entries, errors))
+    }
+
+    async fn create_boot_entry_for_vendor(&self, vendor: &Uuid, load_option: &EFILoadOption, validate: Option<&dyn FileSystemProbe>) -> Result<u16, WriteBootEntryError<Self>> {
+        use WriteBootEntryError::*;
+
+        check_partitions(load_option, validate)?;
+
+        let used = self.enumerate_variables().await.map_err(ListVariablesError)?
+            .iter()
+            .filter(|name| name.vendor() == vendor)
+            .filter_map(boot_entry_id)
+            .collect::<HashSet<_>>();
+        load_option.check_description_length(DEFAULT_MAX_DESCRIPTION_LENGTH)?;
+        let id = (0..=u16::MAX).find(|id| !used.contains(id)).ok_or(NoFreeIdError)?;
+
+        debug!("Creating Boot{:04X} variable...", id);
+
+        let variable = boot_entry_variable(vendor_boot_entry_name(vendor, id), load_option, BOOT_VARIABLE_ATTRIBUTES)?;
+        self.write_variable(&variable).await.map_err(WriteVariableError)?;
+
+        Ok(id)
+    }
+
+    async fn clone_boot_entry(&self, id: u16, new_description: &str) -> Result<u16, WriteBootEntryError<Self>> {
+        use WriteBootEntryError::*;
+
+        let (_, mut load_option) = self.read_boot_variable(id).await
+            .ok_or(NoBootEntryError(id))??;
+        load_option.set_description(new_description);
+
+        // the copy boots whatever the original does, there's nothing new to validate
+        let clone_id = self.create_boot_entry(&load_option, None).await?;
+
+        debug!("Cloned Boot{:04X} to Boot{:04X}", id, clone_id);
+
+        let mut order = match self.read_variable(&WellKnownVariable::BootOrder.variable_name()).await {
+            Some(order) => BootOrder::from_bytes(order.map_err(ReadVariableError)?.data())?,
+            None => BootOrder::new(vec![]),
+        };
+        order.insert_after(id, clone_id);
+        self.write_boot_order(&order).await.map_err(WriteVariableError)?;
+
+        Ok(clone_id)
+    }
+
+    async fn rename_boot_entry(&self, id: u16, new_description: &str, validate: Option<&dyn FileSystemProbe>) -> Result<(), WriteBootEntryError<Self>> {
+        use WriteBootEntryError::*;
+
+        let (variable_attributes, mut load_option) = self.read_boot_variable(id).await
+            .ok_or(NoBootEntryError(id))??;
+        check_partitions(&load_option, validate)?;
+
+        debug!("Renaming Boot{:04X} from {:?} to {:?}", id, load_option.description(), new_description);
+        load_option.set_description(new_description);
+        load_option.check_description_length(DEFAULT_MAX_DESCRIPTION_LENGTH)?;
+
+        let variable = boot_entry_variable(boot_entry_name(id), &load_option, variable_attributes)?;
+        self.write_variable(&variable).await.map_err(WriteVariableError)
+    }
+
+    async fn set_boot_entry_active(&self, id: u16, active: bool) -> Result<(), WriteBootEntryError<Self>> {
+        set_boot_entry_flag(self, id, LoadOptionAttributeFlag::Active, active).await
+    }
+
+    async fn set_boot_entry_hidden(&self, id: u16, hidden: bool) -> Result<(), WriteBootEntryError<Self>> {
+        set_boot_entry_flag(self, id, LoadOptionAttributeFlag::Hidden, hidden).await
+    }
+
+    async fn set_boot_entry_force_reconnect(&self, id: u16, force_reconnect: bool) -> Result<(), WriteBootEntryError<Self>> {
+        set_boot_entry_flag(self, id, LoadOptionAttributeFlag::ForceReconnect, force_reconnect).await
+    }
+
+    async fn write_boot_order(&self, order: &BootOrder) -> Result<(), Self::WriteError> {
+        debug!("Writing boot order: {:?}", order);
+
+        let name = WellKnownVariable::BootOrder.variable_name();
+        self.write_variable(&EFIVariable::new(name, BOOT_VARIABLE_ATTRIBUTES, order.to_bytes())).await
+    }
+
+    async fn delete_boot_entries(&self, ids: &[u16]) -> Result<(), BatchDeleteError<Self>> {
+        use UpdateBootOrderError::*;
+
+        let mut deleted = HashSet::new();
+        let mut failures = vec![];
+        for id in ids {
+            match self.delete_variable(&boot_entry_name(*id)).await {
+                Ok(existed) => {
+                    debug!("Deleted Boot{:04X}: {}", id, existed);
+                    deleted.insert(*id);
+                }
+                Err(err) => failures.push((*id, err)),
+            }
+        }
+
+        // failures are reported along with BootOrder errors, as the other entries are gone either way
+        let order_result = async {
+            if let Some(order) = self.read_variable(&WellKnownVariable::BootOrder.variable_name()).await {
+                let mut order = BootOrder::from_bytes(order.map_err(ReadBootOrderError)?.data())?;
+                let length = order.order.len();
+                order.order.retain(|id| !deleted.contains(id));
+                if order.order.len() != length {
+                    self.write_boot_order(&order).await.map_err(WriteBootOrderError)?;
+                }
+            }
+            Ok::<_, UpdateBootOrderError<Self>>(())
+        }.await;
+
+        if !failures.is_empty() {
+            return Err(BatchDeleteError::DeleteError { failures, order_error: order_result.err() });
+        }
+        Ok(order_result?)
+    }
+
+    async fn reset_boot_order(&self) -> Result<(), WriteBootEntryError<Self>> {
+        use WriteBootEntryError::*;
+
+        let mut ids = self.enumerate_variables().await.map_err(ListVariablesError)?
+            .iter()
+            .filter(|name| name.vendor() == &EFI_GLOBAL_VARIABLE_GUID)
+            .filter_map(boot_entry_id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+
+        debug!("Resetting boot order to ascending ids");
+
+        self.write_boot_order(&BootOrder::new(ids)).await.map_err(WriteVariableError)
+    }
+
+    async fn set_boot_next(&self, id: u16) -> Result<(), Self::WriteError> {
+        debug!("Setting BootNext to Boot{:04X}", id);
+
+        let name = WellKnownVariable::BootNext.variable_name();
+        self.write_variable(&EFIVariable::new(name, BOOT_VARIABLE_ATTRIBUTES, id.to_le_bytes().to_vec())).await
+    }
+
+    async fn boot_summary(&self) -> Result<BootSummary, ListBootEntriesError<Self>> {
+        let summary = self.list_boot_entries().await?.summary();
+
+        Ok(BootSummary {
+            current: read_u16_variable(self, WellKnownVariable::BootCurrent).await?,
+            next: read_u16_variable(self, WellKnownVariable::BootNext).await?,
+            ..summary
+        })
+    }
+
+    async fn effective_timeout(&self) -> u16 {
+        match read_u16_variable(self, WellKnownVariable::Timeout).await {
+            Ok(timeout) => timeout.unwrap_or(DEFAULT_TIMEOUT),
+            Err(err) => {
+                debug!("Failed to read Timeout variable, assuming the default: {}", err);
+                DEFAULT_TIMEOUT
+            }
+        }
+    }
+
+    async fn read_boot_config(&self) -> Result<BootConfig<Self>, ListBootEntriesError<Self>> {
+        let (entries, boot_current, boot_next, timeout) = futures::try_join!(
+            self.list_boot_entries(),
+            read_u16_variable(self, WellKnownVariable::BootCurrent),
+            read_u16_variable(self, WellKnownVariable::BootNext),
+            read_u16_variable(self, WellKnownVariable::Timeout),
+        )?;
+
+        Ok(BootConfig { entries, boot_current, boot_next, timeout })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::convert::Infallible;
+    use std::io::Cursor;
+    use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+    use std::task::Poll;
+    use async_trait::async_trait;
+    use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+    use enumflags2::BitFlags;
+    use futures::executor::block_on;
+    use futures::future::poll_fn;
+    use futures::TryStreamExt;
+    use thiserror::Error;
+    use uuid::Uuid;
+    use crate::backend::EFIVars;
+    use crate::backend::mock::MemoryEFIVars;
+    use crate::efiboot::{BatchDeleteError, BOOT_VARIABLE_ATTRIBUTES, boot_entry_id, boot_entry_name, boot_option_crc, BootConfigDiff, BootEntry, BootOrder, BootOrderError, BootOrderIssues, BootSummary, DEFAULT_TIMEOUT, diff, ListBootEntriesError, ListBootEntriesExt, LoadOptionType, OrderedBootEntries, parse_boot_entry_name, parse_boot_number, PendingBootOrder, ReadBootEntryError, UpdateBootOrderError, vendor_boot_entry_name, WriteBootEntryError};
+    use crate::efidevicepath::{EFIDevicePathProtocol, MediaDevicePath, Signature};
+    use crate::efiloadoption::{DEFAULT_MAX_DESCRIPTION_LENGTH, EFILoadOption, LoadOptionAttributeFlag};
+    #[cfg(target_os = "linux")]
+    use crate::esp::EspInfo;
+    use crate::efivar::{EFI_GLOBAL_VARIABLE_GUID, EFIVariable, EFIVariableAttribute, parse_efivar_payload, VariableName};
+    use crate::fixtures::{BOOT_CDROM, BOOT_NVME, BOOT_PXE_IPV4, BOOT_USB, test_variable, test_variable_as};
+    use crate::resolve::FileSystemProbe;
+
+    /// The `Boot0001` fixture, stored as the entry with the given id.
+    fn boot_entry(id: u16) -> EFIVariable {
+        test_variable_as(&boot_entry_name(1), boot_entry_name(id))
+    }
+
+    fn fixture_load_option() -> EFILoadOption {
+        EFILoadOption::parse(&mut Cursor::new(boot_entry(1).data())).unwrap()
+    }
+
+    #[test]
+    fn test_to_variable_bytes() {
+        let variable = boot_entry(1);
+        let entry = BootEntry::new(1, fixture_load_option());
+
+        let bytes = entry.to_variable_bytes(variable.attributes()).unwrap();
+        assert_eq!((variable.attributes(), variable.data()), parse_efivar_payload(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_create_boot_entry_attributes() {
+        let efivars = MemoryEFIVars::new();
+
+        let id = block_on(efivars.create_boot_entry(&fixture_load_option(), None)).unwrap();
+        let variable = block_on(efivars.read_variable(&boot_entry_name(id))).unwrap().unwrap();
+
+        assert!(variable.attributes().contains(EFIVariableAttribute::NonVolatile));
+        assert!(variable.attributes().contains(EFIVariableAttribute::BootServiceAccess));
+        assert!(variable.attributes().contains(EFIVariableAttribute::RuntimeAccess));
+    }
+
+    #[test]
+    fn test_create_boot_entry_fills_gaps() {
+        let data = fixture_load_option().to_bytes().unwrap();
+        let efivars = MemoryEFIVars::with_variables([
+            EFIVariable::new(boot_entry_name(0), BOOT_VARIABLE_ATTRIBUTES, data.clone()),
+            EFIVariable::new(boot_entry_name(2), BOOT_VARIABLE_ATTRIBUTES, data.clone()),
+            // not a boot entry, the id is upper case hex
+            EFIVariable::new(VariableName::global_vendor_new("Boot000a".to_owned()), BOOT_VARIABLE_ATTRIBUTES, data),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x02, 0x00, 0x00, 0x00]),
+        ]);
+
+        assert_eq!(1, block_on(efivars.create_boot_entry_and_append(&fixture_load_option(), None)).unwrap());
+        assert_eq!(3, block_on(efivars.create_boot_entry(&fixture_load_option(), None)).unwrap());
+        let order = block_on(efivars.list_boot_entries()).unwrap().order().clone();
+        assert_eq!(BootOrder::new(vec![2, 0, 1]), order);
+
+        block_on(efivars.delete_boot_entry(0)).unwrap();
+        assert!(block_on(efivars.read_variable(&boot_entry_name(0))).is_none());
+        assert_eq!(BootOrder::new(vec![2, 1]), block_on(efivars.list_boot_entries()).unwrap().order().clone());
+        assert_eq!(0, block_on(efivars.create_boot_entry(&fixture_load_option(), None)).unwrap());
+    }
+
+    /// A system without any partitions.
+    struct NoPartitionsProbe;
+
+    impl FileSystemProbe for NoPartitionsProbe {
+        fn partition_mount_point(&self, _: &Uuid) -> Option<PathBuf> {
+            None
+        }
+
+        fn file_exists(&self, _: &Path) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_validate_partition() {
+        let efivars = MemoryEFIVars::new();
+        let load_option = fixture_load_option();
+        let partition = load_option.file_path_list().iter()
+            .find_map(|node| match node {
+                EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::HardDrive(hard_drive)) => Some(hard_drive.signature().clone()),
+                _ => None,
+            })
+            .unwrap();
+
+        let result = block_on(efivars.create_boot_entry(&load_option, Some(&NoPartitionsProbe)));
+        assert!(matches!(result, Err(WriteBootEntryError::MissingPartitionError(uuid)) if Signature::GUID(uuid) == partition));
+        assert!(block_on(efivars.enumerate_variables()).unwrap().is_empty());
+
+        let id = block_on(efivars.create_boot_entry(&load_option, None)).unwrap();
+        let result = block_on(efivars.rename_boot_entry(id, "Arch", Some(&NoPartitionsProbe)));
+        assert!(matches!(result, Err(WriteBootEntryError::MissingPartitionError(_))));
+        assert_eq!("Arch Linux", block_on(efivars.reread_boot_entry(id)).unwrap().unwrap().description());
+    }
+
+    #[test]
+    fn test_set_boot_entry_active_forces_attributes() {
+        let mut data = vec![];
+        fixture_load_option().write(&mut data).unwrap();
+        let efivars = MemoryEFIVars::with_variables([
+            EFIVariable::new(boot_entry_name(1), BitFlags::empty(), data),
+        ]);
+
+        block_on(efivars.set_boot_entry_active(1, false)).unwrap();
+
+        let variable = block_on(efivars.read_variable(&boot_entry_name(1))).unwrap().unwrap();
+        assert_eq!(BOOT_VARIABLE_ATTRIBUTES, variable.attributes());
+        assert!(!block_on(efivars.read_boot_entry(&boot_entry_name(1))).unwrap().unwrap().is_active());
+    }
+
+    #[test]
+    fn test_rewrite_keeps_extra_attributes() {
+        let mut data = vec![];
+        fixture_load_option().write(&mut data).unwrap();
+        let attributes = BOOT_VARIABLE_ATTRIBUTES | EFIVariableAttribute::TimeBasedAuthenticatedWriteAccess;
+        let efivars = MemoryEFIVars::with_variables([
+            EFIVariable::new(boot_entry_name(1), attributes, data),
+        ]);
+
+        let (read_attributes, load_option) = block_on(efivars.read_boot_variable(1)).unwrap().unwrap();
+        assert_eq!(attributes, read_attributes);
+        assert_eq!(fixture_load_option(), load_option);
+        assert!(block_on(efivars.read_boot_variable(2)).is_none());
+
+        block_on(efivars.set_boot_entry_active(1, false)).unwrap();
+        assert_eq!(attributes, block_on(efivars.read_boot_variable(1)).unwrap().unwrap().0);
+
+        block_on(efivars.rename_boot_entry(1, "Renamed", None)).unwrap();
+        let (read_attributes, load_option) = block_on(efivars.read_boot_variable(1)).unwrap().unwrap();
+        assert_eq!(attributes, read_attributes);
+        assert_eq!("Renamed", load_option.description());
+    }
+
+    #[test]
+    fn test_edit_boot_entry() {
+        let attributes = BOOT_VARIABLE_ATTRIBUTES | EFIVariableAttribute::TimeBasedAuthenticatedWriteAccess;
+        let efivars = MemoryEFIVars::with_variables([
+            EFIVariable::new(boot_entry_name(1), attributes, fixture_load_option().to_bytes().unwrap()),
+        ]);
+
+        block_on(efivars.edit_boot_entry(1, |load_option| {
+            load_option.set_description("Edited");
+            load_option.set_flag(LoadOptionAttributeFlag::Hidden, true);
+            load_option.set_command_line("quiet");
+        })).unwrap();
+
+        let variable = block_on(efivars.read_variable(&boot_entry_name(1))).unwrap().unwrap();
+        assert_eq!(attributes, variable.attributes());
+        let load_option = EFILoadOption::parse(&mut Cursor::new(variable.data())).unwrap();
+        assert_eq!("Edited", load_option.description());
+        assert!(load_option.attributes().flags().contains(LoadOptionAttributeFlag::Hidden));
+        assert_eq!(Some("quiet".to_owned()), load_option.command_line());
+        assert_eq!(fixture_load_option().file_path_list(), load_option.file_path_list());
+
+        let result = block_on(efivars.edit_boot_entry(2, |_| {}));
+        assert!(matches!(result, Err(WriteBootEntryError::NoBootEntryError(2))), "{result:?}");
+    }
+
+    #[test]
+    fn test_boot_entries_stream() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            boot_entry(2),
+            boot_entry(3),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x02, 0x00, 0x04, 0x00, 0x01, 0x00]),
+        ]);
+
+        let streamed = block_on(efivars.boot_entries_stream().try_collect::<Vec<_>>()).unwrap();
+        let listed = block_on(efivars.list_boot_entries()).unwrap();
+
+        let streamed_ids = streamed.iter().map(|entry| entry.id()).collect::<Vec<_>>();
+        assert_eq!(vec![2, 1], streamed_ids);
+        assert_eq!(listed.iter().map(|entry| entry.id()).collect::<Vec<_>>(), streamed_ids);
+    }
+
+    #[test]
+    fn test_list_boot_entries_lossy() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            EFIVariable::new(boot_entry_name(2), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00, 0x00]),
+            boot_entry(3),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x03, 0x00, 0x02, 0x00, 0x01, 0x00]),
+        ]);
+
+        let (entries, errors) = block_on(efivars.list_boot_entries_lossy()).unwrap();
+        assert!(entries.errors().is_empty());
+        assert_eq!(vec![3, 1], entries.iter().map(BootEntry::id).collect::<Vec<_>>());
+        assert_eq!(vec![2], errors.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+        assert!(matches!(errors[0].1, ReadBootEntryError::ParseError(_)));
+    }
+
+    #[test]
+    fn test_list_boot_entries_with_corrupt_entry() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            EFIVariable::new(boot_entry_name(2), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00, 0x00]),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x02, 0x00, 0x01, 0x00]),
+        ]);
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(vec![1], entries.iter().map(BootEntry::id).collect::<Vec<_>>());
+        assert_eq!(1, entries.errors().len());
+        assert!(matches!(entries.errors()[0], (2, ReadBootEntryError::ParseError(_))));
+
+        // the corrupt entry exists, repairing BootOrder must not drop it
+        assert!(entries.integrity().is_empty());
+        assert_eq!(&BootOrder::new(vec![2, 1]), entries.order());
+        assert_eq!(BootOrder::new(vec![2, 1]), entries.repair());
+    }
+
+    #[test]
+    fn test_list_boot_entries_by_id() {
+        let efivars = MemoryEFIVars::with_variables([boot_entry(0x0A), boot_entry(2), boot_entry(0x100)]);
+
+        let ids = |efivars: &MemoryEFIVars| block_on(efivars.list_boot_entries_by_id()).unwrap()
+            .0.iter().map(BootEntry::id).collect::<Vec<_>>();
+        assert_eq!(vec![2, 0x0A, 0x100], ids(&efivars));
+
+        efivars.insert(EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x00, 0x01, 0x0A, 0x00]));
+        assert_eq!(vec![2, 0x0A, 0x100], ids(&efivars));
+
+        efivars.insert(EFIVariable::new(boot_entry_name(7), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00]));
+        let (entries, errors) = block_on(efivars.list_boot_entries_by_id()).unwrap();
+        assert_eq!(vec![2, 0x0A, 0x100], entries.iter().map(BootEntry::id).collect::<Vec<_>>());
+        assert_eq!(vec![7], errors.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+        assert!(matches!(errors[0].1, ReadBootEntryError::ParseError(_)));
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut distinct_bytes = boot_entry(1).data().to_vec();
+        // first character of the description, after the attributes and file path list length
+        distinct_bytes[6] ^= 0x01;
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            EFIVariable::new(boot_entry_name(2), BOOT_VARIABLE_ATTRIBUTES, distinct_bytes),
+            boot_entry(3),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x03, 0x00, 0x02, 0x00, 0x01, 0x00]),
+        ]);
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+
+        assert_eq!(vec![vec![1, 3]], entries.duplicates());
+    }
+
+    #[test]
+    fn test_boot_order_move_to() {
+        let mut order = BootOrder::new(vec![1, 2, 3, 4]);
+
+        assert!(order.move_to(1, 2));
+        assert_eq!(vec![2, 3, 1, 4], order.iter().copied().collect::<Vec<_>>());
+
+        assert!(order.move_to(2, 10));
+        assert_eq!(vec![3, 1, 4, 2], order.iter().copied().collect::<Vec<_>>());
+
+        assert!(!order.move_to(5, 0));
+    }
+
+    #[test]
+    fn test_boot_order_push_remove_set() {
+        let mut order = BootOrder::new(vec![1, 2, 1]);
+
+        order.push(3);
+        assert!(order.remove(1));
+        assert!(!order.remove(1));
+        assert_eq!(vec![2, 3], order.iter().copied().collect::<Vec<_>>());
+
+        order.set(vec![4]);
+        assert_eq!(vec![4], order.iter().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_write_boot_order_reordered() {
+        let efivars = MemoryEFIVars::new();
+        let mut order = BootOrder::from_bytes(&[0x01, 0x00, 0x02, 0x00]).unwrap();
+        assert!(order.move_to(0x0002, 0));
+
+        block_on(efivars.write_boot_order(&order)).unwrap();
+
+        let variable = block_on(efivars.read_variable(&VariableName::global_vendor_new("BootOrder".to_owned()))).unwrap().unwrap();
+        assert_eq!(&[0x02, 0x00, 0x01, 0x00], variable.data());
+        assert_eq!(BOOT_VARIABLE_ATTRIBUTES, variable.attributes());
+        assert_eq!(order, BootOrder::from_bytes(variable.data()).unwrap());
+    }
+
+    #[test]
+    fn test_boot_order_from_bytes() {
+        let order = BootOrder::from_bytes(&[0x02, 0x00, 0x01, 0x00, 0x00, 0x10]).unwrap();
+        assert_eq!(vec![0x0002, 0x0001, 0x1000], order.iter().copied().collect::<Vec<_>>());
+
+        assert!(matches!(BootOrder::from_bytes(&[0x02, 0x00, 0x01]), Err(BootOrderError::OddLength(3))));
+        assert_eq!(0, BootOrder::from_bytes(&[]).unwrap().iter().count());
+    }
+
+    #[test]
+    fn test_parse_boot_number() {
+        assert_eq!(1, parse_boot_number("0001").unwrap());
+        assert_eq!(1, parse_boot_number("Boot0001").unwrap());
+        assert_eq!(0x1A, parse_boot_number("1a").unwrap());
+        assert!(parse_boot_number("00001").is_err());
+        assert!(parse_boot_number("Boot").is_err());
+        assert!(parse_boot_number("BootNext").is_err());
+        assert!(parse_boot_number("+1").is_err());
+
+        assert_eq!(boot_entry_name(1).file_name(), parse_boot_entry_name("Boot0001").unwrap().file_name());
+    }
+
+    #[test]
+    fn test_boot_order_efibootmgr_format() {
+        let order = BootOrder::from_str("BootOrder: 0002,0001,1000").unwrap();
+        assert_eq!(vec![0x0002, 0x0001, 0x1000], order.iter().copied().collect::<Vec<_>>());
+        assert_eq!("0002,0001,1000", order.to_string());
+
+        assert_eq!(order, BootOrder::from_str("2,1,1000").unwrap());
+        assert_eq!(0, BootOrder::from_str("").unwrap().iter().count());
+        assert_eq!("", BootOrder::from_str("0002,,0001").unwrap_err().0);
+    }
+
+    #[test]
+    fn test_boot_order_round_trip() {
+        let bytes = [0x02, 0x00, 0x01, 0x00, 0x00, 0x10, 0xFF, 0xFF, 0x00, 0x00];
+        assert_eq!(&bytes[..], BootOrder::from_bytes(&bytes).unwrap().to_bytes().as_slice());
+
+        let mut order = BootOrder::from_bytes(&bytes).unwrap();
+        assert!(order.move_up(0x0001));
+        assert!(order.move_down(0x0001));
+        assert_eq!(&bytes[..], order.to_bytes().as_slice());
+    }
+
+    #[test]
+    fn test_read_boot_entry_invalid_description() {
+        // replace the 'A' of "Arch Linux" with a lone surrogate
+        let mut data = boot_entry(1).data().to_vec();
+        data[6..8].copy_from_slice(&0xD800u16.to_le_bytes());
+        let efivars = MemoryEFIVars::with_variables([EFIVariable::new(boot_entry_name(1), BOOT_VARIABLE_ATTRIBUTES, data)]);
+
+        let entry = block_on(efivars.read_boot_entry(&boot_entry_name(1))).unwrap().unwrap();
+        assert_eq!("\u{FFFD}rch Linux", entry.description());
+        assert!(block_on(efivars.set_boot_entry_active(1, false)).is_err());
+    }
+
+    #[test]
+    fn test_list_boot_entries_odd_boot_order() {
+        let efivars = MemoryEFIVars::with_variables([
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00, 0x02, 0x00, 0x03]),
+        ]);
+
+        let result = block_on(efivars.list_boot_entries());
+        assert!(matches!(result, Err(ListBootEntriesError::ParseBootOrderError(BootOrderError::OddLength(5)))));
+    }
+
+    #[test]
+    fn test_list_boot_entries_empty_boot_order() {
+        let efivars = MemoryEFIVars::with_variables([
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![]),
+        ]);
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+
+        assert_eq!(0, entries.iter().count());
+        assert!(entries.integrity().is_empty());
+    }
+
+    #[test]
+    fn test_set_boot_entry_hidden() {
+        let mut data = vec![];
+        fixture_load_option().write(&mut data).unwrap();
+        let efivars = MemoryEFIVars::with_variables([
+            EFIVariable::new(boot_entry_name(1), BOOT_VARIABLE_ATTRIBUTES, data),
+        ]);
+        let hidden_bit = |efivars: &MemoryEFIVars| {
+            let variable = block_on(efivars.read_variable(&boot_entry_name(1))).unwrap().unwrap();
+            Cursor::new(variable.data()).read_u32::<LittleEndian>().unwrap() & 0x08 != 0
+        };
+
+        block_on(efivars.set_boot_entry_hidden(1, true)).unwrap();
+        assert!(hidden_bit(&efivars));
+        assert!(block_on(efivars.read_boot_entry(&boot_entry_name(1))).unwrap().unwrap().is_hidden());
+
+        block_on(efivars.set_boot_entry_hidden(1, false)).unwrap();
+        assert!(!hidden_bit(&efivars));
+        assert!(block_on(efivars.read_boot_entry(&boot_entry_name(1))).unwrap().unwrap().is_active());
+    }
+
+    #[test]
+    fn test_set_boot_entry_force_reconnect() {
+        let mut data = vec![];
+        fixture_load_option().write(&mut data).unwrap();
+        let efivars = MemoryEFIVars::with_variables([
+            EFIVariable::new(boot_entry_name(1), BOOT_VARIABLE_ATTRIBUTES, data),
+        ]);
+
+        block_on(efivars.set_boot_entry_force_reconnect(1, true)).unwrap();
+
+        let entry = block_on(efivars.read_boot_entry(&boot_entry_name(1))).unwrap().unwrap();
+        assert!(entry.force_reconnect());
+        assert!(entry.is_active());
+    }
+
+    #[test]
+    fn test_vendor_boot_entries() {
+        let vendor = Uuid::from_str("3e2a7b14-9c5d-4f86-a1b0-5d6e7f809a1b").unwrap();
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00]),
+            test_variable_as(&boot_entry_name(1), vendor_boot_entry_name(&vendor, 0)),
+            EFIVariable::new(VariableName::new("BootOrder".to_owned(), vendor), BOOT_VARIABLE_ATTRIBUTES, vec![0x00, 0x00]),
+        ]);
+
+        let id = block_on(efivars.create_boot_entry_for_vendor(&vendor, &fixture_load_option(), None)).unwrap();
+        assert_eq!(1, id);
+        assert!(block_on(efivars.read_variable(&vendor_boot_entry_name(&vendor, 1))).is_some());
+
+        let entries = block_on(efivars.list_boot_entries_for_vendor(&vendor)).unwrap();
+        let mut ids = entries.entries.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+        assert_eq!(vec![0, 1], ids);
+
+        let global = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(vec![1], global.entries.keys().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_boot_order_integrity() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            boot_entry(2),
+            boot_entry(3),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x02, 0x00, 0x04, 0x00, 0x01, 0x00]),
+        ]);
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+
+        assert_eq!(BootOrderIssues { dangling: vec![4], duplicated: vec![], orphaned: vec![3] }, entries.integrity());
+        assert_eq!(vec![2, 1, 3], entries.repair().iter().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_boot_order_duplicated_ids() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            boot_entry(2),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00, 0x02, 0x00, 0x01, 0x00]),
+        ]);
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(vec![1, 2], entries.iter().map(|entry| entry.id()).collect::<Vec<_>>());
+        assert_eq!(vec![(Some(0), 1), (Some(1), 2)], entries.iter_with_index().map(|(index, entry)| (index, entry.id())).collect::<Vec<_>>());
+        assert_eq!(vec![1], entries.integrity().duplicated);
+        assert_eq!(vec![1, 2], entries.repair().iter().copied().collect::<Vec<_>>());
+
+        let streamed = block_on(efivars.boot_entries_stream().map_ok(|entry| entry.id()).try_collect::<Vec<_>>()).unwrap();
+        assert_eq!(vec![1, 2], streamed);
+    }
+
+    #[test]
+    fn test_pending_boot_order() {
+        // 5 is dangling and 1 is repeated, both keep their place
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            boot_entry(2),
+            boot_entry(3),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, BootOrder::new(vec![1, 5, 2, 1, 3]).to_bytes()),
+        ]);
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+
+        let mut pending = PendingBootOrder::new(&entries);
+        assert_eq!(&[1, 2, 3], pending.shown());
+        assert!(!pending.is_changed());
+        assert!(!pending.can_move(1, true));
+        assert!(!pending.can_move(3, false));
+        assert!(!pending.can_move(5, true));
+        assert_eq!(None, pending.move_entry(1, true));
+        assert_eq!(None, pending.move_entry(3, false));
+        assert_eq!(None, pending.move_entry(5, false));
+
+        assert_eq!(Some(1), pending.move_entry(3, true));
+        assert_eq!(&[1, 3, 2], pending.shown());
+        assert_eq!(BootOrder::new(vec![1, 5, 3, 1, 2]), pending.order());
+        assert!(pending.is_changed());
+        assert_eq!(Some(0), pending.move_entry(3, true));
+        assert_eq!(BootOrder::new(vec![3, 5, 1, 1, 2]), pending.order());
+
+        // moving back restores the original order
+        assert_eq!(Some(1), pending.move_entry(3, false));
+        assert_eq!(Some(2), pending.move_entry(3, false));
+        assert!(!pending.is_changed());
+
+        pending.move_entry(2, true);
+        block_on(efivars.write_boot_order(&pending.order())).unwrap();
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(&BootOrder::new(vec![2, 5, 1, 1, 3]), entries.order());
+    }
+
+    #[test]
+    fn test_suggested_icon() {
+        let icon = |id| {
+            let variable = test_variable(boot_entry_name(id));
+            BootEntry::new(id, EFILoadOption::parse(&mut Cursor::new(variable.data())).unwrap()).suggested_icon()
+        };
+
+        assert_eq!("drive-harddisk-symbolic", icon(0x0001));
+        assert_eq!("drive-harddisk-symbolic", icon(BOOT_NVME));
+        assert_eq!("media-removable-symbolic", icon(BOOT_USB));
+        assert_eq!("network-wired-symbolic", icon(BOOT_PXE_IPV4));
+        assert_eq!("media-optical-symbolic", icon(BOOT_CDROM));
+
+        // no description and only the end node
+        let bytes = [0x01, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x7F, 0xFF, 0x04, 0x00];
+        let empty = EFILoadOption::parse(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!("application-x-executable-symbolic", BootEntry::new(1, empty).suggested_icon());
+    }
+
+    #[test]
+    fn test_effective_timeout() {
+        let timeout = |data: Vec<u8>| EFIVariable::new(VariableName::global_vendor_new("Timeout".to_owned()), BOOT_VARIABLE_ATTRIBUTES, data);
+
+        assert_eq!(DEFAULT_TIMEOUT, block_on(MemoryEFIVars::new().effective_timeout()));
+        assert_eq!(0, block_on(MemoryEFIVars::with_variables([timeout(vec![0x00, 0x00])]).effective_timeout()));
+        assert_eq!(300, block_on(MemoryEFIVars::with_variables([timeout(vec![0x2C, 0x01])]).effective_timeout()));
+        assert_eq!(DEFAULT_TIMEOUT, block_on(MemoryEFIVars::with_variables([timeout(vec![0x2C])]).effective_timeout()));
+    }
+
+    fn ordered_entries(entries: impl IntoIterator<Item=(u16, EFILoadOption)>, order: Vec<u16>) -> OrderedBootEntries<MemoryEFIVars> {
+        OrderedBootEntries {
+            entries: entries.into_iter().map(|(id, load_option)| (id, BootEntry::new(id, load_option))).collect(),
+            order: BootOrder::new(order),
+            current: None,
+            errors: vec![],
+        }
+    }
+
+    #[test]
+    fn test_booted_as_expected() {
+        let mut inactive = fixture_load_option();
+        let mut attributes = *inactive.attributes();
+        attributes.set_flags(BitFlags::empty());
+        inactive.set_attributes(attributes);
+        let entries = ordered_entries([(1, fixture_load_option()), (2, inactive), (3, fixture_load_option())], vec![2, 3, 1]);
+
+        assert!(entries.booted_as_expected(3));
+        assert!(!entries.booted_as_expected(1));
+        assert!(!entries.booted_as_expected(2));
+        assert!(!ordered_entries([], vec![]).booted_as_expected(1));
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = ordered_entries([(1, fixture_load_option()), (2, fixture_load_option()), (3, fixture_load_option())], vec![1, 2, 3]);
+        assert!(diff(&before, &before).is_empty());
+
+        let after = ordered_entries([(1, fixture_load_option()), (2, fixture_load_option()), (3, fixture_load_option()), (4, fixture_load_option())], vec![1, 4, 2, 3]);
+        assert_eq!(BootConfigDiff { added: vec![4], ..Default::default() }, diff(&before, &after));
+        assert_eq!(BootConfigDiff { removed: vec![4], ..Default::default() }, diff(&after, &before));
+
+        let after = ordered_entries([(1, fixture_load_option()), (2, fixture_load_option()), (3, fixture_load_option())], vec![3, 1, 2]);
+        assert_eq!(BootConfigDiff { reordered: vec![3, 1, 2], ..Default::default() }, diff(&before, &after));
+
+        let after = ordered_entries([(1, fixture_load_option()), (2, fixture_load_option()), (3, fixture_load_option())], vec![2, 1, 3]);
+        assert_eq!(BootConfigDiff { reordered: vec![2, 1], ..Default::default() }, diff(&before, &after));
+
+        let mut renamed = fixture_load_option();
+        renamed.set_description("Renamed");
+        let after = ordered_entries([(1, fixture_load_option()), (2, renamed), (3, fixture_load_option())], vec![1, 2, 3]);
+        assert_eq!(BootConfigDiff { changed: vec![2], ..Default::default() }, diff(&before, &after));
+    }
+
+    #[test]
+    fn test_boot_summary() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            boot_entry(2),
+            boot_entry(3),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x02, 0x00, 0x01, 0x00]),
+            EFIVariable::new(VariableName::global_vendor_new("BootCurrent".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x02, 0x00]),
+        ]);
+        block_on(efivars.set_boot_entry_active(2, false)).unwrap();
+        block_on(efivars.set_boot_entry_hidden(3, true)).unwrap();
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(BootSummary { total: 3, active: 2, hidden: 1, current: None, next: None }, entries.summary());
+
+        block_on(efivars.set_boot_next(3)).unwrap();
+        let summary = block_on(efivars.boot_summary()).unwrap();
+        assert_eq!(BootSummary { total: 3, active: 2, hidden: 1, current: Some(2), next: Some(3) }, summary);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let efivars = MemoryEFIVars::new();
+        assert_eq!(None, block_on(efivars.read_timeout()).unwrap());
+
+        block_on(efivars.set_timeout(300)).unwrap();
+        let variable = block_on(efivars.read_variable(&VariableName::global_vendor_new("Timeout".to_owned()))).unwrap().unwrap();
+        assert_eq!(&[0x2C, 0x01], variable.data());
+        assert_eq!(BOOT_VARIABLE_ATTRIBUTES, variable.attributes());
+        assert_eq!(Some(300), block_on(efivars.read_timeout()).unwrap());
+        assert_eq!(300, block_on(efivars.effective_timeout()));
+    }
+
+    #[test]
+    fn test_boot_next() {
+        let efivars = MemoryEFIVars::new();
+        assert_eq!(None, block_on(efivars.read_boot_next()).unwrap());
+
+        block_on(efivars.set_boot_next(0x1000)).unwrap();
+        assert_eq!(Some(0x1000), block_on(efivars.read_boot_next()).unwrap());
+
+        assert!(block_on(efivars.clear_boot_next()).unwrap());
+        assert!(!block_on(efivars.clear_boot_next()).unwrap());
+        assert_eq!(None, block_on(efivars.read_boot_next()).unwrap());
+
+        let name = VariableName::global_vendor_new("BootNext".to_owned());
+        block_on(efivars.write_variable(&EFIVariable::new(name, BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00, 0x00]))).unwrap();
+        assert_eq!(None, block_on(efivars.read_boot_next()).unwrap());
+    }
+
+    #[test]
+    fn test_current_boot_entry() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            boot_entry(2),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00, 0x02, 0x00]),
+        ]);
+        assert!(block_on(efivars.list_boot_entries()).unwrap().current().is_none());
+
+        let current = VariableName::global_vendor_new("BootCurrent".to_owned());
+        block_on(efivars.write_variable(&EFIVariable::new(current, BOOT_VARIABLE_ATTRIBUTES, vec![0x02, 0x00]))).unwrap();
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(Some(2), entries.current().map(BootEntry::id));
+    }
+
+    #[test]
+    fn test_list_driver_entries() {
+        let data = fixture_load_option().to_bytes().unwrap();
+        let driver_entry = |id| EFIVariable::new(LoadOptionType::Driver.entry_name(&EFI_GLOBAL_VARIABLE_GUID, id), BOOT_VARIABLE_ATTRIBUTES, data.clone());
+        let efivars = MemoryEFIVars::with_variables([
+            driver_entry(0),
+            driver_entry(1),
+            EFIVariable::new(boot_entry_name(1), BOOT_VARIABLE_ATTRIBUTES, data.clone()),
+            EFIVariable::new(VariableName::global_vendor_new("DriverOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00, 0x00, 0x00]),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00]),
+        ]);
+
+        let entries = block_on(efivars.list_driver_entries()).unwrap();
+        assert_eq!(vec![1, 0], entries.iter().map(BootEntry::id).collect::<Vec<_>>());
+        assert_eq!(fixture_load_option(), *entries.iter().next().unwrap().load_option());
+        assert!(entries.current().is_none());
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(vec![1], entries.iter().map(BootEntry::id).collect::<Vec<_>>());
+
+        // no SysPrepOrder, and no SysPrep#### entries
+        let entries = block_on(efivars.list_sysprep_entries()).unwrap();
+        assert_eq!(0, entries.iter().count());
+    }
+
+    #[test]
+    fn test_load_option_type_entry_id() {
+        let name = |key: &str| VariableName::global_vendor_new(key.to_owned());
+
+        assert_eq!(Some(0x1A), LoadOptionType::Driver.entry_id(&name("Driver001A")));
+        assert_eq!(None, LoadOptionType::Boot.entry_id(&name("Driver001A")));
+        assert_eq!(Some(2), LoadOptionType::SysPrep.entry_id(&name("SysPrep0002")));
+        assert_eq!(None, LoadOptionType::SysPrep.entry_id(&name("SysPrepOrder")));
+        assert_eq!("SysPrep0002", LoadOptionType::SysPrep.entry_name(&EFI_GLOBAL_VARIABLE_GUID, 2).key());
+    }
+
+    /// Delays each boot entry read by yielding fewer times the higher its id, so reads issued
+    /// together finish in reverse order, and records how many reads were in flight at once.
+    #[derive(Debug)]
+    struct SlowEFIVars {
+        inner: MemoryEFIVars,
+        in_flight: Cell<usize>,
+        peak: Cell<usize>,
+    }
+
+    #[async_trait(? Send)]
+    impl EFIVars for SlowEFIVars {
+        type ListError = Infallible;
+        type ReadError = Infallible;
+        type WriteError = Infallible;
+
+        fn max_concurrency(&self) -> usize {
+            4
+        }
+
+        async fn enumerate_variables(&self) -> Result<Vec<VariableName>, Self::ListError> {
+            self.inner.enumerate_variables().await
+        }
+
+        async fn read_variable(&self, name: &VariableName) -> Option<Result<EFIVariable, Self::ReadError>> {
+            self.in_flight.set(self.in_flight.get() + 1);
+            self.peak.set(self.peak.get().max(self.in_flight.get()));
+            let mut yields = 16 - boot_entry_id(name).unwrap_or(16).min(16);
+            poll_fn(|cx| if yields == 0 {
+                Poll::Ready(())
+            } else {
+                yields -= 1;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }).await;
+            self.in_flight.set(self.in_flight.get() - 1);
+            self.inner.read_variable(name).await
+        }
+
+        async fn write_variable(&self, variable: &EFIVariable) -> Result<(), Self::WriteError> {
+            self.inner.write_variable(variable).await
+        }
+
+        async fn delete_variable(&self, name: &VariableName) -> Result<bool, Self::WriteError> {
+            self.inner.delete_variable(name).await
+        }
+    }
+
+    #[test]
+    fn test_list_boot_entries_concurrently() {
+        let data = fixture_load_option().to_bytes().unwrap();
+        let order = [3u16, 0, 7, 1, 6, 2, 5, 4];
+        let efivars = SlowEFIVars {
+            inner: MemoryEFIVars::with_variables((0..8).map(|id| EFIVariable::new(boot_entry_name(id), BOOT_VARIABLE_ATTRIBUTES, data.clone()))),
+            in_flight: Cell::new(0),
+            peak: Cell::new(0),
+        };
+        efivars.inner.insert(EFIVariable::new(
+            VariableName::global_vendor_new("BootOrder".to_owned()),
+            BOOT_VARIABLE_ATTRIBUTES,
+            order.iter().flat_map(|id| id.to_le_bytes()).collect(),
+        ));
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(order.to_vec(), entries.iter().map(BootEntry::id).collect::<Vec<_>>());
+        assert_eq!(4, efivars.peak.get());
+
+        efivars.peak.set(0);
+        let (entries, errors) = block_on(efivars.list_boot_entries_lossy()).unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(order.to_vec(), entries.iter().map(BootEntry::id).collect::<Vec<_>>());
+        assert_eq!(4, efivars.peak.get());
+
+        let (entries, _) = block_on(efivars.list_boot_entries_by_id()).unwrap();
+        assert_eq!((0..8).collect::<Vec<_>>(), entries.iter().map(BootEntry::id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_read_boot_config() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            boot_entry(2),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x02, 0x00, 0x01, 0x00]),
+            EFIVariable::new(VariableName::global_vendor_new("BootCurrent".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x02, 0x00]),
+            EFIVariable::new(VariableName::global_vendor_new("BootNext".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00]),
+            EFIVariable::new(VariableName::global_vendor_new("Timeout".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x03, 0x00]),
+        ]);
+
+        let config = block_on(efivars.read_boot_config()).unwrap();
+
+        assert!(block_on(efivars.list_boot_entries()).unwrap() == config.entries);
+        assert_eq!(Some(2), config.boot_current);
+        assert_eq!(Some(1), config.boot_next);
+        assert_eq!(Some(3), config.timeout);
+    }
+
+    #[test]
+    fn test_boot_entries_eq() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00]),
+        ]);
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert!(entries == block_on(efivars.list_boot_entries()).unwrap());
+        assert!(entries.iter().next() == block_on(efivars.read_boot_entry(&boot_entry_name(1))).unwrap().ok().as_ref());
+
+        block_on(efivars.set_boot_entry_active(1, false)).unwrap();
+        assert!(entries != block_on(efivars.list_boot_entries()).unwrap());
+    }
+
+    #[test]
+    fn test_find_entries_for_partition() {
+        let partition = Uuid::from_str("eba9a856-dfdd-42eb-be76-31760ae90f55").unwrap();
+        let other_partition = Uuid::from_str("0c3c6e5a-6f0b-4c8e-9d0d-8d4b2f0c7a11").unwrap();
+        let mut other_bytes = boot_entry(1).data().to_vec();
+        let signature_offset = other_bytes.windows(16).position(|window| window == partition.to_bytes_le()).unwrap();
+        other_bytes[signature_offset..signature_offset + 16].copy_from_slice(&other_partition.to_bytes_le());
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            EFIVariable::new(boot_entry_name(2), BOOT_VARIABLE_ATTRIBUTES, other_bytes),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x02, 0x00, 0x01, 0x00]),
+        ]);
+
+        assert_eq!(vec![1], block_on(efivars.find_entries_for_partition(&partition)).unwrap());
+        assert_eq!(vec![2], block_on(efivars.find_entries_for_partition(&other_partition)).unwrap());
+    }
+
+    #[test]
+    fn test_delete_boot_entries() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            boot_entry(2),
+            boot_entry(3),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x03, 0x00, 0x02, 0x00, 0x01, 0x00]),
+        ]);
+
+        block_on(efivars.delete_boot_entries(&[1, 3])).unwrap();
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(vec![2], entries.iter().map(BootEntry::id).collect::<Vec<_>>());
+        assert_eq!(&vec![2], &entries.order().order);
+        assert!(block_on(efivars.read_variable(&boot_entry_name(1))).is_none());
+        assert!(block_on(efivars.read_variable(&boot_entry_name(3))).is_none());
+    }
+
+    #[derive(Debug, Error)]
+    #[error("simulated delete failure")]
+    struct SimulatedDeleteError;
+
+    /// Fails deleting the variable with the key, everything else goes to the inner backend.
+    #[derive(Debug)]
+    struct FailingDeleteEFIVars {
+        inner: MemoryEFIVars,
+        fail_key: &'static str,
+    }
+
+    #[async_trait(? Send)]
+    impl EFIVars for FailingDeleteEFIVars {
+        type ListError = Infallible;
+        type ReadError = Infallible;
+        type WriteError = SimulatedDeleteError;
+
+        async fn enumerate_variables(&self) -> Result<Vec<VariableName>, Self::ListError> {
+            self.inner.enumerate_variables().await
+        }
+
+        async fn read_variable(&self, name: &VariableName) -> Option<Result<EFIVariable, Self::ReadError>> {
+            self.inner.read_variable(name).await
+        }
+
+        async fn write_variable(&self, variable: &EFIVariable) -> Result<(), Self::WriteError> {
+            self.inner.write_variable(variable).await.map_err(|err| match err {})
+        }
+
+        async fn delete_variable(&self, name: &VariableName) -> Result<bool, Self::WriteError> {
+            if name.key() == self.fail_key {
+                return Err(SimulatedDeleteError);
+            }
+            self.inner.delete_variable(name).await.map_err(|err| match err {})
+        }
+    }
+
+    #[test]
+    fn test_delete_boot_entries_reports_order_error() {
+        let efivars = FailingDeleteEFIVars {
+            inner: MemoryEFIVars::with_variables([
+                boot_entry(1),
+                boot_entry(3),
+                EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x03, 0x00, 0x01]),
+            ]),
+            fail_key: "Boot0003",
+        };
+
+        let result = block_on(efivars.delete_boot_entries(&[1, 3]));
+
+        let Err(BatchDeleteError::DeleteError { failures, order_error }) = result else {
+            panic!("expected a delete error, got {:?}", result);
+        };
+        assert_eq!(vec![3], failures.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+        assert!(matches!(order_error, Some(UpdateBootOrderError::ParseBootOrderError(BootOrderError::OddLength(3)))));
+        assert!(block_on(efivars.read_variable(&boot_entry_name(1))).is_none());
+    }
+
+    #[test]
+    fn test_reset_boot_order() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(0x0010),
+            boot_entry(0x0003),
+            boot_entry(0x0001),
+            EFIVariable::new(vendor_boot_entry_name(&Uuid::from_str("3c6f5e2a-7b1d-4c8e-9f0a-1b2c3d4e5f60").unwrap(), 0x0002), BOOT_VARIABLE_ATTRIBUTES, boot_entry(1).data().to_vec()),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x10, 0x00, 0x01, 0x00]),
+        ]);
+
+        block_on(efivars.reset_boot_order()).unwrap();
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(&vec![0x0001, 0x0003, 0x0010], &entries.order().order);
+    }
+
+    #[test]
+    fn test_clone_boot_entry() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            boot_entry(2),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00, 0x02, 0x00]),
+        ]);
+
+        let id = block_on(efivars.clone_boot_entry(1, "Arch Linux (debug)")).unwrap();
+        assert!(id != 1 && id != 2);
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(vec![1, id, 2], entries.order().iter().copied().collect::<Vec<_>>());
+
+        let source = block_on(efivars.read_boot_entry(&boot_entry_name(1))).unwrap().unwrap().into_load_option();
+        let clone = block_on(efivars.read_boot_entry(&boot_entry_name(id))).unwrap().unwrap().into_load_option();
+        assert_eq!("Arch Linux (debug)", clone.description());
+        assert_eq!(source.file_path_list(), clone.file_path_list());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_new_boot_entry() {
+        let esp = EspInfo::new(1, 0x800, 0x1F4000, Uuid::from_str("eba9a856-dfdd-42eb-be76-31760ae90f55").unwrap(), "/boot/efi");
+        let efivars = MemoryEFIVars::with_variables([
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x00, 0x00]),
+        ]);
+
+        let id = block_on(efivars.new_boot_entry()
+            .description("Arch Linux")
+            .esp_file(&esp, "EFI//Linux/arch-linux.efi")
+            .cmdline("root=/dev/sda2 rw")
+            .create()).unwrap();
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(vec![0, id], entries.order().iter().copied().collect::<Vec<_>>());
+        let entry = block_on(efivars.read_boot_entry(&boot_entry_name(id))).unwrap().unwrap().into_load_option();
+        assert_eq!(EFILoadOption::for_esp_file("Arch Linux", &esp, "EFI/Linux/arch-linux.efi", Some("root=/dev/sda2 rw")), entry);
+
+        let result = block_on(efivars.new_boot_entry().description("Nothing").create());
+        assert!(matches!(result, Err(WriteBootEntryError::NoDevicePathError)));
+    }
+
+    #[test]
+    fn test_rename_boot_entry() {
+        let original = boot_entry(1);
+        let attributes = BOOT_VARIABLE_ATTRIBUTES;
+        let efivars = MemoryEFIVars::with_variables([
+            original.clone(),
+        ]);
+
+        block_on(efivars.rename_boot_entry(1, "Arch", None)).unwrap();
+
+        let variable = block_on(efivars.read_variable(&boot_entry_name(1))).unwrap().unwrap();
+        assert_eq!(attributes, variable.attributes());
+
+        // attributes and file path list length, then the description, then the file path list
+        let old_description_size = ("Arch Linux".len() + 1) * 2;
+        let new_description_size = ("Arch".len() + 1) * 2;
+        let (old, new) = (original.data(), variable.data());
+        assert_eq!(old[..6], new[..6]);
+        assert_eq!("Arch\0".encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<_>>(), new[6..6 + new_description_size]);
+        assert_eq!(old[6 + old_description_size..], new[6 + new_description_size..]);
+
+        assert!(matches!(block_on(efivars.rename_boot_entry(2, "Missing", None)), Err(WriteBootEntryError::NoBootEntryError(2))));
+
+        block_on(efivars.rename_boot_entry(1, &"a".repeat(DEFAULT_MAX_DESCRIPTION_LENGTH), None)).unwrap();
+        let result = block_on(efivars.rename_boot_entry(1, &"a".repeat(DEFAULT_MAX_DESCRIPTION_LENGTH + 1), None));
+        assert!(matches!(result, Err(WriteBootEntryError::DescriptionTooLongError(_))));
+        assert!(block_on(efivars.create_boot_entry(&fixture_load_option(), None)).is_ok());
+    }
+
+    #[test]
+    fn test_reread_boot_entry() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+        ]);
+
+        block_on(efivars.rename_boot_entry(1, "Arch", None)).unwrap();
+        block_on(efivars.set_boot_entry_active(1, false)).unwrap();
+
+        let entry = block_on(efivars.reread_boot_entry(1)).unwrap().unwrap();
+        assert_eq!(1, entry.id());
+        assert_eq!("Arch", entry.description());
+        assert!(!entry.is_active());
+
+        assert!(block_on(efivars.reread_boot_entry(2)).is_none());
+    }
+
+    #[test]
+    fn test_boot_option_crc() {
+        assert_eq!(0xCBF43926, boot_option_crc(b"123456789"));
+
+        assert_eq!(0xC51CA182, boot_option_crc(boot_entry(1).data()));
+        assert_eq!(0xC51CA182, boot_option_crc(&fixture_load_option().to_bytes().unwrap()));
+    }
+
+    #[test]
+    fn test_iter_with_index() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            boot_entry(2),
+            boot_entry(3),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x04, 0x00, 0x02, 0x00, 0x01, 0x00]),
+        ]);
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        let indices = entries.iter_with_index().map(|(index, entry)| (index, entry.id())).collect::<Vec<_>>();
+
+        assert_eq!(vec![(Some(1), 2), (Some(2), 1), (None, 3)], indices);
+    }
+
+    /// Load option bytes booting only a file path, with the fixture's description.
+    fn file_path_load_option_bytes(path: &str) -> Vec<u8> {
+        let file_path_list = [EFIDevicePathProtocol::new_file_path(path), EFIDevicePathProtocol::new_end_entire()];
+        let mut bytes = Vec::new();
+        bytes.write_u32::<LittleEndian>(0x00000001).unwrap();
+        bytes.write_u16::<LittleEndian>(file_path_list.iter().map(|node| node.size().unwrap()).sum()).unwrap();
+        for char in "Fallback\0".encode_utf16() {
+            bytes.write_u16::<LittleEndian>(char).unwrap();
+        }
+        for node in &file_path_list {
+            node.write(&mut bytes).unwrap();
+        }
+        bytes
+    }
 
-        let variables = self.enumerate_variables().await.map_err(ListVariablesError)?;
-        let entries = stream::iter(variables).filter_map(|name| async move { self.read_boot_entry(&name).await })
-            .try_collect::<Vec<_>>().await?;
+    #[test]
+    fn test_has_explicit_fallback() {
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            EFIVariable::new(boot_entry_name(2), BOOT_VARIABLE_ATTRIBUTES, file_path_load_option_bytes("\\EFI\\BOOT\\BOOTX64.EFI")),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00, 0x02, 0x00]),
+        ]);
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert!(entries.has_explicit_fallback_for("X64"));
+        assert!(!entries.has_explicit_fallback_for("AA64"));
 
-        let entries = entries.into_iter().map(|entry| (entry.id, entry)).collect();
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            EFIVariable::new(boot_entry_name(2), BOOT_VARIABLE_ATTRIBUTES, file_path_load_option_bytes("/efi/boot/bootaa64.efi")),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x02, 0x00]),
+        ]);
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert!(entries.has_explicit_fallback_for("AA64"));
+        assert!(!entries.has_explicit_fallback_for("X64"));
 
-        Ok(OrderedBootEntries { order, entries })
+        let efivars = MemoryEFIVars::with_variables([
+            boot_entry(1),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00]),
+        ]);
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert!(!entries.has_explicit_fallback_for("X64"));
+        assert!(!entries.has_explicit_fallback());
     }
 }
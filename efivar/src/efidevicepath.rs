@@ -1,12 +1,18 @@
 use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
 use std::io;
-use std::io::{Read, Write};
-use std::iter::Sum;
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::{Path, PathBuf};
 use bytemuck::cast_slice;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use thiserror::Error;
 use uuid::Uuid;
+use crate::efivar::encode_utf16_nul;
+use crate::gpt::{GptError, GptPartition, GuidPartitionTable};
+
+/// Where the ESP is usually mounted, in order of preference.
+pub(crate) const ESP_MOUNT_POINTS: [&str; 3] = ["/boot/efi", "/efi", "/boot"];
 
 #[derive(Debug, Error)]
 pub enum DevicePathProtocolParseError {
@@ -21,24 +27,78 @@ pub enum DevicePathProtocolParseError {
     },
     #[error("error parsing subtype {sub_type}, {message}: {source:?}")]
     ParseSubType { sub_type: String, message: String, source: Option<Box<dyn Error>> },
+    #[error("device path node {typ:02X}/{sub_type:02X} has length {length}, shorter than its 4-byte header")]
+    NodeTooShort { typ: u8, sub_type: u8, length: u16 },
 }
 
 pub type Result<T> = std::result::Result<T, DevicePathProtocolParseError>;
 
+#[derive(Debug, Error)]
+#[error("{0:?} isn't under any of the usual ESP mount points {ESP_MOUNT_POINTS:?}")]
+pub struct NotOnEspError(pub PathBuf);
+
+/// Largest amount of data a device path node can hold, as its length including the 4-byte header
+/// is stored in a u16.
+pub const MAX_NODE_DATA_SIZE: usize = u16::MAX as usize - 4;
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum EFIDevicePathProtocol {
+    HardwareDevicePath(HardwareDevicePath) = EFIDevicePathProtocol::HARDWARE_DEVICE_PATH,
+    AcpiDevicePath(AcpiDevicePath) = EFIDevicePathProtocol::ACPI_DEVICE_PATH,
+    MessagingDevicePath(MessagingDevicePath) = EFIDevicePathProtocol::MESSAGING_DEVICE_PATH,
     MediaDevicePath(MediaDevicePath) = EFIDevicePathProtocol::MEDIA_DEVICE_PATH,
     End(EndSubType) = EFIDevicePathProtocol::END_OF_HARDWARE_DEVICE_PATH,
+    /// A node of a type or subtype that isn't modeled, kept as is so it's written back unchanged.
+    Unknown(UnknownDevicePath),
+}
+
+/// Formats the node in the text representation of the UEFI spec, as shown by `efibootmgr -v`.
+/// Unmodeled nodes are shown as `Path(type,subtype,data)`.
+impl Display for EFIDevicePathProtocol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EFIDevicePathProtocol::HardwareDevicePath(hardware) => Display::fmt(hardware, f),
+            EFIDevicePathProtocol::AcpiDevicePath(acpi) => Display::fmt(acpi, f),
+            EFIDevicePathProtocol::MessagingDevicePath(messaging) => Display::fmt(messaging, f),
+            EFIDevicePathProtocol::MediaDevicePath(media) => Display::fmt(media, f),
+            EFIDevicePathProtocol::End(EndSubType::EndInstanceDevicePath) => f.write_str("EndInstance"),
+            EFIDevicePathProtocol::End(EndSubType::EndEntireDevicePath) => f.write_str("End"),
+            EFIDevicePathProtocol::Unknown(unknown) => {
+                let data = unknown.data.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+                write!(f, "Path({},{},{})", unknown.typ, unknown.sub_type, data)
+            }
+        }
+    }
 }
 
-impl<'a> Sum<&'a EFIDevicePathProtocol> for u16 {
-    fn sum<I: Iterator<Item=&'a EFIDevicePathProtocol>>(iter: I) -> Self {
-        iter.map(|value| value.size()).sum()
+/// Displays a device path like `efibootmgr -v`, the nodes joined by `/` and instances separated by
+/// `,`, like `HD(1,GPT,...)/File(\EFI\BOOT\BOOTX64.EFI)`. The end node is left out.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DisplayDevicePath<'a>(pub &'a [EFIDevicePathProtocol]);
+
+impl Display for DisplayDevicePath<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut separator = "";
+        for node in self.0 {
+            match node {
+                EFIDevicePathProtocol::End(EndSubType::EndInstanceDevicePath) => separator = ",",
+                EFIDevicePathProtocol::End(EndSubType::EndEntireDevicePath) => break,
+                node => {
+                    write!(f, "{}{}", separator, node)?;
+                    separator = "/";
+                }
+            }
+        }
+        Ok(())
     }
 }
 
 impl EFIDevicePathProtocol {
+    const HARDWARE_DEVICE_PATH: u8 = 0x01;
+    const ACPI_DEVICE_PATH: u8 = 0x02;
+    const MESSAGING_DEVICE_PATH: u8 = 0x03;
     const MEDIA_DEVICE_PATH: u8 = 0x04;
     const END_OF_HARDWARE_DEVICE_PATH: u8 = 0x7F;
 
@@ -46,63 +106,720 @@ impl EFIDevicePathProtocol {
         EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::HardDrive(HardDriveDevicePath::new_gpt(partition_number, partition_start, partition_size, uuid)))
     }
 
+    pub fn new_hard_drive_mbr(partition_number: u32, partition_start: u64, partition_size: u64, signature: u32) -> Self {
+        EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::HardDrive(HardDriveDevicePath::new_mbr(partition_number, partition_start, partition_size, signature)))
+    }
+
     pub fn new_file_path(path: impl Into<String>) -> Self {
         EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::FilePath(FilePathDevicePath {
             path_name: path.into()
         }))
     }
 
+    /// A file path node for a path relative to the root of the ESP, normalized like
+    /// [FilePathDevicePath::from_esp_path].
+    pub fn new_esp_file_path(path: &str) -> Self {
+        EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::FilePath(FilePathDevicePath::from_esp_path(path)))
+    }
+
     pub fn new_end_entire() -> Self {
         EFIDevicePathProtocol::End(EndSubType::EndEntireDevicePath)
     }
 
-    pub fn size(&self) -> u16 {
-        4 + match self {
+    /// Length of the node including its 4-byte header, or `None` if its data is larger than
+    /// [MAX_NODE_DATA_SIZE] and the length doesn't fit the u16 in the header.
+    pub fn size(&self) -> Option<u16> {
+        let data_size = match self {
+            EFIDevicePathProtocol::HardwareDevicePath(value) => value.size(),
+            EFIDevicePathProtocol::AcpiDevicePath(value) => value.size(),
+            EFIDevicePathProtocol::MessagingDevicePath(value) => value.size(),
             EFIDevicePathProtocol::MediaDevicePath(value) => value.size(),
             EFIDevicePathProtocol::End(_) => 0,
-        }
+            EFIDevicePathProtocol::Unknown(value) => value.data.len(),
+        };
+        Self::node_length(data_size)
+    }
+
+    fn node_length(data_size: usize) -> Option<u16> {
+        (data_size <= MAX_NODE_DATA_SIZE).then(|| data_size as u16 + 4)
     }
 
     pub fn parse(read: &mut impl Read) -> Result<Self> {
         let typ = read.read_u8()?;
         let sub_type = read.read_u8()?;
-        let _length = read.read_u16::<LittleEndian>()?;
-        match typ {
-            Self::MEDIA_DEVICE_PATH => Ok(EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::parse(sub_type, read)?)),
+        let length = read.read_u16::<LittleEndian>()?;
+        // a length below the header size can't advance the reader, fail before it loops on crafted input
+        if length < 4 {
+            return Err(DevicePathProtocolParseError::NodeTooShort { typ, sub_type, length });
+        }
+        // the subtypes are parsed from the node data only, so they can't read into the next node
+        let mut data = vec![0; length as usize - 4];
+        read.read_exact(&mut data)?;
+        let mut node_read = Cursor::new(data.as_slice());
+        let read = &mut node_read;
+        let node = match typ {
+            Self::HARDWARE_DEVICE_PATH => HardwareDevicePath::parse(sub_type, read).map(EFIDevicePathProtocol::HardwareDevicePath),
+            Self::ACPI_DEVICE_PATH => AcpiDevicePath::parse(sub_type, length - 4, read).map(EFIDevicePathProtocol::AcpiDevicePath),
+            Self::MESSAGING_DEVICE_PATH => MessagingDevicePath::parse(sub_type, read).map(EFIDevicePathProtocol::MessagingDevicePath),
+            Self::MEDIA_DEVICE_PATH => MediaDevicePath::parse(sub_type, length - 4, read).map(EFIDevicePathProtocol::MediaDevicePath),
             Self::END_OF_HARDWARE_DEVICE_PATH => {
-                Ok(EFIDevicePathProtocol::End(sub_type.try_into().map_err(|_| DevicePathProtocolParseError::UnknownSubType {
+                sub_type.try_into().map(EFIDevicePathProtocol::End).map_err(|_| DevicePathProtocolParseError::UnknownSubType {
                     typ: "End",
                     sub_type,
-                })?))
+                })
             }
             _ => Err(DevicePathProtocolParseError::UnknownType(typ)),
+        };
+
+        // unknown subtypes, and known ones with a length that doesn't match their layout, are kept
+        // as is so they're written back unchanged
+        let consumed = node_read.position() == data.len() as u64;
+        match node {
+            Ok(node) if consumed => Ok(node),
+            Ok(_) | Err(DevicePathProtocolParseError::UnknownType(_) | DevicePathProtocolParseError::UnknownSubType { .. }) => {
+                Ok(EFIDevicePathProtocol::Unknown(UnknownDevicePath { typ, sub_type, data }))
+            }
+            Err(DevicePathProtocolParseError::IoError(err)) if matches!(err.kind(), io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidData) => {
+                Ok(EFIDevicePathProtocol::Unknown(UnknownDevicePath { typ, sub_type, data }))
+            }
+            Err(err) => Err(err),
         }
     }
 
-    pub fn write(&self, write: &mut impl Write) -> io::Result<()> {
-        let (typ, sub_type) = match self {
+    /// Parses a multi-instance device path, where each instance is terminated by an
+    /// [EndSubType::EndInstanceDevicePath] node and the whole path by an
+    /// [EndSubType::EndEntireDevicePath] node. The end nodes are not included in the instances.
+    pub fn parse_instances(read: &mut impl Read) -> Result<Vec<Vec<Self>>> {
+        let mut instances = vec![];
+        let mut instance = vec![];
+        loop {
+            match Self::parse(read)? {
+                EFIDevicePathProtocol::End(EndSubType::EndInstanceDevicePath) => instances.push(std::mem::take(&mut instance)),
+                EFIDevicePathProtocol::End(EndSubType::EndEntireDevicePath) => {
+                    instances.push(instance);
+                    break Ok(instances);
+                }
+                node => instance.push(node),
+            }
+        }
+    }
+
+    /// The type and subtype of the node, as written in its header.
+    pub fn type_and_sub_type(&self) -> (u8, u8) {
+        match self {
+            EFIDevicePathProtocol::HardwareDevicePath(value) => (Self::HARDWARE_DEVICE_PATH, value.sub_type()),
+            EFIDevicePathProtocol::AcpiDevicePath(value) => (Self::ACPI_DEVICE_PATH, value.sub_type()),
+            EFIDevicePathProtocol::MessagingDevicePath(value) => (Self::MESSAGING_DEVICE_PATH, value.sub_type()),
             EFIDevicePathProtocol::MediaDevicePath(value) => (Self::MEDIA_DEVICE_PATH, value.sub_type()),
             EFIDevicePathProtocol::End(value) => (Self::END_OF_HARDWARE_DEVICE_PATH, value.sub_type()),
+            EFIDevicePathProtocol::Unknown(value) => (value.typ, value.sub_type),
+        }
+    }
+
+    /// Short name of the storage bus or controller the node describes, like `NVMe` or `eMMC`, for
+    /// labels shown to users. Unmodeled nodes are named by their type and subtype as well.
+    pub fn bus_name(&self) -> Option<&'static str> {
+        match self.type_and_sub_type() {
+            (Self::MESSAGING_DEVICE_PATH, MessagingDevicePath::ATAPI_SUBTYPE) => Some("ATA"),
+            (Self::MESSAGING_DEVICE_PATH, MessagingDevicePath::SCSI_SUBTYPE) => Some("SCSI"),
+            (Self::MESSAGING_DEVICE_PATH, MessagingDevicePath::SATA_SUBTYPE) => Some("SATA"),
+            (Self::MESSAGING_DEVICE_PATH, MessagingDevicePath::NVME_SUBTYPE) => Some("NVMe"),
+            (Self::MESSAGING_DEVICE_PATH, MessagingDevicePath::SD_SUBTYPE) => Some("SD"),
+            (Self::MESSAGING_DEVICE_PATH, MessagingDevicePath::EMMC_SUBTYPE) => Some("eMMC"),
+            (Self::MESSAGING_DEVICE_PATH, MessagingDevicePath::USB_SUBTYPE
+                | MessagingDevicePath::USB_CLASS_SUBTYPE
+                | MessagingDevicePath::USB_WWID_SUBTYPE) => Some("USB"),
+            _ => None,
+        }
+    }
+
+    /// The kind of device the node points at, if it says anything about it. Unmodeled nodes are
+    /// classified by their type and subtype as well.
+    pub fn device_kind(&self) -> Option<DeviceKind> {
+        match self.type_and_sub_type() {
+            (Self::MESSAGING_DEVICE_PATH, MessagingDevicePath::MAC_ADDRESS_SUBTYPE
+                | MessagingDevicePath::IPV4_SUBTYPE
+                | MessagingDevicePath::IPV6_SUBTYPE
+                | MessagingDevicePath::VLAN_SUBTYPE
+                | MessagingDevicePath::URI_SUBTYPE) => Some(DeviceKind::Network),
+            (Self::MEDIA_DEVICE_PATH, MediaDevicePath::CDROM_SUBTYPE) => Some(DeviceKind::Optical),
+            (Self::MESSAGING_DEVICE_PATH, MessagingDevicePath::USB_SUBTYPE
+                | MessagingDevicePath::USB_CLASS_SUBTYPE
+                | MessagingDevicePath::USB_WWID_SUBTYPE) => Some(DeviceKind::Usb),
+            (Self::MEDIA_DEVICE_PATH, MediaDevicePath::HARD_DRIVE_SUBTYPE | MediaDevicePath::FILEPATH_SUBTYPE) => Some(DeviceKind::Disk),
+            _ => None,
+        }
+    }
+
+    /// Writes the node, failing with an [io::ErrorKind::InvalidInput] error if its data is too large
+    /// for a device path node, see [EFIDevicePathProtocol::size].
+    pub fn write(&self, write: &mut impl Write) -> io::Result<()> {
+        let (typ, sub_type) = self.type_and_sub_type();
+
+        // the data is serialized first so the length in the header is that of the written data
+        let mut data = vec![];
+        match self {
+            EFIDevicePathProtocol::HardwareDevicePath(hardware) => hardware.write(&mut data)?,
+            EFIDevicePathProtocol::AcpiDevicePath(acpi) => acpi.write(&mut data)?,
+            EFIDevicePathProtocol::MessagingDevicePath(messaging) => messaging.write(&mut data)?,
+            EFIDevicePathProtocol::MediaDevicePath(media) => media.write(&mut data)?,
+            EFIDevicePathProtocol::End(_) => (),
+            EFIDevicePathProtocol::Unknown(unknown) => data.extend_from_slice(&unknown.data),
         };
+        let length = Self::node_length(data.len())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} bytes of data are too large for a device path node", data.len())))?;
 
         write.write_u8(typ)?;
         write.write_u8(sub_type)?;
+        write.write_u16::<LittleEndian>(length)?;
+        write.write_all(&data)
+    }
+}
+
+/// Rough kind of device a device path node points at, in order of precedence when a path has
+/// nodes of several kinds, like a CD-ROM drive on USB.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DeviceKind {
+    Network,
+    Optical,
+    Usb,
+    Disk,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnknownDevicePath {
+    typ: u8,
+    sub_type: u8,
+    data: Vec<u8>,
+}
+
+impl UnknownDevicePath {
+    pub fn typ(&self) -> u8 {
+        self.typ
+    }
+
+    pub fn sub_type(&self) -> u8 {
+        self.sub_type
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
 
-        write.write_u16::<LittleEndian>(self.size())?;
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum HardwareDevicePath {
+    Pci { function: u8, device: u8 } = HardwareDevicePath::PCI_SUBTYPE,
+    PcCard { function_number: u8 } = HardwareDevicePath::PCCARD_SUBTYPE,
+    /// Baseboard management controller, with the interface type as defined by IPMI: 0 unknown,
+    /// 1 KCS, 2 SMIC, 3 BT.
+    Bmc { interface_type: u8, base_address: u64 } = HardwareDevicePath::BMC_SUBTYPE,
+}
 
+impl Display for HardwareDevicePath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            EFIDevicePathProtocol::MediaDevicePath(media) => media.write(write)?,
-            EFIDevicePathProtocol::End(_) => (),
-        };
+            HardwareDevicePath::Pci { function, device } => write!(f, "Pci(0x{:x},0x{:x})", device, function),
+            HardwareDevicePath::PcCard { function_number } => write!(f, "PcCard(0x{:x})", function_number),
+            HardwareDevicePath::Bmc { interface_type, base_address } => write!(f, "BMC(0x{:x},0x{:x})", interface_type, base_address),
+        }
+    }
+}
+
+impl HardwareDevicePath {
+    const PCI_SUBTYPE: u8 = 0x01;
+    const PCCARD_SUBTYPE: u8 = 0x02;
+    const BMC_SUBTYPE: u8 = 0x06;
+
+    pub fn parse(sub_type: u8, read: &mut impl Read) -> Result<Self> {
+        match sub_type {
+            Self::PCI_SUBTYPE => Ok(HardwareDevicePath::Pci {
+                function: read.read_u8()?,
+                device: read.read_u8()?,
+            }),
+            Self::PCCARD_SUBTYPE => Ok(HardwareDevicePath::PcCard { function_number: read.read_u8()? }),
+            Self::BMC_SUBTYPE => Ok(HardwareDevicePath::Bmc {
+                interface_type: read.read_u8()?,
+                base_address: read.read_u64::<LittleEndian>()?,
+            }),
+            _ => Err(DevicePathProtocolParseError::UnknownSubType { typ: "HardwareDevicePath", sub_type }),
+        }
+    }
+
+    pub fn write(&self, write: &mut impl Write) -> io::Result<()> {
+        match self {
+            HardwareDevicePath::Pci { function, device } => {
+                write.write_u8(*function)?;
+                write.write_u8(*device)?;
+            }
+            HardwareDevicePath::PcCard { function_number } => write.write_u8(*function_number)?,
+            HardwareDevicePath::Bmc { interface_type, base_address } => {
+                write.write_u8(*interface_type)?;
+                write.write_u64::<LittleEndian>(*base_address)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            HardwareDevicePath::Pci { .. } => 1 + 1,
+            HardwareDevicePath::PcCard { .. } => 1,
+            HardwareDevicePath::Bmc { .. } => 1 + 8,
+        }
+    }
+
+    pub fn sub_type(&self) -> u8 {
+        match self {
+            HardwareDevicePath::Pci { .. } => Self::PCI_SUBTYPE,
+            HardwareDevicePath::PcCard { .. } => Self::PCCARD_SUBTYPE,
+            HardwareDevicePath::Bmc { .. } => Self::BMC_SUBTYPE,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum AcpiDevicePath {
+    Acpi { hid: u32, uid: u32 } = AcpiDevicePath::ACPI_SUBTYPE,
+    Expanded {
+        hid: u32,
+        uid: u32,
+        cid: u32,
+        hid_str: String,
+        uid_str: String,
+        cid_str: String,
+    } = AcpiDevicePath::EXPANDED_SUBTYPE,
+    /// One or more `_ADR` values, as used by display output devices.
+    Adr(Vec<u32>) = AcpiDevicePath::ADR_SUBTYPE,
+    /// NVDIMM namespace, identified by its device handle in the ACPI NFIT table.
+    Nvdimm { nfit_device_handle: u32 } = AcpiDevicePath::NVDIMM_SUBTYPE,
+}
+
+impl Display for AcpiDevicePath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AcpiDevicePath::Acpi { hid, uid } => write!(f, "Acpi({},0x{:x})", format_acpi_id(*hid), uid),
+            AcpiDevicePath::Expanded { hid, uid, cid, hid_str, uid_str, cid_str } => {
+                write!(f, "AcpiEx({},{},0x{:x},{},{},{})", format_acpi_id(*hid), format_acpi_id(*cid), uid, hid_str, cid_str, uid_str)
+            }
+            AcpiDevicePath::Adr(adrs) => {
+                let adrs = adrs.iter().map(|adr| format!("0x{:x}", adr)).collect::<Vec<_>>();
+                write!(f, "AcpiAdr({})", adrs.join(","))
+            }
+            AcpiDevicePath::Nvdimm { nfit_device_handle } => write!(f, "NvdimmAcpiAdr(0x{:x})", nfit_device_handle),
+        }
+    }
+}
+
+/// Formats an ACPI `_HID` or `_CID` as its EISA ID, like `PNP0A03`, or as hex if it isn't one.
+/// EISA IDs pack three letters of 5 bits each into the low 16 bits, big-endian, followed by the
+/// product number in the high 16 bits.
+fn format_acpi_id(id: u32) -> String {
+    let vendor = id & 0xFFFF;
+    let letters = [(vendor >> 10) & 0x1F, (vendor >> 5) & 0x1F, vendor & 0x1F];
+    if vendor & 0x8000 != 0 || letters.iter().any(|letter| !(1..=26).contains(letter)) {
+        return format!("0x{:x}", id);
+    }
+
+    let letters = letters.iter().map(|letter| char::from(b'@' + *letter as u8)).collect::<String>();
+    format!("{}{:04X}", letters, id >> 16)
+}
+
+impl AcpiDevicePath {
+    const ACPI_SUBTYPE: u8 = 0x01;
+    const EXPANDED_SUBTYPE: u8 = 0x02;
+    const ADR_SUBTYPE: u8 = 0x03;
+    const NVDIMM_SUBTYPE: u8 = 0x04;
+
+    /// `length` is the length of the node excluding the 4-byte header, needed for the variable
+    /// sized subtypes. Fails with an [io::ErrorKind::InvalidData] error if it doesn't match the
+    /// layout of the subtype, before reading anything.
+    pub fn parse(sub_type: u8, length: u16, read: &mut impl Read) -> Result<Self> {
+        use DevicePathProtocolParseError::ParseSubType;
+
+        let invalid_length = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{message}, node data is {length} bytes"));
+
+        match sub_type {
+            Self::ACPI_SUBTYPE => Ok(AcpiDevicePath::Acpi {
+                hid: read.read_u32::<LittleEndian>()?,
+                uid: read.read_u32::<LittleEndian>()?,
+            }),
+            Self::EXPANDED_SUBTYPE => {
+                let strings_length = length.checked_sub(12)
+                    .ok_or_else(|| invalid_length("expanded ACPI node too short for its IDs"))?;
+                let hid = read.read_u32::<LittleEndian>()?;
+                let uid = read.read_u32::<LittleEndian>()?;
+                let cid = read.read_u32::<LittleEndian>()?;
+
+                let strings = {
+                    let mut buffer = vec![0u8; strings_length as usize];
+                    read.read_exact(&mut buffer)?;
+                    buffer
+                };
+                let mut strings = strings.split(|char| *char == 0x00)
+                    .map(|string| String::from_utf8(string.to_vec()));
+                let mut next_string = |message: &str| strings.next()
+                    .unwrap_or_else(|| Ok(String::new()))
+                    .map_err(|err| ParseSubType { sub_type: "AcpiExpandedDevicePath".to_owned(), message: message.to_owned(), source: Some(Box::new(err)) });
+
+                let hid_str = next_string("parse hid string")?;
+                let uid_str = next_string("parse uid string")?;
+                let cid_str = next_string("parse cid string")?;
+
+                Ok(AcpiDevicePath::Expanded { hid, uid, cid, hid_str, uid_str, cid_str })
+            }
+            Self::ADR_SUBTYPE => {
+                if length == 0 || !length.is_multiple_of(4) {
+                    return Err(invalid_length("ACPI _ADR node isn't a list of 4-byte addresses").into());
+                }
+                let adrs = (0..length / 4)
+                    .map(|_| read.read_u32::<LittleEndian>())
+                    .collect::<io::Result<Vec<_>>>()?;
+                Ok(AcpiDevicePath::Adr(adrs))
+            }
+            Self::NVDIMM_SUBTYPE => Ok(AcpiDevicePath::Nvdimm { nfit_device_handle: read.read_u32::<LittleEndian>()? }),
+            _ => Err(DevicePathProtocolParseError::UnknownSubType { typ: "AcpiDevicePath", sub_type }),
+        }
+    }
+
+    pub fn write(&self, write: &mut impl Write) -> io::Result<()> {
+        match self {
+            AcpiDevicePath::Acpi { hid, uid } => {
+                write.write_u32::<LittleEndian>(*hid)?;
+                write.write_u32::<LittleEndian>(*uid)?;
+            }
+            AcpiDevicePath::Expanded { hid, uid, cid, hid_str, uid_str, cid_str } => {
+                write.write_u32::<LittleEndian>(*hid)?;
+                write.write_u32::<LittleEndian>(*uid)?;
+                write.write_u32::<LittleEndian>(*cid)?;
+                for string in [hid_str, uid_str, cid_str] {
+                    write.write_all(string.as_bytes())?;
+                    write.write_u8(0x00)?;
+                }
+            }
+            AcpiDevicePath::Adr(adrs) => {
+                for adr in adrs {
+                    write.write_u32::<LittleEndian>(*adr)?;
+                }
+            }
+            AcpiDevicePath::Nvdimm { nfit_device_handle } => write.write_u32::<LittleEndian>(*nfit_device_handle)?,
+        }
+
+        Ok(())
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            AcpiDevicePath::Acpi { .. } => 4 + 4,
+            AcpiDevicePath::Expanded { hid_str, uid_str, cid_str, .. } => {
+                4 + 4 + 4 + hid_str.len() + 1 + uid_str.len() + 1 + cid_str.len() + 1
+            }
+            AcpiDevicePath::Adr(adrs) => adrs.len() * 4,
+            AcpiDevicePath::Nvdimm { .. } => 4,
+        }
+    }
+
+    pub fn sub_type(&self) -> u8 {
+        match self {
+            AcpiDevicePath::Acpi { .. } => Self::ACPI_SUBTYPE,
+            AcpiDevicePath::Expanded { .. } => Self::EXPANDED_SUBTYPE,
+            AcpiDevicePath::Adr(_) => Self::ADR_SUBTYPE,
+            AcpiDevicePath::Nvdimm { .. } => Self::NVDIMM_SUBTYPE,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum MessagingDevicePath {
+    FibreChannel { reserved: u32, wwn: u64, lun: u64 } = MessagingDevicePath::FIBRE_CHANNEL_SUBTYPE,
+    FireWire { reserved: u32, guid: u64 } = MessagingDevicePath::FIREWIRE_SUBTYPE,
+    I2O { tid: u32 } = MessagingDevicePath::I2O_SUBTYPE,
+    /// Network interface for PXE and HTTP boot. The address is padded with zeros to 32 bytes,
+    /// interface types 0 and 1 (Ethernet) use the first 6.
+    MacAddress { address: [u8; 32], if_type: u8 } = MessagingDevicePath::MAC_ADDRESS_SUBTYPE,
+    /// Parity and stop bits use the encoding of the specification, 0 meaning the device default for
+    /// either. A baud rate of 0 also means the default.
+    Uart { reserved: u32, baud_rate: u64, data_bits: u8, parity: u8, stop_bits: u8 } = MessagingDevicePath::UART_SUBTYPE,
+    /// Matches USB devices by ids and class codes, for firmware booting USB devices by class.
+    /// `0xFFFF` in any of the ids or `0xFF` in the class codes matches any device. Displayed as `-`,
+    /// but kept as is so the node writes back byte for byte.
+    UsbClass { vendor_id: u16, product_id: u16, device_class: u8, device_subclass: u8, device_protocol: u8 } = MessagingDevicePath::USB_CLASS_SUBTYPE,
+    /// A port multiplier port of `0xFFFF` means the device is attached directly to the HBA port.
+    /// Displayed as `-`, but kept as is so the node writes back byte for byte.
+    Sata { hba_port: u16, port_multiplier_port: u16, lun: u16 } = MessagingDevicePath::SATA_SUBTYPE,
+    Vlan(u16) = MessagingDevicePath::VLAN_SUBTYPE,
+    /// The IEEE EUI-64 is stored as a big-endian byte array, all zeros if the namespace has none.
+    Nvme { namespace_id: u32, eui64: [u8; 8] } = MessagingDevicePath::NVME_SUBTYPE,
+    /// WWN and LUN are stored as big-endian byte arrays, unlike [MessagingDevicePath::FibreChannel].
+    FibreChannelEx { reserved: u32, wwn: [u8; 8], lun: [u8; 8] } = MessagingDevicePath::FIBRE_CHANNEL_EX_SUBTYPE,
+    Bluetooth { address: [u8; 6] } = MessagingDevicePath::BLUETOOTH_SUBTYPE,
+}
+
+impl Display for MessagingDevicePath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessagingDevicePath::FibreChannel { wwn, lun, .. } => write!(f, "Fibre(0x{:x},0x{:x})", wwn, lun),
+            MessagingDevicePath::FireWire { guid, .. } => write!(f, "1394(0x{:x})", guid),
+            MessagingDevicePath::I2O { tid } => write!(f, "I2O({})", tid),
+            MessagingDevicePath::MacAddress { address, if_type } => {
+                let length = if *if_type <= 1 { 6 } else { address.len() };
+                let address = address[..length].iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+                write!(f, "MAC({},{})", address, if_type)
+            }
+            MessagingDevicePath::Uart { baud_rate, data_bits, parity, stop_bits, .. } => {
+                let parity = match parity {
+                    1 => "N",
+                    2 => "E",
+                    3 => "O",
+                    4 => "M",
+                    5 => "S",
+                    _ => "D",
+                };
+                let stop_bits = match stop_bits {
+                    1 => "1",
+                    2 => "1.5",
+                    3 => "2",
+                    _ => "D",
+                };
+                write!(f, "Uart({},{},{},{})", baud_rate, data_bits, parity, stop_bits)
+            }
+            MessagingDevicePath::UsbClass { vendor_id, product_id, device_class, device_subclass, device_protocol } => {
+                let vendor_id = format_sentinel(*vendor_id, 0xFFFF, |id| format!("0x{:04x}", id));
+                let product_id = format_sentinel(*product_id, 0xFFFF, |id| format!("0x{:04x}", id));
+                let device_subclass = format_sentinel(*device_subclass, 0xFF, |code| format!("0x{:02x}", code));
+                let device_protocol = format_sentinel(*device_protocol, 0xFF, |code| format!("0x{:02x}", code));
+                // well-known classes are named after the class instead of listing its code
+                match usb_class_name(*device_class) {
+                    Some(name) => write!(f, "{}({},{},{},{})", name, vendor_id, product_id, device_subclass, device_protocol),
+                    None => write!(f, "UsbClass({},{},{},{},{})", vendor_id, product_id,
+                                   format_sentinel(*device_class, 0xFF, |code| format!("0x{:02x}", code)), device_subclass, device_protocol),
+                }
+            }
+            MessagingDevicePath::Sata { hba_port, port_multiplier_port, lun } => {
+                write!(f, "Sata({},{},{})", hba_port, format_sentinel(*port_multiplier_port, 0xFFFF, |port| port.to_string()), lun)
+            }
+            MessagingDevicePath::Vlan(vlan_id) => write!(f, "Vlan({})", vlan_id),
+            MessagingDevicePath::Nvme { namespace_id, eui64 } => {
+                let eui64 = eui64.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>();
+                write!(f, "NVMe(0x{:x},{})", namespace_id, eui64.join("-"))
+            }
+            MessagingDevicePath::FibreChannelEx { wwn, lun, .. } => {
+                write!(f, "FibreEx(0x{:016x},0x{:016x})", u64::from_be_bytes(*wwn), u64::from_be_bytes(*lun))
+            }
+            MessagingDevicePath::Bluetooth { address } => {
+                let address = address.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>();
+                write!(f, "Bluetooth({})", address.join(":"))
+            }
+        }
+    }
+}
+
+/// Text representation names of the USB device classes from the UEFI spec.
+fn usb_class_name(device_class: u8) -> Option<&'static str> {
+    match device_class {
+        0x01 => Some("UsbAudio"),
+        0x02 => Some("UsbCDCControl"),
+        0x03 => Some("UsbHID"),
+        0x06 => Some("UsbImage"),
+        0x07 => Some("UsbPrinter"),
+        0x08 => Some("UsbMassStorage"),
+        0x09 => Some("UsbHub"),
+        0x0A => Some("UsbCDCData"),
+        0x0B => Some("UsbSmartCard"),
+        0x0E => Some("UsbVideo"),
+        0xDC => Some("UsbDiagnostic"),
+        0xE0 => Some("UsbWireless"),
+        _ => None,
+    }
+}
+
+/// Formats the value, or `-` if it is the sentinel for "none" or "any". Only for display, the raw
+/// value is what gets written.
+fn format_sentinel<T: PartialEq>(value: T, sentinel: T, format: impl FnOnce(T) -> String) -> String {
+    if value == sentinel { "-".to_owned() } else { format(value) }
+}
+
+impl MessagingDevicePath {
+    const ATAPI_SUBTYPE: u8 = 0x01;
+    const SCSI_SUBTYPE: u8 = 0x02;
+    const FIBRE_CHANNEL_SUBTYPE: u8 = 0x03;
+    const FIREWIRE_SUBTYPE: u8 = 0x04;
+    const USB_SUBTYPE: u8 = 0x05;
+    const I2O_SUBTYPE: u8 = 0x06;
+    const MAC_ADDRESS_SUBTYPE: u8 = 0x0B;
+    const IPV4_SUBTYPE: u8 = 0x0C;
+    const IPV6_SUBTYPE: u8 = 0x0D;
+    const UART_SUBTYPE: u8 = 0x0E;
+    const USB_CLASS_SUBTYPE: u8 = 0x0F;
+    const USB_WWID_SUBTYPE: u8 = 0x10;
+    const SATA_SUBTYPE: u8 = 0x12;
+    const VLAN_SUBTYPE: u8 = 0x14;
+    const FIBRE_CHANNEL_EX_SUBTYPE: u8 = 0x15;
+    const NVME_SUBTYPE: u8 = 0x17;
+    const URI_SUBTYPE: u8 = 0x18;
+    const SD_SUBTYPE: u8 = 0x1A;
+    const BLUETOOTH_SUBTYPE: u8 = 0x1B;
+    const EMMC_SUBTYPE: u8 = 0x1D;
+
+    pub fn parse(sub_type: u8, read: &mut impl Read) -> Result<Self> {
+        match sub_type {
+            Self::FIBRE_CHANNEL_SUBTYPE => Ok(MessagingDevicePath::FibreChannel {
+                reserved: read.read_u32::<LittleEndian>()?,
+                wwn: read.read_u64::<LittleEndian>()?,
+                lun: read.read_u64::<LittleEndian>()?,
+            }),
+            Self::FIREWIRE_SUBTYPE => Ok(MessagingDevicePath::FireWire {
+                reserved: read.read_u32::<LittleEndian>()?,
+                guid: read.read_u64::<LittleEndian>()?,
+            }),
+            Self::I2O_SUBTYPE => Ok(MessagingDevicePath::I2O { tid: read.read_u32::<LittleEndian>()? }),
+            Self::MAC_ADDRESS_SUBTYPE => {
+                let mut address = [0u8; 32];
+                read.read_exact(&mut address)?;
+                Ok(MessagingDevicePath::MacAddress { address, if_type: read.read_u8()? })
+            }
+            Self::UART_SUBTYPE => Ok(MessagingDevicePath::Uart {
+                reserved: read.read_u32::<LittleEndian>()?,
+                baud_rate: read.read_u64::<LittleEndian>()?,
+                data_bits: read.read_u8()?,
+                parity: read.read_u8()?,
+                stop_bits: read.read_u8()?,
+            }),
+            Self::USB_CLASS_SUBTYPE => Ok(MessagingDevicePath::UsbClass {
+                vendor_id: read.read_u16::<LittleEndian>()?,
+                product_id: read.read_u16::<LittleEndian>()?,
+                device_class: read.read_u8()?,
+                device_subclass: read.read_u8()?,
+                device_protocol: read.read_u8()?,
+            }),
+            Self::SATA_SUBTYPE => Ok(MessagingDevicePath::Sata {
+                hba_port: read.read_u16::<LittleEndian>()?,
+                port_multiplier_port: read.read_u16::<LittleEndian>()?,
+                lun: read.read_u16::<LittleEndian>()?,
+            }),
+            Self::VLAN_SUBTYPE => Ok(MessagingDevicePath::Vlan(read.read_u16::<LittleEndian>()?)),
+            Self::NVME_SUBTYPE => {
+                let namespace_id = read.read_u32::<LittleEndian>()?;
+                let mut eui64 = [0u8; 8];
+                read.read_exact(&mut eui64)?;
+                Ok(MessagingDevicePath::Nvme { namespace_id, eui64 })
+            }
+            Self::FIBRE_CHANNEL_EX_SUBTYPE => {
+                let reserved = read.read_u32::<LittleEndian>()?;
+                let mut wwn = [0u8; 8];
+                read.read_exact(&mut wwn)?;
+                let mut lun = [0u8; 8];
+                read.read_exact(&mut lun)?;
+                Ok(MessagingDevicePath::FibreChannelEx { reserved, wwn, lun })
+            }
+            Self::BLUETOOTH_SUBTYPE => {
+                let mut address = [0u8; 6];
+                read.read_exact(&mut address)?;
+                Ok(MessagingDevicePath::Bluetooth { address })
+            }
+            _ => Err(DevicePathProtocolParseError::UnknownSubType { typ: "MessagingDevicePath", sub_type }),
+        }
+    }
+
+    pub fn write(&self, write: &mut impl Write) -> io::Result<()> {
+        match self {
+            MessagingDevicePath::FibreChannel { reserved, wwn, lun } => {
+                write.write_u32::<LittleEndian>(*reserved)?;
+                write.write_u64::<LittleEndian>(*wwn)?;
+                write.write_u64::<LittleEndian>(*lun)?;
+            }
+            MessagingDevicePath::FireWire { reserved, guid } => {
+                write.write_u32::<LittleEndian>(*reserved)?;
+                write.write_u64::<LittleEndian>(*guid)?;
+            }
+            MessagingDevicePath::I2O { tid } => write.write_u32::<LittleEndian>(*tid)?,
+            MessagingDevicePath::MacAddress { address, if_type } => {
+                write.write_all(address)?;
+                write.write_u8(*if_type)?;
+            }
+            MessagingDevicePath::Uart { reserved, baud_rate, data_bits, parity, stop_bits } => {
+                write.write_u32::<LittleEndian>(*reserved)?;
+                write.write_u64::<LittleEndian>(*baud_rate)?;
+                write.write_u8(*data_bits)?;
+                write.write_u8(*parity)?;
+                write.write_u8(*stop_bits)?;
+            }
+            MessagingDevicePath::UsbClass { vendor_id, product_id, device_class, device_subclass, device_protocol } => {
+                write.write_u16::<LittleEndian>(*vendor_id)?;
+                write.write_u16::<LittleEndian>(*product_id)?;
+                write.write_u8(*device_class)?;
+                write.write_u8(*device_subclass)?;
+                write.write_u8(*device_protocol)?;
+            }
+            MessagingDevicePath::Sata { hba_port, port_multiplier_port, lun } => {
+                write.write_u16::<LittleEndian>(*hba_port)?;
+                write.write_u16::<LittleEndian>(*port_multiplier_port)?;
+                write.write_u16::<LittleEndian>(*lun)?;
+            }
+            MessagingDevicePath::Vlan(vlan_id) => write.write_u16::<LittleEndian>(*vlan_id)?,
+            MessagingDevicePath::Nvme { namespace_id, eui64 } => {
+                write.write_u32::<LittleEndian>(*namespace_id)?;
+                write.write_all(eui64)?;
+            }
+            MessagingDevicePath::FibreChannelEx { reserved, wwn, lun } => {
+                write.write_u32::<LittleEndian>(*reserved)?;
+                write.write_all(wwn)?;
+                write.write_all(lun)?;
+            }
+            MessagingDevicePath::Bluetooth { address } => write.write_all(address)?,
+        }
 
         Ok(())
     }
+
+    pub fn size(&self) -> usize {
+        match self {
+            MessagingDevicePath::FibreChannel { .. } => 4 + 8 + 8,
+            MessagingDevicePath::FireWire { .. } => 4 + 8,
+            MessagingDevicePath::I2O { .. } => 4,
+            MessagingDevicePath::MacAddress { .. } => 32 + 1,
+            MessagingDevicePath::Uart { .. } => 4 + 8 + 1 + 1 + 1,
+            MessagingDevicePath::UsbClass { .. } => 2 + 2 + 1 + 1 + 1,
+            MessagingDevicePath::Sata { .. } => 2 + 2 + 2,
+            MessagingDevicePath::Vlan(_) => 2,
+            MessagingDevicePath::Nvme { .. } => 4 + 8,
+            MessagingDevicePath::FibreChannelEx { .. } => 4 + 8 + 8,
+            MessagingDevicePath::Bluetooth { .. } => 6,
+        }
+    }
+
+    pub fn sub_type(&self) -> u8 {
+        match self {
+            MessagingDevicePath::FibreChannel { .. } => Self::FIBRE_CHANNEL_SUBTYPE,
+            MessagingDevicePath::FireWire { .. } => Self::FIREWIRE_SUBTYPE,
+            MessagingDevicePath::I2O { .. } => Self::I2O_SUBTYPE,
+            MessagingDevicePath::MacAddress { .. } => Self::MAC_ADDRESS_SUBTYPE,
+            MessagingDevicePath::Uart { .. } => Self::UART_SUBTYPE,
+            MessagingDevicePath::UsbClass { .. } => Self::USB_CLASS_SUBTYPE,
+            MessagingDevicePath::Sata { .. } => Self::SATA_SUBTYPE,
+            MessagingDevicePath::Vlan(_) => Self::VLAN_SUBTYPE,
+            MessagingDevicePath::Nvme { .. } => Self::NVME_SUBTYPE,
+            MessagingDevicePath::FibreChannelEx { .. } => Self::FIBRE_CHANNEL_EX_SUBTYPE,
+            MessagingDevicePath::Bluetooth { .. } => Self::BLUETOOTH_SUBTYPE,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum MediaDevicePath {
     HardDrive(HardDriveDevicePath) = MediaDevicePath::HARD_DRIVE_SUBTYPE,
+    CdRom(CdRomDevicePath) = MediaDevicePath::CDROM_SUBTYPE,
+    Vendor(VendorMediaDevicePath) = MediaDevicePath::VENDOR_SUBTYPE,
     FilePath(FilePathDevicePath) = MediaDevicePath::FILEPATH_SUBTYPE,
 }
 
@@ -117,9 +834,12 @@ impl MediaDevicePath {
     const RELATIVE_OFFSET_RANGE_SUBTYPE: u8 = 0x08;
     const RAM_DISK_SUBTYPE: u8 = 0x09;
 
-    pub fn parse(sub_type: u8, read: &mut impl Read) -> Result<Self> {
+    /// Parses the node data following the header, `length` bytes of it.
+    pub fn parse(sub_type: u8, length: u16, read: &mut impl Read) -> Result<Self> {
         match sub_type {
             Self::HARD_DRIVE_SUBTYPE => Ok(MediaDevicePath::HardDrive(HardDriveDevicePath::parse(read)?)),
+            Self::CDROM_SUBTYPE => Ok(MediaDevicePath::CdRom(CdRomDevicePath::parse(read)?)),
+            Self::VENDOR_SUBTYPE => Ok(MediaDevicePath::Vendor(VendorMediaDevicePath::parse(length, read)?)),
             Self::FILEPATH_SUBTYPE => Ok(MediaDevicePath::FilePath(FilePathDevicePath::parse(read)?)),
             _ => Err(DevicePathProtocolParseError::UnknownSubType { typ: "MediaDevicePath", sub_type }),
         }
@@ -128,31 +848,58 @@ impl MediaDevicePath {
     pub fn write(&self, write: &mut impl Write) -> io::Result<()> {
         match self {
             MediaDevicePath::HardDrive(value) => value.write(write)?,
-            MediaDevicePath::FilePath(FilePathDevicePath { path_name }) => {
-                let path_name = path_name.encode_utf16().chain([0x0000]).collect::<Vec<_>>();
-                write.write_all(cast_slice(path_name.as_slice()))?;
-            }
+            MediaDevicePath::CdRom(value) => value.write(write)?,
+            MediaDevicePath::Vendor(value) => value.write(write)?,
+            MediaDevicePath::FilePath(value) => value.write(write)?,
         }
 
         Ok(())
     }
 
-    pub fn size(&self) -> u16 {
+    pub fn size(&self) -> usize {
         match self {
             MediaDevicePath::HardDrive(HardDriveDevicePath { .. }) => 4 + 8 + 8 + 16 + 1 + 1, // 32+64+64+8*16+8+8
-            MediaDevicePath::FilePath(FilePathDevicePath { path_name }) => path_name.encode_utf16().chain([0x0000]).count() as u16 * 2,
+            MediaDevicePath::CdRom(CdRomDevicePath { .. }) => 4 + 8 + 8, // 32+64+64
+            MediaDevicePath::Vendor(value) => 16 + value.data.len(), // 8*16+data
+            MediaDevicePath::FilePath(value) => value.size(),
         }
     }
 
     pub fn sub_type(&self) -> u8 {
         match self {
             MediaDevicePath::HardDrive(_) => Self::HARD_DRIVE_SUBTYPE,
+            MediaDevicePath::CdRom(_) => Self::CDROM_SUBTYPE,
+            MediaDevicePath::Vendor(_) => Self::VENDOR_SUBTYPE,
             MediaDevicePath::FilePath(_) => Self::FILEPATH_SUBTYPE,
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+impl Display for MediaDevicePath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MediaDevicePath::HardDrive(hard_drive) => {
+                write!(f, "HD({},{},", hard_drive.partition_number, hard_drive.partition_table_name())?;
+                match &hard_drive.signature {
+                    Signature::None(_) => f.write_str("0")?,
+                    Signature::MBRSignature(data) => write!(f, "0x{:08x}", u32::from_le_bytes([data[0], data[1], data[2], data[3]]))?,
+                    Signature::GUID(uuid) => write!(f, "{}", uuid)?,
+                }
+                write!(f, ",0x{:x},0x{:x})", hard_drive.partition_start, hard_drive.partition_size)
+            }
+            MediaDevicePath::CdRom(cdrom) => write!(f, "CDROM(0x{:x},0x{:x},0x{:x})", cdrom.boot_entry, cdrom.partition_start, cdrom.partition_size),
+            MediaDevicePath::Vendor(vendor) if vendor.data.is_empty() => write!(f, "VenMedia({})", vendor.vendor),
+            MediaDevicePath::Vendor(vendor) => {
+                let data = vendor.data.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+                write!(f, "VenMedia({},{})", vendor.vendor, data)
+            }
+            MediaDevicePath::FilePath(file_path) => write!(f, "File({})", file_path.path_name),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HardDriveDevicePath {
     partition_number: u32,
     partition_start: u64,
@@ -161,6 +908,18 @@ pub struct HardDriveDevicePath {
     partition_table: PartitionTableType,
 }
 
+impl Debug for HardDriveDevicePath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HD(part={}, {:?}, sig=", self.partition_number, self.partition_table)?;
+        match &self.signature {
+            Signature::None(_) => f.write_str("none")?,
+            Signature::MBRSignature(data) => write!(f, "0x{:08X}", u32::from_le_bytes([data[0], data[1], data[2], data[3]]))?,
+            Signature::GUID(uuid) => write!(f, "{}", uuid)?,
+        }
+        write!(f, ", start=0x{:X}, size=0x{:X})", self.partition_start, self.partition_size)
+    }
+}
+
 impl HardDriveDevicePath {
     pub fn new_gpt(partition_number: u32, partition_start: u64, partition_size: u64, uuid: Uuid) -> Self {
         HardDriveDevicePath {
@@ -172,6 +931,30 @@ impl HardDriveDevicePath {
         }
     }
 
+    /// The 32-bit disk signature from offset 0x1B8 of the MBR is stored little-endian in the first
+    /// 4 bytes of the 16-byte signature field, the rest is zeroed.
+    pub fn new_mbr(partition_number: u32, partition_start: u64, partition_size: u64, signature: u32) -> Self {
+        let mut data = [0u8; 16];
+        data[..4].copy_from_slice(&signature.to_le_bytes());
+        HardDriveDevicePath {
+            partition_number,
+            partition_start,
+            partition_size,
+            signature: Signature::MBRSignature(data),
+            partition_table: PartitionTableType::MBR,
+        }
+    }
+
+    /// Reads the disk's GPT and builds the node for the partition with the number, as
+    /// [HardDriveDevicePath::new_gpt] would for the same partition. `disk` is the whole disk, like
+    /// `/dev/nvme0n1` or an image of it, not the partition itself.
+    pub fn from_gpt_partition(disk: &mut (impl Read + Seek), partition_number: u32) -> std::result::Result<Self, GptError> {
+        GuidPartitionTable::read(disk)?
+            .partition(partition_number)
+            .map(GptPartition::hard_drive_device_path)
+            .ok_or(GptError::NoSuchPartition(partition_number))
+    }
+
     pub fn parse(read: &mut impl Read) -> Result<Self> {
         use DevicePathProtocolParseError::ParseSubType;
 
@@ -218,9 +1001,131 @@ impl HardDriveDevicePath {
 
         Ok(())
     }
+
+    pub fn partition_number(&self) -> u32 {
+        self.partition_number
+    }
+
+    /// First sector of the partition, in logical blocks of the disk.
+    pub fn partition_start(&self) -> u64 {
+        self.partition_start
+    }
+
+    /// Size of the partition in logical blocks.
+    pub fn partition_size(&self) -> u64 {
+        self.partition_size
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    pub fn partition_table(&self) -> PartitionTableType {
+        self.partition_table
+    }
+
+    pub fn is_gpt(&self) -> bool {
+        self.partition_table == PartitionTableType::GPT
+    }
+
+    /// `GPT` or `MBR`, for labels like "GPT Partition 1".
+    pub fn partition_table_name(&self) -> &'static str {
+        match self.partition_table {
+            PartitionTableType::MBR => "MBR",
+            PartitionTableType::GPT => "GPT",
+        }
+    }
+}
+
+/// El Torito boot image on a CD-ROM or DVD, the partition is given in 2048 byte sectors.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CdRomDevicePath {
+    /// Entry in the El Torito boot catalog, 0 for the initial/default entry.
+    boot_entry: u32,
+    partition_start: u64,
+    partition_size: u64,
+}
+
+impl CdRomDevicePath {
+    pub fn new(boot_entry: u32, partition_start: u64, partition_size: u64) -> Self {
+        CdRomDevicePath { boot_entry, partition_start, partition_size }
+    }
+
+    pub fn parse(read: &mut impl Read) -> Result<Self> {
+        Ok(CdRomDevicePath {
+            boot_entry: read.read_u32::<LittleEndian>()?,
+            partition_start: read.read_u64::<LittleEndian>()?,
+            partition_size: read.read_u64::<LittleEndian>()?,
+        })
+    }
+
+    pub fn write(&self, write: &mut impl Write) -> io::Result<()> {
+        write.write_u32::<LittleEndian>(self.boot_entry)?;
+        write.write_u64::<LittleEndian>(self.partition_start)?;
+        write.write_u64::<LittleEndian>(self.partition_size)?;
+
+        Ok(())
+    }
+
+    pub fn boot_entry(&self) -> u32 {
+        self.boot_entry
+    }
+
+    pub fn partition_start(&self) -> u64 {
+        self.partition_start
+    }
+
+    pub fn partition_size(&self) -> u64 {
+        self.partition_size
+    }
+}
+
+/// Media defined by a vendor, identified by its GUID and followed by data only the vendor knows
+/// how to interpret.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VendorMediaDevicePath {
+    vendor: Uuid,
+    data: Vec<u8>,
+}
+
+impl VendorMediaDevicePath {
+    pub fn new(vendor: Uuid, data: Vec<u8>) -> Self {
+        VendorMediaDevicePath { vendor, data }
+    }
+
+    pub fn parse(length: u16, read: &mut impl Read) -> Result<Self> {
+        // a node too short for the GUID fails as invalid data, so it's kept as an unknown node
+        let data_length = (length as usize).checked_sub(16)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("node too short for the vendor GUID, node data is {length} bytes")))?;
+
+        let mut vendor = [0u8; 16];
+        read.read_exact(&mut vendor)?;
+        let mut data = vec![0u8; data_length];
+        read.read_exact(&mut data)?;
+
+        Ok(VendorMediaDevicePath { vendor: Uuid::from_bytes_le(vendor), data })
+    }
+
+    pub fn write(&self, write: &mut impl Write) -> io::Result<()> {
+        write.write_all(&self.vendor.to_bytes_le())?;
+        write.write_all(&self.data)?;
+
+        Ok(())
+    }
+
+    pub fn vendor(&self) -> &Uuid {
+        &self.vendor
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, TryFromPrimitive, IntoPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum PartitionTableType {
     MBR = 0x01,
@@ -228,10 +1133,12 @@ pub enum PartitionTableType {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Signature {
     None([u8; 16]) = Signature::NO_SIGNATURE,
-    /// 32-bit signature from address 0x1b8 of the type 0x01 MBR.
+    /// 32-bit signature from address 0x1b8 of the type 0x01 MBR, little-endian in the first 4 bytes
+    /// followed by zeros.
     MBRSignature([u8; 16]) = Signature::MBR_SIGNATURE,
     GUID(Uuid) = Signature::GUID_SIGNATURE,
 }
@@ -252,7 +1159,93 @@ impl Signature {
     const GUID_SIGNATURE: u8 = 0x02;
 }
 
+/// Architectures with a removable media fallback bootloader defined by the UEFI spec.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Arch {
+    Ia32,
+    X64,
+    Ia64,
+    Arm,
+    Aa64,
+    RiscV64,
+    LoongArch64,
+}
+
+impl Arch {
+    pub const ALL: [Arch; 7] = [Arch::Ia32, Arch::X64, Arch::Ia64, Arch::Arm, Arch::Aa64, Arch::RiscV64, Arch::LoongArch64];
+
+    /// The architecture this is built for.
+    pub const NATIVE: Option<Arch> = if cfg!(target_arch = "x86_64") {
+        Some(Arch::X64)
+    } else if cfg!(target_arch = "x86") {
+        Some(Arch::Ia32)
+    } else if cfg!(target_arch = "aarch64") {
+        Some(Arch::Aa64)
+    } else if cfg!(target_arch = "arm") {
+        Some(Arch::Arm)
+    } else if cfg!(target_arch = "riscv64") {
+        Some(Arch::RiscV64)
+    } else if cfg!(target_arch = "loongarch64") {
+        Some(Arch::LoongArch64)
+    } else {
+        None
+    };
+
+    /// Suffix of the fallback bootloader, like `X64` in `BOOTX64.EFI`.
+    pub const fn suffix(self) -> &'static str {
+        match self {
+            Arch::Ia32 => "IA32",
+            Arch::X64 => "X64",
+            Arch::Ia64 => "IA64",
+            Arch::Arm => "ARM",
+            Arch::Aa64 => "AA64",
+            Arch::RiscV64 => "RISCV64",
+            Arch::LoongArch64 => "LOONGARCH64",
+        }
+    }
+}
+
+/// Architecture suffix of the fallback bootloader for the architecture this is built for.
+pub const NATIVE_FALLBACK_ARCHITECTURE: Option<&str> = match Arch::NATIVE {
+    Some(arch) => Some(arch.suffix()),
+    None => None,
+};
+
+/// File name of the removable media fallback bootloader for the architecture, like
+/// `BOOTAA64.EFI`. It lives in `\EFI\BOOT` on the ESP.
+pub const fn default_loader_name_for(arch: Arch) -> &'static str {
+    match arch {
+        Arch::Ia32 => "BOOTIA32.EFI",
+        Arch::X64 => "BOOTX64.EFI",
+        Arch::Ia64 => "BOOTIA64.EFI",
+        Arch::Arm => "BOOTARM.EFI",
+        Arch::Aa64 => "BOOTAA64.EFI",
+        Arch::RiscV64 => "BOOTRISCV64.EFI",
+        Arch::LoongArch64 => "BOOTLOONGARCH64.EFI",
+    }
+}
+
+/// Like [default_loader_name_for], for the architecture this is built for. Only available when
+/// building for an architecture in [Arch].
+#[cfg(any(target_arch = "x86_64", target_arch = "x86", target_arch = "aarch64", target_arch = "arm", target_arch = "riscv64", target_arch = "loongarch64"))]
+pub const fn default_loader_name() -> &'static str {
+    match Arch::NATIVE {
+        Some(arch) => default_loader_name_for(arch),
+        None => unreachable!(),
+    }
+}
+
+/// Uppercases the path and normalizes it to backslashes with a leading backslash.
+fn canonical_path(path: &str) -> String {
+    let mut canonical = path.replace('/', "\\").to_uppercase();
+    if !canonical.starts_with('\\') {
+        canonical.insert(0, '\\');
+    }
+    canonical
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FilePathDevicePath {
     path_name: String,
 }
@@ -273,9 +1266,74 @@ impl FilePathDevicePath {
             .map_err(|err| ParseSubType { sub_type: "FilePathDevicePath".to_owned(), message: "parse utf-16".to_owned(), source: Some(Box::new(err)) })?;
         Ok(FilePathDevicePath { path_name })
     }
+
+    /// Converts a path to a file on the ESP, like `/boot/EFI/Linux/arch.efi`, to the backslash
+    /// separated path that UEFI expects. Absolute paths have to be under one of the usual ESP mount
+    /// points and are made relative to it, relative paths are taken as relative to the root of the
+    /// ESP and normalized like [FilePathDevicePath::from_esp_path].
+    pub fn from_os_path(path: &Path) -> std::result::Result<Self, NotOnEspError> {
+        let relative = match path.is_absolute() {
+            true => ESP_MOUNT_POINTS.iter()
+                .find_map(|mount_point| path.strip_prefix(mount_point).ok())
+                .ok_or_else(|| NotOnEspError(path.to_owned()))?,
+            false => path,
+        };
+        Ok(Self::from_esp_path(&relative.to_string_lossy()))
+    }
+
+    /// Normalizes a path relative to the root of the ESP to the backslash separated path with a
+    /// single leading backslash that UEFI expects. Both slashes and backslashes separate
+    /// components, and duplicate and trailing separators and `.` components are dropped.
+    pub fn from_esp_path(path: &str) -> Self {
+        let mut path_name = path
+            .split(['/', '\\'])
+            .filter(|component| !component.is_empty() && *component != ".")
+            .fold(String::new(), |mut path_name, component| {
+                path_name.push('\\');
+                path_name.push_str(component);
+                path_name
+            });
+        if path_name.is_empty() {
+            path_name.push('\\');
+        }
+        FilePathDevicePath { path_name }
+    }
+
+    pub fn path_name(&self) -> &str {
+        &self.path_name
+    }
+
+    /// The architecture suffix if the path points at the removable media fallback bootloader
+    /// `\EFI\BOOT\BOOT<arch>.EFI`, ignoring case and slash direction.
+    pub fn fallback_architecture(&self) -> Option<&'static str> {
+        let architecture = canonical_path(&self.path_name);
+        let architecture = architecture.strip_prefix("\\EFI\\BOOT\\BOOT")?.strip_suffix(".EFI")?;
+        Arch::ALL.into_iter().map(Arch::suffix).find(|other| *other == architecture)
+    }
+
+    /// Suggests the conventional uppercase `\EFI\BOOT\BOOT<arch>.EFI` spelling if the path points
+    /// at the removable media fallback bootloader, but is spelled differently. FAT is case
+    /// insensitive, but some firmware only finds the fallback path in uppercase. Returns `None`
+    /// for any other path, or if the path is already canonical.
+    pub fn suggest_canonical(&self) -> Option<String> {
+        self.fallback_architecture()?;
+        let canonical = canonical_path(&self.path_name);
+        (canonical != self.path_name).then_some(canonical)
+    }
+
+    /// Size of the path as NUL-terminated UTF-16. Paths longer than [MAX_NODE_DATA_SIZE] bytes
+    /// don't fit in a device path node and fail to [EFIDevicePathProtocol::write].
+    pub fn size(&self) -> usize {
+        (self.path_name.encode_utf16().count() + 1) * 2
+    }
+
+    pub fn write(&self, write: &mut impl Write) -> io::Result<()> {
+        write.write_all(cast_slice(encode_utf16_nul(&self.path_name).as_slice()))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, IntoPrimitive, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum EndSubType {
     EndEntireDevicePath = EndSubType::END_ENTIRE_DEVICE_PATH,
@@ -293,3 +1351,562 @@ impl EndSubType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::io::Cursor;
+    use std::path::Path;
+    use std::str::FromStr;
+    use uuid::Uuid;
+    use crate::efidevicepath::{AcpiDevicePath, Arch, CdRomDevicePath, default_loader_name_for, DeviceKind, DisplayDevicePath, DevicePathProtocolParseError, EFIDevicePathProtocol, EndSubType, FilePathDevicePath, HardDriveDevicePath, HardwareDevicePath, MAX_NODE_DATA_SIZE, MediaDevicePath, MessagingDevicePath, NotOnEspError, PartitionTableType, Signature, UnknownDevicePath, VendorMediaDevicePath};
+
+    /// Asserts [EFIDevicePathProtocol::size] agrees with the number of bytes
+    /// [EFIDevicePathProtocol::write] emits, as a mismatch corrupts the file path list length.
+    fn assert_size_matches_write(node: &EFIDevicePathProtocol) {
+        let mut written = vec![];
+        node.write(&mut written).unwrap();
+        assert_eq!(Some(written.len()), node.size().map(usize::from), "size() disagrees with write() for {node:?}");
+    }
+
+    /// Parses a single device path node and asserts it's written back byte-for-byte.
+    fn round_trip(bytes: &[u8]) -> EFIDevicePathProtocol {
+        let parsed = EFIDevicePathProtocol::parse(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(Some(bytes.len()), parsed.size().map(usize::from));
+        assert_size_matches_write(&parsed);
+
+        let mut written = vec![];
+        parsed.write(&mut written).unwrap();
+        assert_eq!(bytes, written.as_slice());
+
+        parsed
+    }
+
+    #[test]
+    fn test_size_matches_write() {
+        let nodes = [
+            EFIDevicePathProtocol::HardwareDevicePath(HardwareDevicePath::Pci { function: 0, device: 0x1D }),
+            EFIDevicePathProtocol::HardwareDevicePath(HardwareDevicePath::PcCard { function_number: 1 }),
+            EFIDevicePathProtocol::HardwareDevicePath(HardwareDevicePath::Bmc { interface_type: 1, base_address: 0xCA2 }),
+            EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Acpi { hid: 0x0A0341D0, uid: 0 }),
+            EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Expanded {
+                hid: 0x0A0341D0,
+                uid: 0,
+                cid: 0,
+                hid_str: "PNP0A08".to_owned(),
+                uid_str: String::new(),
+                cid_str: "PNP0A03".to_owned(),
+            }),
+            EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Adr(vec![0x80010100, 0x80010200])),
+            EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Nvdimm { nfit_device_handle: 0x1 }),
+            EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::FibreChannel { reserved: 0, wwn: 1, lun: 2 }),
+            EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::FireWire { reserved: 0, guid: 1 }),
+            EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::I2O { tid: 1 }),
+            EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::Uart { reserved: 0, baud_rate: 115200, data_bits: 8, parity: 1, stop_bits: 1 }),
+            EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::Vlan(10)),
+            EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::Nvme { namespace_id: 1, eui64: [0; 8] }),
+            EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::FibreChannelEx { reserved: 0, wwn: [1; 8], lun: [2; 8] }),
+            EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::Bluetooth { address: [0; 6] }),
+            EFIDevicePathProtocol::new_hard_drive_gpt(1, 0x800, 0x100000, Uuid::from_str("eba9a856-dfdd-42eb-be76-31760ae90f55").unwrap()),
+            EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::CdRom(CdRomDevicePath::new(0, 0x1C, 0x1680))),
+            EFIDevicePathProtocol::new_file_path("\\EFI\\BOOT\\BOOTX64.EFI"),
+            EFIDevicePathProtocol::new_file_path(""),
+            EFIDevicePathProtocol::End(EndSubType::EndInstanceDevicePath),
+            EFIDevicePathProtocol::new_end_entire(),
+            EFIDevicePathProtocol::Unknown(UnknownDevicePath { typ: 0x05, sub_type: 0x01, data: vec![0x01, 0x02, 0x03] }),
+        ];
+
+        for node in &nodes {
+            assert_size_matches_write(node);
+        }
+    }
+
+    #[test]
+    fn test_firewire_round_trip() {
+        let bytes = [
+            0x03, 0x04, 0x10, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11,
+        ];
+
+        let expected = EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::FireWire { reserved: 0, guid: 0x1122334455667788 });
+        assert_eq!(expected, round_trip(&bytes));
+    }
+
+    #[test]
+    fn test_i2o_round_trip() {
+        let bytes = [0x03, 0x06, 0x08, 0x00, 0x2A, 0x00, 0x00, 0x00];
+
+        let expected = EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::I2O { tid: 42 });
+        assert_eq!(expected, round_trip(&bytes));
+    }
+
+    #[test]
+    fn test_uart_round_trip() {
+        let bytes = [
+            0x03, 0x0E, 0x13, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0xC2, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x08, 0x01, 0x01,
+        ];
+
+        let uart = MessagingDevicePath::Uart { reserved: 0, baud_rate: 115200, data_bits: 8, parity: 1, stop_bits: 1 };
+        assert_eq!(EFIDevicePathProtocol::MessagingDevicePath(uart.clone()), round_trip(&bytes));
+        assert_eq!("Uart(115200,8,N,1)", uart.to_string());
+    }
+
+    #[test]
+    fn test_sata_sentinel_round_trip() {
+        let bytes = [0x03, 0x12, 0x0A, 0x00, 0x01, 0x00, 0xFF, 0xFF, 0x00, 0x00];
+
+        let node = round_trip(&bytes);
+        let sata = MessagingDevicePath::Sata { hba_port: 1, port_multiplier_port: 0xFFFF, lun: 0 };
+        assert_eq!(EFIDevicePathProtocol::MessagingDevicePath(sata.clone()), node);
+        assert_eq!("Sata(1,-,0)", sata.to_string());
+
+        let mut written = vec![];
+        node.write(&mut written).unwrap();
+        assert_eq!(&bytes[..], written.as_slice());
+    }
+
+    #[test]
+    fn test_usb_class_sentinel_round_trip() {
+        let bytes = [0x03, 0x0F, 0x0B, 0x00, 0x6B, 0x1D, 0xFF, 0xFF, 0x08, 0xFF, 0xFF];
+
+        let node = round_trip(&bytes);
+        let usb_class = MessagingDevicePath::UsbClass { vendor_id: 0x1D6B, product_id: 0xFFFF, device_class: 0x08, device_subclass: 0xFF, device_protocol: 0xFF };
+        assert_eq!(EFIDevicePathProtocol::MessagingDevicePath(usb_class.clone()), node);
+        assert_eq!("UsbMassStorage(0x1d6b,-,-,-)", usb_class.to_string());
+        assert_eq!(Some(DeviceKind::Usb), node.device_kind());
+
+        let mut written = vec![];
+        node.write(&mut written).unwrap();
+        assert_eq!(&bytes[..], written.as_slice());
+    }
+
+    #[test]
+    fn test_usb_class_round_trip() {
+        let bytes = [0x03, 0x0F, 0x0B, 0x00, 0x81, 0x07, 0x81, 0x55, 0xFE, 0x01, 0x02];
+
+        let usb_class = MessagingDevicePath::UsbClass { vendor_id: 0x0781, product_id: 0x5581, device_class: 0xFE, device_subclass: 0x01, device_protocol: 0x02 };
+        assert_eq!(EFIDevicePathProtocol::MessagingDevicePath(usb_class.clone()), round_trip(&bytes));
+        assert_eq!("UsbClass(0x0781,0x5581,0xfe,0x01,0x02)", usb_class.to_string());
+
+        let hid = MessagingDevicePath::UsbClass { vendor_id: 0xFFFF, product_id: 0xFFFF, device_class: 0x03, device_subclass: 0x01, device_protocol: 0x01 };
+        assert_eq!("UsbHID(-,-,0x01,0x01)", hid.to_string());
+    }
+
+    #[test]
+    fn test_vlan_round_trip() {
+        let bytes = [0x03, 0x14, 0x06, 0x00, 0x64, 0x00];
+
+        let expected = EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::Vlan(100));
+        assert_eq!(expected, round_trip(&bytes));
+        assert_eq!("Vlan(100)", MessagingDevicePath::Vlan(100).to_string());
+    }
+
+    #[test]
+    fn test_fibre_channel_round_trip() {
+        let bytes = [
+            0x03, 0x03, 0x18, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x2E, 0x04, 0x1F, 0xC9, 0x00, 0x11, 0x00, 0x50,
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let expected = EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::FibreChannel {
+            reserved: 0,
+            wwn: 0x50001100C91F042E,
+            lun: 1,
+        });
+        assert_eq!(expected, round_trip(&bytes));
+    }
+
+    #[test]
+    fn test_fibre_channel_ex_round_trip() {
+        let bytes = [
+            0x03, 0x15, 0x18, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x50, 0x00, 0x11, 0x00, 0xC9, 0x1F, 0x04, 0x2E,
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let expected = EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::FibreChannelEx {
+            reserved: 0,
+            wwn: [0x50, 0x00, 0x11, 0x00, 0xC9, 0x1F, 0x04, 0x2E],
+            lun: [0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        });
+        assert_eq!(expected, round_trip(&bytes));
+    }
+
+    #[test]
+    fn test_pci_round_trip() {
+        let bytes = [0x01, 0x01, 0x06, 0x00, 0x02, 0x1D];
+
+        let pci = HardwareDevicePath::Pci { function: 0x02, device: 0x1D };
+        assert_eq!(EFIDevicePathProtocol::HardwareDevicePath(pci.clone()), round_trip(&bytes));
+        assert_eq!("Pci(0x1d,0x2)", pci.to_string());
+    }
+
+    #[test]
+    fn test_pccard_round_trip() {
+        let bytes = [0x01, 0x02, 0x05, 0x00, 0x01];
+
+        let pccard = HardwareDevicePath::PcCard { function_number: 0x01 };
+        assert_eq!(EFIDevicePathProtocol::HardwareDevicePath(pccard.clone()), round_trip(&bytes));
+        assert_eq!("PcCard(0x1)", pccard.to_string());
+    }
+
+    #[test]
+    fn test_bmc_round_trip() {
+        let bytes = [
+            0x01, 0x06, 0x0D, 0x00,
+            0x01,
+            0xA2, 0x0C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let bmc = HardwareDevicePath::Bmc { interface_type: 0x01, base_address: 0xCA2 };
+        assert_eq!(EFIDevicePathProtocol::HardwareDevicePath(bmc.clone()), round_trip(&bytes));
+        assert_eq!("BMC(0x1,0xca2)", bmc.to_string());
+    }
+
+    #[test]
+    fn test_nvme_round_trip() {
+        let bytes = [
+            0x03, 0x17, 0x10, 0x00,
+            0x01, 0x00, 0x00, 0x00,
+            0x00, 0x25, 0x38, 0x5B, 0x21, 0xB0, 0x12, 0x34,
+        ];
+
+        let nvme = MessagingDevicePath::Nvme { namespace_id: 1, eui64: [0x00, 0x25, 0x38, 0x5B, 0x21, 0xB0, 0x12, 0x34] };
+        assert_eq!(EFIDevicePathProtocol::MessagingDevicePath(nvme.clone()), round_trip(&bytes));
+        assert_eq!("NVMe(0x1,00-25-38-5B-21-B0-12-34)", nvme.to_string());
+    }
+
+    #[test]
+    fn test_mac_address_round_trip() {
+        // PXE boot entry of a QEMU virtio NIC
+        let mut bytes = vec![0x03, 0x0B, 0x25, 0x00, 0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+        bytes.extend_from_slice(&[0x00; 26]);
+        bytes.push(0x01);
+
+        let mut address = [0u8; 32];
+        address[..6].copy_from_slice(&[0x52, 0x54, 0x00, 0x12, 0x34, 0x56]);
+        let mac = MessagingDevicePath::MacAddress { address, if_type: 1 };
+        let node = round_trip(&bytes);
+        assert_eq!(EFIDevicePathProtocol::MessagingDevicePath(mac.clone()), node);
+        assert_eq!(Some(DeviceKind::Network), node.device_kind());
+        assert_eq!("MAC(525400123456,1)", mac.to_string());
+    }
+
+    #[test]
+    fn test_bluetooth_round_trip() {
+        let bytes = [0x03, 0x1B, 0x0A, 0x00, 0x00, 0x1A, 0x7D, 0xDA, 0x71, 0x13];
+
+        let address = [0x00, 0x1A, 0x7D, 0xDA, 0x71, 0x13];
+        let expected = EFIDevicePathProtocol::MessagingDevicePath(MessagingDevicePath::Bluetooth { address });
+        assert_eq!(expected, round_trip(&bytes));
+        assert_eq!("Bluetooth(00:1a:7d:da:71:13)", MessagingDevicePath::Bluetooth { address }.to_string());
+    }
+
+    #[test]
+    fn test_acpi_expanded_round_trip() {
+        let bytes = [
+            0x02, 0x02, 0x1E, 0x00,
+            0xD0, 0x41, 0x0A, 0x0A,
+            0x00, 0x00, 0x00, 0x00,
+            0xD0, 0x41, 0x03, 0x0A,
+            0x00,
+            0x55, 0x49, 0x44, 0x31, 0x00,
+            0x50, 0x4E, 0x50, 0x30, 0x41, 0x30, 0x33, 0x00,
+        ];
+
+        let expected = EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Expanded {
+            hid: 0x0A0A41D0,
+            uid: 0,
+            cid: 0x0A0341D0,
+            hid_str: "".to_owned(),
+            uid_str: "UID1".to_owned(),
+            cid_str: "PNP0A03".to_owned(),
+        });
+        assert_eq!(expected, round_trip(&bytes));
+    }
+
+    #[test]
+    fn test_acpi_display() {
+        assert_eq!("Acpi(PNP0A03,0x0)", AcpiDevicePath::Acpi { hid: 0x0A0341D0, uid: 0 }.to_string());
+        assert_eq!("Acpi(PNP0501,0x1)", AcpiDevicePath::Acpi { hid: 0x050141D0, uid: 1 }.to_string());
+        assert_eq!("Acpi(0xdeadbeef,0x0)", AcpiDevicePath::Acpi { hid: 0xDEADBEEF, uid: 0 }.to_string());
+
+        let expanded = AcpiDevicePath::Expanded {
+            hid: 0x080AD041,
+            uid: 0,
+            cid: 0x0A0341D0,
+            hid_str: String::new(),
+            uid_str: String::new(),
+            cid_str: String::new(),
+        };
+        assert_eq!("AcpiEx(0x80ad041,PNP0A03,0x0,,,)", expanded.to_string());
+    }
+
+    #[test]
+    fn test_acpi_adr_round_trip() {
+        let bytes = [
+            0x02, 0x03, 0x0C, 0x00,
+            0x00, 0x01, 0x01, 0x80,
+            0x10, 0x03, 0x00, 0x00,
+        ];
+
+        let expected = EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Adr(vec![0x80010100, 0x00000310]));
+        assert_eq!(expected, round_trip(&bytes));
+    }
+
+    #[test]
+    fn test_acpi_malformed_length() {
+        // an expanded node too short for its three IDs, followed by an end node it must not read
+        let bytes = [0x02, 0x02, 0x08, 0x00, 0xD0, 0x41, 0x03, 0x0A, 0x7F, 0xFF, 0x04, 0x00];
+        let instances = EFIDevicePathProtocol::parse_instances(&mut Cursor::new(bytes)).unwrap();
+        let short = EFIDevicePathProtocol::Unknown(UnknownDevicePath { typ: 0x02, sub_type: 0x02, data: vec![0xD0, 0x41, 0x03, 0x0A] });
+        assert_eq!(vec![vec![short]], instances);
+
+        // an _ADR node with two trailing bytes
+        let bytes = [0x02, 0x03, 0x0A, 0x00, 0x00, 0x01, 0x01, 0x80, 0x10, 0x03];
+        assert!(matches!(round_trip(&bytes), EFIDevicePathProtocol::Unknown(_)));
+
+        let result = AcpiDevicePath::parse(0x03, 6, &mut Cursor::new(&bytes[4..]));
+        assert!(matches!(result, Err(DevicePathProtocolParseError::IoError(ref err)) if err.kind() == io::ErrorKind::InvalidData), "{result:?}");
+    }
+
+    #[test]
+    fn test_acpi_nvdimm_round_trip() {
+        let bytes = [0x02, 0x04, 0x08, 0x00, 0x01, 0x10, 0x00, 0x00];
+
+        let nvdimm = AcpiDevicePath::Nvdimm { nfit_device_handle: 0x1001 };
+        assert_eq!(EFIDevicePathProtocol::AcpiDevicePath(nvdimm.clone()), round_trip(&bytes));
+        assert_eq!("NvdimmAcpiAdr(0x1001)", nvdimm.to_string());
+    }
+
+    #[test]
+    fn test_unknown_round_trip() {
+        // BIOS boot specification node, an unmodeled type
+        let bytes = [0x05, 0x01, 0x0A, 0x00, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(
+            EFIDevicePathProtocol::Unknown(UnknownDevicePath { typ: 0x05, sub_type: 0x01, data: vec![0x06, 0x00, 0x00, 0x00, 0x00, 0x00] }),
+            round_trip(&bytes),
+        );
+
+        // USB node, an unmodeled subtype of a modeled type
+        let bytes = [0x03, 0x05, 0x06, 0x00, 0x02, 0x00];
+        let EFIDevicePathProtocol::Unknown(unknown) = round_trip(&bytes) else { panic!("USB node was modeled") };
+        assert_eq!((0x03, 0x05, &bytes[4..]), (unknown.typ(), unknown.sub_type(), unknown.data()));
+
+        let truncated = [0x05, 0x01, 0x03, 0x00];
+        assert!(EFIDevicePathProtocol::parse(&mut Cursor::new(truncated)).is_err());
+
+        // modeled types are checked too, a zero length would otherwise be ignored by their parsers
+        let zero_length = [0x04, 0x04, 0x00, 0x00, b'a', 0x00, 0x00, 0x00];
+        let result = EFIDevicePathProtocol::parse(&mut Cursor::new(zero_length));
+        assert!(matches!(result, Err(DevicePathProtocolParseError::NodeTooShort { typ: 0x04, sub_type: 0x04, length: 0 })), "{result:?}");
+    }
+
+    #[test]
+    fn test_padded_known_node() {
+        // a PCI node with two bytes of padding, followed by an end node
+        let bytes = [
+            0x01, 0x01, 0x08, 0x00, 0x00, 0x1F, 0xAA, 0xBB,
+            0x7F, 0xFF, 0x04, 0x00,
+        ];
+        let instances = EFIDevicePathProtocol::parse_instances(&mut Cursor::new(bytes)).unwrap();
+
+        let padded = EFIDevicePathProtocol::Unknown(UnknownDevicePath { typ: 0x01, sub_type: 0x01, data: vec![0x00, 0x1F, 0xAA, 0xBB] });
+        assert_eq!(vec![vec![padded]], instances);
+        round_trip(&bytes[..8]);
+    }
+
+    #[test]
+    fn test_short_known_node() {
+        // a NVMe node missing its EUI-64, the end node must not be read as part of it
+        let bytes = [
+            0x03, 0x17, 0x08, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x7F, 0xFF, 0x04, 0x00,
+        ];
+        let instances = EFIDevicePathProtocol::parse_instances(&mut Cursor::new(bytes)).unwrap();
+
+        let short = EFIDevicePathProtocol::Unknown(UnknownDevicePath { typ: 0x03, sub_type: 0x17, data: vec![0x01, 0x00, 0x00, 0x00] });
+        assert_eq!(vec![vec![short]], instances);
+        round_trip(&bytes[..8]);
+    }
+
+    #[test]
+    fn test_file_path_size_boundary() {
+        // the largest path that fits, leaving room for the terminator
+        let largest = EFIDevicePathProtocol::new_file_path("A".repeat(MAX_NODE_DATA_SIZE / 2 - 1));
+        let mut written = vec![];
+        largest.write(&mut written).unwrap();
+        assert_eq!(Some(written.len()), largest.size().map(usize::from));
+        assert_eq!(Some(u16::MAX - 1), largest.size());
+
+        let too_long = EFIDevicePathProtocol::new_file_path("A".repeat(MAX_NODE_DATA_SIZE / 2));
+        assert_eq!(None, too_long.size());
+        let err = too_long.write(&mut vec![]).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+    }
+
+    #[test]
+    fn test_file_path_suggest_canonical() {
+        let path = |path: &str| FilePathDevicePath { path_name: path.to_owned() };
+
+        assert_eq!(Some("\\EFI\\BOOT\\BOOTX64.EFI".to_owned()), path("\\EFI\\Boot\\bootx64.efi").suggest_canonical());
+        assert_eq!(Some("\\EFI\\BOOT\\BOOTX64.EFI".to_owned()), path("EFI/BOOT/BOOTX64.EFI").suggest_canonical());
+        assert_eq!(None, path("\\EFI\\BOOT\\BOOTX64.EFI").suggest_canonical());
+        assert_eq!(None, path("\\EFI\\Linux\\arch-linux.efi").suggest_canonical());
+        assert_eq!(None, path("\\EFI\\BOOT\\BOOTFOO.EFI").suggest_canonical());
+    }
+
+    #[test]
+    fn test_file_path_from_os_path() {
+        let path = |path: &str| FilePathDevicePath::from_os_path(Path::new(path)).unwrap().path_name;
+
+        assert_eq!("\\EFI\\Linux\\arch.efi", path("/boot/EFI/Linux/arch.efi"));
+        assert_eq!("\\EFI\\Linux\\arch.efi", path("/boot/efi/EFI/Linux/arch.efi"));
+        assert_eq!("\\EFI\\Linux\\arch.efi", path("/efi/EFI/Linux/arch.efi"));
+        assert_eq!("\\EFI\\Linux\\arch.efi", path("EFI/Linux/arch.efi"));
+        // duplicate and trailing separators
+        assert_eq!("\\EFI\\Linux", path("/boot//EFI///Linux/"));
+        assert_eq!("\\EFI\\Linux\\arch.efi", path("EFI/./Linux/arch.efi"));
+        // already backslashed, or mixed
+        assert_eq!("\\EFI\\BOOT\\BOOTX64.EFI", path("\\EFI\\BOOT\\BOOTX64.EFI"));
+        assert_eq!("\\EFI\\BOOT\\BOOTX64.EFI", path("EFI\\BOOT/BOOTX64.EFI"));
+        assert_eq!("\\EFI\\BOOT\\BOOTX64.EFI", path("\\\\EFI\\\\BOOT\\BOOTX64.EFI\\"));
+        // the root of the ESP
+        assert_eq!("\\", path("/boot/efi"));
+        assert_eq!("\\", path(""));
+    }
+
+    #[test]
+    fn test_file_path_from_os_path_outside_esp() {
+        let result = FilePathDevicePath::from_os_path(Path::new("/home/user/arch.efi"));
+        assert!(matches!(result, Err(NotOnEspError(ref path)) if path == Path::new("/home/user/arch.efi")), "{result:?}");
+        // only whole components of the mount point match
+        assert!(FilePathDevicePath::from_os_path(Path::new("/boots/EFI/arch.efi")).is_err());
+    }
+
+    #[test]
+    fn test_default_loader_name() {
+        assert_eq!("BOOTX64.EFI", default_loader_name_for(Arch::X64));
+        assert_eq!("BOOTAA64.EFI", default_loader_name_for(Arch::Aa64));
+        assert_eq!("BOOTIA32.EFI", default_loader_name_for(Arch::Ia32));
+        assert_eq!("BOOTARM.EFI", default_loader_name_for(Arch::Arm));
+
+        // every loader name is recognized as the fallback of its own architecture
+        for arch in Arch::ALL {
+            let path = FilePathDevicePath { path_name: format!("\\EFI\\BOOT\\{}", default_loader_name_for(arch)) };
+            assert_eq!(Some(arch.suffix()), path.fallback_architecture());
+        }
+    }
+
+    #[test]
+    fn test_cdrom_round_trip() {
+        // default El Torito boot image starting at sector 0x1C
+        let bytes = [
+            0x04, 0x02, 0x18, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x1C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x80, 0x16, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let cdrom = CdRomDevicePath::new(0, 0x1C, 0x1680);
+        let node = round_trip(&bytes);
+        assert_eq!(EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::CdRom(cdrom)), node);
+        assert_eq!(Some(DeviceKind::Optical), node.device_kind());
+    }
+
+    #[test]
+    fn test_hard_drive_mbr_round_trip() {
+        // second MBR partition of a disk with signature 0x12345678
+        let bytes = [
+            0x04, 0x01, 0x2A, 0x00,
+            0x02, 0x00, 0x00, 0x00,
+            0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x78, 0x56, 0x34, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x01,
+            0x01,
+        ];
+
+        let node = round_trip(&bytes);
+        assert_eq!(EFIDevicePathProtocol::new_hard_drive_mbr(2, 0x800, 0x100000, 0x12345678), node);
+        assert_eq!(Signature::MBRSignature([0x78, 0x56, 0x34, 0x12, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]), *HardDriveDevicePath::new_mbr(2, 0x800, 0x100000, 0x12345678).signature());
+        assert_eq!("HD(2,MBR,0x12345678,0x800,0x100000)", node.to_string());
+    }
+
+    #[test]
+    fn test_vendor_media_round_trip() {
+        // vendor media node with four bytes of vendor-defined data
+        let bytes = [
+            0x04, 0x03, 0x18, 0x00,
+            0x61, 0xDF, 0xE4, 0x8B, 0xCA, 0x93, 0xD2, 0x11, 0xAA, 0x0D, 0x00, 0xE0, 0x98, 0x03, 0x2B, 0x8C,
+            0xDE, 0xAD, 0xBE, 0xEF,
+        ];
+
+        let vendor = VendorMediaDevicePath::new(Uuid::from_str("8be4df61-93ca-11d2-aa0d-00e098032b8c").unwrap(), vec![0xDE, 0xAD, 0xBE, 0xEF]);
+        let node = round_trip(&bytes);
+        assert_eq!(EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::Vendor(vendor)), node);
+        assert_eq!("VenMedia(8be4df61-93ca-11d2-aa0d-00e098032b8c,deadbeef)", node.to_string());
+
+        // without vendor data
+        let mut bytes = bytes[..20].to_vec();
+        bytes[2] = 0x14;
+        let node = round_trip(&bytes);
+        assert_eq!("VenMedia(8be4df61-93ca-11d2-aa0d-00e098032b8c)", node.to_string());
+
+        // too short to hold the GUID, kept as is
+        let bytes = [0x04, 0x03, 0x08, 0x00, 0x01, 0x02, 0x03, 0x04];
+        let short = EFIDevicePathProtocol::Unknown(UnknownDevicePath { typ: 0x04, sub_type: 0x03, data: vec![0x01, 0x02, 0x03, 0x04] });
+        assert_eq!(short, round_trip(&bytes));
+
+        let result = VendorMediaDevicePath::parse(4, &mut Cursor::new(&bytes[4..]));
+        assert!(matches!(result, Err(DevicePathProtocolParseError::IoError(ref err)) if err.kind() == io::ErrorKind::InvalidData), "{result:?}");
+    }
+
+    #[test]
+    fn test_media_display() {
+        let mbr = HardDriveDevicePath::new_mbr(2, 0x800, 0x100000, 0x12345678);
+        assert_eq!("HD(2,MBR,0x12345678,0x800,0x100000)", MediaDevicePath::HardDrive(mbr).to_string());
+        assert_eq!("CDROM(0x0,0x1c,0x1680)", MediaDevicePath::CdRom(CdRomDevicePath::new(0, 0x1C, 0x1680)).to_string());
+    }
+
+    #[test]
+    fn test_display_device_path() {
+        let nodes = [
+            EFIDevicePathProtocol::AcpiDevicePath(AcpiDevicePath::Acpi { hid: 0x0A0341D0, uid: 0 }),
+            EFIDevicePathProtocol::HardwareDevicePath(HardwareDevicePath::Pci { function: 0, device: 0x1D }),
+            EFIDevicePathProtocol::End(EndSubType::EndInstanceDevicePath),
+            EFIDevicePathProtocol::Unknown(UnknownDevicePath { typ: 0x05, sub_type: 0x01, data: vec![0x06, 0x00] }),
+            EFIDevicePathProtocol::new_end_entire(),
+        ];
+        assert_eq!("Acpi(PNP0A03,0x0)/Pci(0x1d,0x0),Path(5,1,0600)", DisplayDevicePath(&nodes).to_string());
+        assert_eq!("", DisplayDevicePath(&[]).to_string());
+    }
+
+    #[test]
+    fn test_hard_drive_debug() {
+        let gpt = HardDriveDevicePath::new_gpt(1, 0x800, 0x1F4000, Uuid::from_str("eba9a856-dfdd-42eb-be76-31760ae90f55").unwrap());
+        assert_eq!("HD(part=1, GPT, sig=eba9a856-dfdd-42eb-be76-31760ae90f55, start=0x800, size=0x1F4000)", format!("{:?}", gpt));
+
+        let mbr = HardDriveDevicePath::new_mbr(2, 0x800, 0x100000, 0x12345678);
+        assert_eq!("HD(part=2, MBR, sig=0x12345678, start=0x800, size=0x100000)", format!("{:?}", mbr));
+    }
+
+    #[test]
+    fn test_partition_table() {
+        let gpt = HardDriveDevicePath::new_gpt(1, 0x800, 0x1F4000, Uuid::from_str("eba9a856-dfdd-42eb-be76-31760ae90f55").unwrap());
+        assert!(gpt.is_gpt());
+        assert_eq!(PartitionTableType::GPT, gpt.partition_table());
+        assert_eq!("GPT", gpt.partition_table_name());
+
+        let mbr = HardDriveDevicePath::new_mbr(2, 0x800, 0x100000, 0x12345678);
+        assert!(!mbr.is_gpt());
+        assert_eq!("MBR", mbr.partition_table_name());
+    }
+}
@@ -1,19 +1,32 @@
 use std::io::{Cursor, Read, Write};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use log::debug;
-use std::fmt::{Debug, Formatter};
+use log::{debug, warn};
+use std::fmt::{Debug, Display, Formatter};
 use enumflags2::BitFlags;
 use thiserror::Error;
 use std::io;
-use std::iter::once;
 use std::string::FromUtf16Error;
 use std::ops::Range;
 use bytemuck::cast_slice;
+#[cfg(feature = "gvfs")]
 use gio::glib;
-use crate::efidevicepath::{DevicePathProtocolParseError, EFIDevicePathProtocol};
+use uuid::Uuid;
+use crate::efidevicepath::{DeviceKind, DevicePathProtocolParseError, DisplayDevicePath, EFIDevicePathProtocol, EndSubType, MediaDevicePath, Signature};
+#[cfg(target_os = "linux")]
+use crate::esp::EspInfo;
+use crate::efivar::{EFIVariableAttribute, encode_utf16_nul};
+
+/// Largest optional data accepted by [EFILoadOption::parse_strict]: a 2048 character kernel command
+/// line, the limit on x86, encoded as UTF-16.
+pub const MAX_STRICT_OPTIONAL_DATA_SIZE: usize = 4096;
+
+/// Longest description in UTF-16 code units, without the terminator, that new and renamed boot
+/// entries get. Some firmware misbehaves on much longer descriptions.
+pub const DEFAULT_MAX_DESCRIPTION_LENGTH: usize = 255;
 
 /// Modelled after [https://uefi.org/specs/UEFI/2.10/03_Boot_Manager.html#load-options](https://uefi.org/specs/UEFI/2.10/03_Boot_Manager.html#load-options)
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EFILoadOption {
     attributes: LoadOptionAttributes,
     file_path_list: Vec<EFIDevicePathProtocol>,
@@ -21,8 +34,36 @@ pub struct EFILoadOption {
     optional_data: Vec<u8>,
 }
 
+/// How forgiving [EFILoadOption::parse_with] is.
+#[derive(Copy, Clone, PartialEq)]
+enum ParseMode {
+    Default,
+    Strict,
+    Lenient,
+}
+
+#[derive(Debug, Error)]
+#[error("description of {length} UTF-16 code units exceeds the maximum of {max}")]
+pub struct DescriptionTooLongError {
+    pub length: usize,
+    pub max: usize,
+}
+
+#[derive(Debug, Error)]
+pub enum InvalidFilePathListError {
+    /// The file path list of a new load option ends the device path itself, which
+    /// [EFILoadOption::write] already does.
+    #[error("file path list must not contain an end of entire device path node, it is appended when writing")]
+    UnexpectedEndNode,
+    /// The file path list including its end node is longer than the u16 length in the header of a
+    /// load option, or one of its nodes is too large for a device path node.
+    #[error("file path list is too long for a load option")]
+    TooLong,
+}
+
 #[derive(Debug, Error)]
 pub enum LoadOptionParseError {
+    #[cfg(feature = "gvfs")]
     #[error(transparent)]
     ReadError(#[from] glib::Error),
     #[error(transparent)]
@@ -31,15 +72,113 @@ pub enum LoadOptionParseError {
     FromUtf16Error(#[from] FromUtf16Error),
     #[error(transparent)]
     DevicePathProtocolParseError(#[from] DevicePathProtocolParseError),
+    #[error("file path list ended without an end device path node")]
+    MissingEndNode,
+    /// The end node of the file path list came before the length in the header said it would, the
+    /// bytes in between would otherwise be silently dropped.
+    #[error("file path list is declared as {declared} bytes, but its end device path node is after {consumed} bytes")]
+    FilePathListLengthMismatch { declared: u16, consumed: u16 },
+    #[error("optional data of {0} bytes exceeds the maximum of {MAX_STRICT_OPTIONAL_DATA_SIZE} bytes")]
+    OptionalDataTooLarge(usize),
+}
+
+/// Encodes a command line as NUL-terminated UTF-16LE optional data, the way boot loaders and
+/// the Linux EFI stub expect it.
+pub(crate) fn encode_command_line(cmdline: &str) -> Vec<u8> {
+    encode_utf16_nul(cmdline).into_iter().flat_map(u16::to_le_bytes).collect()
+}
+
+fn is_end_entire(node: &EFIDevicePathProtocol) -> bool {
+    matches!(node, EFIDevicePathProtocol::End(EndSubType::EndEntireDevicePath))
+}
+
+/// Length of the file path list including the end node appended when writing, or `None` if it
+/// doesn't fit the u16 length in the header.
+fn file_path_list_length(file_path_list: &[EFIDevicePathProtocol]) -> Option<u16> {
+    let end = EFIDevicePathProtocol::new_end_entire();
+    let length = file_path_list.iter().chain([&end])
+        .try_fold(0u16, |length, node| length.checked_add(node.size()?));
+    length
+}
+
+fn check_file_path_list(file_path_list: &[EFIDevicePathProtocol]) -> Result<(), InvalidFilePathListError> {
+    if file_path_list.iter().any(is_end_entire) {
+        return Err(InvalidFilePathListError::UnexpectedEndNode);
+    }
+    file_path_list_length(file_path_list).ok_or(InvalidFilePathListError::TooLong)?;
+    Ok(())
 }
 
 impl EFILoadOption {
+    /// Builds an active boot entry for a file on the ESP, with the command line encoded as UTF-16
+    /// optional data. `efi_path` is relative to the root of the ESP and normalized like
+    /// [FilePathDevicePath::from_esp_path](crate::efidevicepath::FilePathDevicePath::from_esp_path).
+    #[cfg(target_os = "linux")]
+    pub fn for_esp_file(description: &str, esp: &EspInfo, efi_path: &str, cmdline: Option<&str>) -> EFILoadOption {
+        let optional_data = cmdline.map(encode_command_line).unwrap_or_default();
+
+        EFILoadOption {
+            attributes: LoadOptionAttributes::new(LoadOptionAttributeFlag::Active.into(), LoadOptionCategory::BOOT),
+            file_path_list: vec![
+                esp.device_path(),
+                EFIDevicePathProtocol::new_esp_file_path(efi_path),
+            ],
+            description: description.to_owned(),
+            optional_data,
+        }
+    }
+
+    /// Builds a load option from its parts. The file path list is the device path without its end
+    /// node, instances may be separated by [EndSubType::EndInstanceDevicePath] nodes. Fails if the
+    /// list contains an end of entire device path node, or is too long to be written.
+    pub fn new(attributes: LoadOptionAttributes, description: impl Into<String>, file_path_list: Vec<EFIDevicePathProtocol>, optional_data: Vec<u8>) -> Result<EFILoadOption, InvalidFilePathListError> {
+        check_file_path_list(&file_path_list)?;
+
+        Ok(EFILoadOption { attributes, description: description.into(), file_path_list, optional_data })
+    }
+
+    /// Appends the node to the file path list. The list is left unchanged if the node is an end of
+    /// entire device path node, or the list would become too long.
+    pub fn push_device_path(&mut self, node: EFIDevicePathProtocol) -> Result<(), InvalidFilePathListError> {
+        self.file_path_list.push(node);
+        if let Err(err) = check_file_path_list(&self.file_path_list) {
+            self.file_path_list.pop();
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Parses a load option, failing with [LoadOptionParseError::FilePathListLengthMismatch] if the
+    /// file path list continues past its end node. The optional data always starts after the
+    /// length declared in the header.
     pub fn parse(read: &mut impl Read) -> Result<EFILoadOption, LoadOptionParseError> {
+        Self::parse_with(read, ParseMode::Default)
+    }
+
+    /// Like [EFILoadOption::parse], but also fails with
+    /// [LoadOptionParseError::OptionalDataTooLarge] if the optional data is larger than
+    /// [MAX_STRICT_OPTIONAL_DATA_SIZE], as is likely for garbage appended to the entry.
+    pub fn parse_strict(read: &mut impl Read) -> Result<EFILoadOption, LoadOptionParseError> {
+        Self::parse_with(read, ParseMode::Strict)
+    }
+
+    /// Like [EFILoadOption::parse], but replaces invalid UTF-16 in the description with `U+FFFD`
+    /// and ignores bytes after the end node of the file path list instead of failing, for showing
+    /// entries written by buggy setup utilities. Writing the result back loses the original
+    /// description and the ignored bytes.
+    pub fn parse_lenient(read: &mut impl Read) -> Result<EFILoadOption, LoadOptionParseError> {
+        Self::parse_with(read, ParseMode::Lenient)
+    }
+
+    fn parse_with(read: &mut impl Read, mode: ParseMode) -> Result<EFILoadOption, LoadOptionParseError> {
         debug!("Beginning to parse EFILoadOption...");
 
         let attributes = LoadOptionAttributes::from(read.read_u32::<LittleEndian>()?);
 
         debug!("Parsed attributes: {:?}", attributes);
+        if attributes.category().is_reserved() {
+            warn!("Load option uses reserved category {:#06x}", attributes.category().bits());
+        }
 
         let file_path_list_length = read.read_u16::<LittleEndian>()?;
         debug!("Parsed file path list length: {:?}", file_path_list_length);
@@ -53,7 +192,10 @@ impl EFILoadOption {
                 }
                 description.push(char);
             }
-            String::from_utf16(&description)?
+            match mode {
+                ParseMode::Lenient => String::from_utf16_lossy(&description),
+                _ => String::from_utf16(&description)?,
+            }
         };
         debug!("Parsed description: {}", description);
         let file_path_list = {
@@ -62,15 +204,26 @@ impl EFILoadOption {
             read.read_exact(&mut buffer)?;
 
             let mut list = vec![];
+            let length = buffer.len() as u64;
             let mut read = Cursor::new(buffer);
             loop {
+                if read.position() >= length {
+                    return Err(LoadOptionParseError::MissingEndNode);
+                }
                 let device_path = EFIDevicePathProtocol::parse(&mut read)?;
                 debug!("Parsed device path protocol: {device_path:?}");
-                if matches!(device_path, EFIDevicePathProtocol::End(_)) {
+                // instance end nodes are kept to separate the instances of multi-instance paths
+                if matches!(device_path, EFIDevicePathProtocol::End(EndSubType::EndEntireDevicePath)) {
                     break;
                 }
                 list.push(device_path);
             }
+            if mode != ParseMode::Lenient && read.position() < length {
+                return Err(LoadOptionParseError::FilePathListLengthMismatch {
+                    declared: file_path_list_length,
+                    consumed: read.position() as u16,
+                });
+            }
             list
         };
 
@@ -80,26 +233,30 @@ impl EFILoadOption {
             read.read_to_end(&mut buf)?;
             buf
         };
+        if mode == ParseMode::Strict && optional_data.len() > MAX_STRICT_OPTIONAL_DATA_SIZE {
+            return Err(LoadOptionParseError::OptionalDataTooLarge(optional_data.len()));
+        }
 
         Ok(EFILoadOption { attributes, description, file_path_list, optional_data })
     }
 
+    /// Writes the load option, failing with an [io::ErrorKind::InvalidInput] error if the file path
+    /// list is too long, which only a deserialized load option can be.
     pub fn write(&self, write: &mut impl Write) -> io::Result<()> {
-        write.write_u32::<LittleEndian>(self.attributes.bits())?;
+        let file_path_list_length = file_path_list_length(&self.file_path_list)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, InvalidFilePathListError::TooLong))?;
 
-        // concat end device path entry
-        let end = EFIDevicePathProtocol::new_end_entire();
-        let file_path_list_with_end = self.file_path_list.iter().chain([&end]);
-
-        // file path list length
-        write.write_u16::<LittleEndian>(file_path_list_with_end.clone().sum())?;
+        write.write_u32::<LittleEndian>(self.attributes.bits())?;
+        write.write_u16::<LittleEndian>(file_path_list_length)?;
 
         {
-            let description = self.description.encode_utf16().chain(once(0x0000)).collect::<Vec<_>>();
+            let description = encode_utf16_nul(&self.description);
             write.write_all(cast_slice(description.as_slice()))?;
         }
 
-        for device_path in file_path_list_with_end {
+        // concat end device path entry
+        let end = EFIDevicePathProtocol::new_end_entire();
+        for device_path in self.file_path_list.iter().chain([&end]) {
             device_path.write(write)?;
         }
 
@@ -108,17 +265,206 @@ impl EFILoadOption {
         Ok(())
     }
 
+    /// Like [EFILoadOption::write], but fails with an [io::ErrorKind::InvalidInput] error wrapping
+    /// a [DescriptionTooLongError] instead if the description is longer than
+    /// `max_description_length` UTF-16 code units.
+    pub fn write_checked(&self, write: &mut impl Write, max_description_length: usize) -> io::Result<()> {
+        self.check_description_length(max_description_length)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        self.write(write)
+    }
+
+    /// Checks that the description is at most `max` UTF-16 code units long, without the
+    /// terminator. See [DEFAULT_MAX_DESCRIPTION_LENGTH].
+    pub fn check_description_length(&self, max: usize) -> Result<(), DescriptionTooLongError> {
+        let length = self.description.encode_utf16().count();
+        if length > max {
+            return Err(DescriptionTooLongError { length, max });
+        }
+        Ok(())
+    }
+
+    /// Serializes the load option, as stored in the data of a `Boot####` variable.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![];
+        self.write(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Like [EFILoadOption::to_bytes], prefixed with the 4-byte variable attributes as expected
+    /// by efivarfs.
+    pub fn to_bytes_with_attributes(&self, attributes: BitFlags<EFIVariableAttribute>) -> io::Result<Vec<u8>> {
+        let mut buffer = attributes.bits().to_le_bytes().to_vec();
+        self.write(&mut buffer)?;
+        Ok(buffer)
+    }
+
     pub fn description(&self) -> &str {
         &self.description
     }
 
+    pub fn set_description(&mut self, description: impl Into<String>) {
+        self.description = description.into();
+    }
+
+    /// Returns the load option with another description, keeping everything else including
+    /// unmodeled device path nodes byte for byte.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.set_description(description);
+        self
+    }
+
+    /// Returns the load option with the command line as UTF-16 optional data, or without optional
+    /// data for `None`. Everything else is kept byte for byte.
+    pub fn with_command_line(mut self, cmdline: Option<&str>) -> Self {
+        self.optional_data = cmdline.map(encode_command_line).unwrap_or_default();
+        self
+    }
+
+    /// Replaces the optional data with the command line as NUL-terminated UTF-16, the reverse of
+    /// [EFILoadOption::command_line].
+    pub fn set_command_line(&mut self, cmdline: &str) {
+        self.optional_data = encode_command_line(cmdline);
+    }
+
+    pub fn optional_data(&self) -> &[u8] {
+        &self.optional_data
+    }
+
+    pub fn set_optional_data(&mut self, optional_data: Vec<u8>) {
+        self.optional_data = optional_data;
+    }
+
+    /// The optional data as a UTF-16 command line, as used by the Linux EFI stub and UKIs. Returns
+    /// `None` if the optional data isn't valid UTF-16.
+    pub fn command_line(&self) -> Option<String> {
+        if !self.optional_data.len().is_multiple_of(2) {
+            return None;
+        }
+        String::from_utf16(&self.optional_data_utf16()).ok()
+    }
+
+    /// Like [EFILoadOption::command_line], but replaces invalid UTF-16 and a trailing odd byte with
+    /// `U+FFFD` instead of failing.
+    pub fn command_line_lossy(&self) -> String {
+        let mut command_line = String::from_utf16_lossy(&self.optional_data_utf16());
+        if !self.optional_data.len().is_multiple_of(2) {
+            command_line.push(char::REPLACEMENT_CHARACTER);
+        }
+        command_line
+    }
+
+    /// Optional data as UTF-16 code units, without trailing NUL terminators.
+    fn optional_data_utf16(&self) -> Vec<u16> {
+        let mut chars = self.optional_data.chunks_exact(2)
+            .map(|char| u16::from_le_bytes([char[0], char[1]]))
+            .collect::<Vec<_>>();
+        while chars.last() == Some(&0x0000) {
+            chars.pop();
+        }
+        chars
+    }
+
+    pub fn file_path_list(&self) -> &[EFIDevicePathProtocol] {
+        &self.file_path_list
+    }
+
+    /// Displays the file path list like `efibootmgr -v`, see [DisplayDevicePath].
+    pub fn display_file_path_list(&self) -> DisplayDevicePath<'_> {
+        DisplayDevicePath(&self.file_path_list)
+    }
+
+    /// Iterates the device path nodes of the file path list, without the end node.
+    pub fn device_path_nodes(&self) -> impl Iterator<Item=&EFIDevicePathProtocol> {
+        self.file_path_list.iter()
+    }
+
+    /// Iterates the instances of the file path list, split at the
+    /// [EndSubType::EndInstanceDevicePath] nodes, which are not included in the instances. Most
+    /// load options have a single instance.
+    pub fn device_path_instances(&self) -> impl Iterator<Item=&[EFIDevicePathProtocol]> {
+        self.file_path_list.split(|node| matches!(node, EFIDevicePathProtocol::End(EndSubType::EndInstanceDevicePath)))
+    }
+
+    /// One-line description of what the entry boots for users, like
+    /// `NVMe Partition 1 → \EFI\Linux\arch.efi` or `USB Drive → \EFI\BOOT\BOOTX64.EFI`. Unlike
+    /// the spec text representation of the device path, this leaves out controller details.
+    pub fn human_summary(&self) -> String {
+        let kind = self.device_path_nodes().filter_map(EFIDevicePathProtocol::device_kind).min();
+        // the innermost bus is the one the disk is attached to
+        let bus = self.device_path_nodes().filter_map(EFIDevicePathProtocol::bus_name).last();
+        let mut partition = None;
+        let mut path = None;
+        for node in self.device_path_nodes() {
+            match node {
+                EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::HardDrive(hard_drive)) => partition = Some(hard_drive),
+                EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::FilePath(file_path)) => path = Some(file_path.path_name()),
+                _ => (),
+            }
+        }
+
+        let device = match (kind, bus, partition) {
+            (Some(DeviceKind::Network), _, _) => Some("Network Boot".to_owned()),
+            (Some(DeviceKind::Optical), _, _) => Some("CD/DVD Drive".to_owned()),
+            (Some(DeviceKind::Usb), _, _) => Some("USB Drive".to_owned()),
+            (_, Some(bus), Some(partition)) => Some(format!("{} Partition {}", bus, partition.partition_number())),
+            (_, None, Some(partition)) => Some(format!("{} Partition {}", partition.partition_table_name(), partition.partition_number())),
+            (_, Some(bus), None) => Some(format!("{} Drive", bus)),
+            (_, None, None) => None,
+        };
+
+        match (device, path) {
+            (Some(device), Some(path)) => format!("{} → {}", device, path),
+            (Some(device), None) => device,
+            (None, Some(path)) => path.to_owned(),
+            (None, None) => "Unknown Device".to_owned(),
+        }
+    }
+
+    /// Whether any hard drive node of the file path list points at the GPT partition.
+    pub fn targets_partition(&self, uuid: &Uuid) -> bool {
+        self.device_path_nodes().any(|node| matches!(
+            node,
+            EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::HardDrive(hard_drive)) if hard_drive.signature() == &Signature::GUID(*uuid)
+        ))
+    }
+
+    /// Whether both load options boot the same thing under the same name, ignoring attributes and
+    /// optional data.
+    pub fn is_duplicate_of(&self, other: &EFILoadOption) -> bool {
+        self.description == other.description && self.file_path_list == other.file_path_list
+    }
+
     pub fn attributes(&self) -> &LoadOptionAttributes {
         &self.attributes
     }
+
+    /// Whether the category is one the specification reserves, which firmware may not know how
+    /// to handle. Such entries are kept as is, but are worth pointing out.
+    pub fn category_is_reserved(&self) -> bool {
+        self.attributes.category().is_reserved()
+    }
+
+    pub fn set_attributes(&mut self, attributes: LoadOptionAttributes) {
+        self.attributes = attributes;
+    }
+
+    /// Sets or clears a single flag, keeping the others and the category.
+    pub fn set_flag(&mut self, flag: LoadOptionAttributeFlag, enabled: bool) {
+        let mut flags = self.attributes.flags();
+        if enabled {
+            flags.insert(flag);
+        } else {
+            flags.remove(flag);
+        }
+        self.attributes.set_flags(flags);
+    }
 }
 
 #[repr(transparent)]
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct LoadOptionAttributes(u32);
 
 impl Debug for LoadOptionAttributes {
@@ -141,6 +487,12 @@ impl LoadOptionAttributes {
         self.0
     }
 
+    /// The attributes as stored in the load option, including reserved bits that aren't modelled
+    /// by [LoadOptionAttributes::flags] or [LoadOptionAttributes::category].
+    pub fn raw(&self) -> u32 {
+        self.0
+    }
+
     fn category_bits(&self) -> u32 {
         self.0 & Self::CATEGORY_MASK
     }
@@ -157,8 +509,9 @@ impl LoadOptionAttributes {
         BitFlags::from_bits_truncate(self.flag_bits())
     }
 
+    /// Replaces the modelled flags, keeping the category and any reserved bits.
     pub fn set_flags(&mut self, flags: BitFlags<LoadOptionAttributeFlag>) {
-        self.0 = flags.bits() | self.category_bits();
+        self.0 = flags.bits() | (self.0 & !BitFlags::<LoadOptionAttributeFlag>::ALL.bits());
     }
 
     pub fn set_category(&mut self, category: LoadOptionCategory) {
@@ -181,6 +534,31 @@ pub enum LoadOptionAttributeFlag {
     Hidden = 0x00000008,
 }
 
+impl Display for LoadOptionAttributeFlag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+/// Displays load option attribute flags joined by `|`, like `Active | Hidden`, or `<none>`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DisplayLoadOptionFlags(pub BitFlags<LoadOptionAttributeFlag>);
+
+impl Display for DisplayLoadOptionFlags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("<none>");
+        }
+        for (index, flag) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" | ")?;
+            }
+            Display::fmt(&flag, f)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Copy, Clone, PartialEq)]
 pub struct LoadOptionCategory(u32);
 
@@ -195,8 +573,8 @@ impl Debug for LoadOptionCategory {
 }
 
 impl LoadOptionCategory {
-    const BOOT: LoadOptionCategory = LoadOptionCategory(0x00000000);
-    const APP: LoadOptionCategory = LoadOptionCategory(0x00000100);
+    pub const BOOT: LoadOptionCategory = LoadOptionCategory(0x00000000);
+    pub const APP: LoadOptionCategory = LoadOptionCategory(0x00000100);
     const RESERVED_RANGE: Range<u32> = 0x00000200..0x00002000;
 
     pub fn is_boot(&self) -> bool {
@@ -218,13 +596,18 @@ impl LoadOptionCategory {
 
 #[cfg(test)]
 mod tests {
+    use std::io;
     use std::io::Cursor;
     use std::str::FromStr;
-    use byteorder::{LittleEndian, ReadBytesExt};
-    use gio::glib::MainContext;
+    use enumflags2::BitFlags;
     use uuid::Uuid;
-    use crate::efidevicepath::{EFIDevicePathProtocol, HardDriveDevicePath, MediaDevicePath, PartitionTableType, Signature};
-    use crate::efiloadoption::{EFILoadOption, LoadOptionAttributeFlag, LoadOptionAttributes, LoadOptionCategory};
+    use crate::efidevicepath::{DevicePathProtocolParseError, EFIDevicePathProtocol, EndSubType, MediaDevicePath, PartitionTableType, Signature};
+    use crate::efiloadoption::{DEFAULT_MAX_DESCRIPTION_LENGTH, DisplayLoadOptionFlags, EFILoadOption, InvalidFilePathListError, LoadOptionAttributeFlag, LoadOptionAttributes, LoadOptionCategory, LoadOptionParseError, MAX_STRICT_OPTIONAL_DATA_SIZE};
+    use crate::efiboot::boot_entry_name;
+    use crate::efivar::parse_efivar_payload;
+    #[cfg(target_os = "linux")]
+    use crate::esp::EspInfo;
+    use crate::fixtures::{BOOT_CDROM, BOOT_NVME, BOOT_PXE_IPV4, BOOT_USB, test_variable};
 
     fn equivalent_load_option() -> EFILoadOption {
         EFILoadOption {
@@ -243,14 +626,139 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_efi_load_option_for_esp_file() {
+        let esp = EspInfo::new(1, 0x800, 0x1F4000, Uuid::from_str("eba9a856-dfdd-42eb-be76-31760ae90f55").unwrap(), "/boot/efi");
+
+        // the fixture's path lacks the leading backslash that paths on the ESP are given
+        let mut expected = equivalent_load_option();
+        expected.file_path_list[1] = EFIDevicePathProtocol::new_file_path("\\EFI\\Linux\\arch-linux.efi");
+        assert_eq!(expected, EFILoadOption::for_esp_file("Arch Linux", &esp, "EFI/Linux/arch-linux.efi", None));
+        assert_eq!(expected, EFILoadOption::for_esp_file("Arch Linux", &esp, "/EFI//Linux/./arch-linux.efi", None));
+
+        let load_option = EFILoadOption::for_esp_file("Arch Linux", &esp, "EFI\\Linux\\arch-linux.efi", Some("root=/dev/sda2 rw"));
+        assert!(load_option.is_duplicate_of(&expected));
+        assert_eq!(Some("root=/dev/sda2 rw".to_owned()), load_option.command_line());
+    }
+
+    #[test]
+    fn test_efi_load_option_targets_partition() {
+        let load_option = equivalent_load_option();
+
+        let uuids = load_option.device_path_nodes()
+            .filter_map(|node| match node {
+                EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::HardDrive(hard_drive)) => Some(hard_drive.signature()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vec![&Signature::GUID(Uuid::from_str("eba9a856-dfdd-42eb-be76-31760ae90f55").unwrap())], uuids);
+
+        assert!(load_option.targets_partition(&Uuid::from_str("eba9a856-dfdd-42eb-be76-31760ae90f55").unwrap()));
+        assert!(!load_option.targets_partition(&Uuid::from_str("00000000-dfdd-42eb-be76-31760ae90f55").unwrap()));
+    }
+
+    #[test]
+    fn test_efi_load_option_device_path_instances() {
+        let mut load_option = equivalent_load_option();
+        let first = load_option.file_path_list.clone();
+        let second = vec![EFIDevicePathProtocol::new_file_path("EFI\\BOOT\\BOOTX64.EFI")];
+        load_option.file_path_list = [first.clone(), vec![EFIDevicePathProtocol::End(EndSubType::EndInstanceDevicePath)], second.clone()].concat();
+
+        let mut buffer = vec![];
+        load_option.write(&mut buffer).unwrap();
+        let parsed = EFILoadOption::parse(&mut Cursor::new(buffer)).unwrap();
+
+        assert_eq!(vec![first.as_slice(), second.as_slice()], parsed.device_path_instances().collect::<Vec<_>>());
+        assert_eq!(1, equivalent_load_option().device_path_instances().count());
+    }
+
+    #[test]
+    fn test_load_option_attributes_raw_round_trip() {
+        let mut load_option = equivalent_load_option();
+        load_option.attributes = LoadOptionAttributes::from(0x8000_0109);
+        let mut buffer = vec![];
+        load_option.write(&mut buffer).unwrap();
+
+        let parsed = EFILoadOption::parse(&mut Cursor::new(buffer)).unwrap();
+
+        assert_eq!(0x8000_0109, parsed.attributes().raw());
+        assert_eq!(*parsed.attributes(), LoadOptionAttributes::from(parsed.attributes().raw()));
+    }
+
+    #[test]
+    fn test_efi_load_option_reserved_category() {
+        let mut buffer = vec![];
+        equivalent_load_option().write(&mut buffer).unwrap();
+        assert!(!EFILoadOption::parse(&mut Cursor::new(&buffer)).unwrap().category_is_reserved());
+
+        buffer[..4].copy_from_slice(&0x0000_0201u32.to_le_bytes());
+        let load_option = EFILoadOption::parse(&mut Cursor::new(&buffer)).unwrap();
+
+        assert!(load_option.category_is_reserved());
+        assert!(load_option.attributes().flags().contains(LoadOptionAttributeFlag::Active));
+        assert_eq!(buffer, load_option.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_load_option_attributes_set_flags_keeps_reserved_bits() {
+        let mut attributes = LoadOptionAttributes::from(0x8000_0100);
+
+        attributes.set_flags(LoadOptionAttributeFlag::Active.into());
+        assert_eq!(0x8000_0101, attributes.raw());
+        assert!(attributes.category().is_app());
+
+        attributes.set_flags(BitFlags::empty());
+        assert_eq!(0x8000_0100, attributes.raw());
+    }
+
+    #[test]
+    fn test_efi_load_option_set_flag() {
+        let mut load_option = equivalent_load_option();
+
+        load_option.set_flag(LoadOptionAttributeFlag::Hidden, true);
+        assert_eq!(LoadOptionAttributeFlag::Active | LoadOptionAttributeFlag::Hidden, load_option.attributes().flags());
+        load_option.set_flag(LoadOptionAttributeFlag::Active, false);
+        assert_eq!(BitFlags::from(LoadOptionAttributeFlag::Hidden), load_option.attributes().flags());
+        assert_eq!(LoadOptionCategory::BOOT, load_option.attributes().category());
+
+        load_option.set_flag(LoadOptionAttributeFlag::Hidden, false);
+        load_option.set_flag(LoadOptionAttributeFlag::Active, true);
+        assert_eq!(equivalent_load_option(), load_option);
+    }
+
+    #[test]
+    fn test_description_length_boundary() {
+        let mut load_option = equivalent_load_option();
+
+        load_option.set_description("a".repeat(DEFAULT_MAX_DESCRIPTION_LENGTH));
+        load_option.check_description_length(DEFAULT_MAX_DESCRIPTION_LENGTH).unwrap();
+        load_option.write_checked(&mut vec![], DEFAULT_MAX_DESCRIPTION_LENGTH).unwrap();
+
+        // characters outside the BMP take two code units
+        load_option.set_description(format!("{}\u{1F680}", "a".repeat(DEFAULT_MAX_DESCRIPTION_LENGTH - 1)));
+        let err = load_option.check_description_length(DEFAULT_MAX_DESCRIPTION_LENGTH).unwrap_err();
+        assert_eq!((DEFAULT_MAX_DESCRIPTION_LENGTH + 1, DEFAULT_MAX_DESCRIPTION_LENGTH), (err.length, err.max));
+
+        let err = load_option.write_checked(&mut vec![], DEFAULT_MAX_DESCRIPTION_LENGTH).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+        load_option.write_checked(&mut vec![], DEFAULT_MAX_DESCRIPTION_LENGTH + 1).unwrap();
+    }
+
+    #[test]
+    fn test_display_load_option_flags() {
+        let flags = LoadOptionAttributeFlag::Active | LoadOptionAttributeFlag::Hidden | LoadOptionAttributeFlag::ForceReconnect;
+        assert_eq!("Active | ForceReconnect | Hidden", DisplayLoadOptionFlags(flags).to_string());
+        assert_eq!("Hidden", DisplayLoadOptionFlags(LoadOptionAttributeFlag::Hidden.into()).to_string());
+        assert_eq!("<none>", DisplayLoadOptionFlags(BitFlags::empty()).to_string());
+    }
+
     #[test]
     fn test_efi_load_option_parse() {
         // copied from my personal laptop
-        let boot_entry_bytes = include_bytes!("test/Boot0001-8be4df61-93ca-11d2-aa0d-00e098032b8c");
-        let mut read = Cursor::new(boot_entry_bytes);
-        let _efivarfs_attrs = read.read_u32::<LittleEndian>();
+        let boot_entry = test_variable(boot_entry_name(1));
 
-        let parsed = EFILoadOption::parse(&mut read).unwrap();
+        let parsed = EFILoadOption::parse(&mut Cursor::new(boot_entry.data())).unwrap();
 
         let expected = equivalent_load_option();
 
@@ -260,13 +768,323 @@ mod tests {
     #[test]
     fn test_efi_load_option_write() {
         let source = equivalent_load_option();
-        let expected = include_bytes!("test/Boot0001-8be4df61-93ca-11d2-aa0d-00e098032b8c");
+        let expected = test_variable(boot_entry_name(1));
 
         let buffer = vec![];
         let mut write = Cursor::new(buffer);
         source.write(&mut write).unwrap();
 
         let buffer = write.into_inner();
-        assert_eq!(&expected[4..], buffer.as_slice());
+        assert_eq!(expected.data(), buffer.as_slice());
+    }
+
+    #[test]
+    fn test_efi_load_option_command_line() {
+        let mut load_option = equivalent_load_option();
+        load_option.optional_data = "root=/dev/sda2\0".encode_utf16().flat_map(u16::to_le_bytes).collect();
+
+        assert_eq!(Some("root=/dev/sda2".to_owned()), load_option.command_line());
+        assert_eq!("root=/dev/sda2", load_option.command_line_lossy());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_efi_load_option_serde_round_trip() {
+        let load_option = equivalent_load_option();
+        let json = serde_json::to_string(&load_option).unwrap();
+        assert!(json.contains(r#""attributes":1,"#), "{}", json);
+        assert!(json.contains(r#""description":"Arch Linux""#), "{}", json);
+
+        let deserialized: EFILoadOption = serde_json::from_str(&json).unwrap();
+        assert_eq!(load_option, deserialized);
+    }
+
+    #[test]
+    fn test_efi_load_option_set_command_line() {
+        let mut load_option = equivalent_load_option();
+        load_option.set_command_line("root=/dev/sda2 rw");
+        assert_eq!(b"r\0o\0", &load_option.optional_data()[..4]);
+        assert_eq!(&[0x00, 0x00], &load_option.optional_data()[load_option.optional_data().len() - 2..]);
+
+        let parsed = EFILoadOption::parse(&mut Cursor::new(load_option.to_bytes().unwrap())).unwrap();
+        assert_eq!(Some("root=/dev/sda2 rw".to_owned()), parsed.command_line());
+
+        // arbitrary binary optional data, here an odd length blob containing a lone surrogate
+        load_option.set_optional_data(vec![0x01, 0x00, 0x00, 0xDC, 0x88, 0x13, 0x37]);
+        assert_eq!(None, load_option.command_line());
+    }
+
+    #[test]
+    fn test_efi_load_option_command_line_invalid() {
+        let mut load_option = equivalent_load_option();
+        // unpaired high surrogate followed by 'a'
+        load_option.optional_data = vec![0x00, 0xD8, 0x61, 0x00];
+
+        assert_eq!(None, load_option.command_line());
+        assert_eq!("\u{FFFD}a", load_option.command_line_lossy());
+
+        load_option.optional_data.push(0x62);
+        assert_eq!(None, load_option.command_line());
+        assert_eq!("\u{FFFD}a\u{FFFD}", load_option.command_line_lossy());
+    }
+
+    #[test]
+    fn test_efi_load_option_force_reconnect_round_trip() {
+        let mut load_option = equivalent_load_option();
+        load_option.set_attributes(LoadOptionAttributes::new(LoadOptionAttributeFlag::Active | LoadOptionAttributeFlag::ForceReconnect, LoadOptionCategory::BOOT));
+
+        let mut buffer = vec![];
+        load_option.write(&mut buffer).unwrap();
+        assert_eq!(&[0x03, 0x00, 0x00, 0x00], &buffer[0..4]);
+
+        let parsed = EFILoadOption::parse(&mut Cursor::new(buffer)).unwrap();
+        assert!(parsed.attributes().flags().contains(LoadOptionAttributeFlag::ForceReconnect));
+        assert_eq!(load_option, parsed);
+    }
+
+    fn fixture_load_option(id: u16) -> EFILoadOption {
+        EFILoadOption::parse(&mut Cursor::new(test_variable(boot_entry_name(id)).data())).unwrap()
+    }
+
+    #[test]
+    fn test_fixtures_parse() {
+        // the real and synthetic `Boot####` dumps must parse and write back byte-for-byte
+        for id in [0x0001, BOOT_NVME, BOOT_USB, BOOT_PXE_IPV4, BOOT_CDROM] {
+            let variable = test_variable(boot_entry_name(id));
+            let name = variable.name();
+            let load_option = EFILoadOption::parse(&mut Cursor::new(variable.data())).unwrap_or_else(|err| panic!("{name} failed to parse: {err}"));
+
+            let mut written = vec![];
+            load_option.write(&mut written).unwrap();
+            assert_eq!(variable.data(), written.as_slice(), "{name} didn't round-trip");
+        }
+    }
+
+    #[test]
+    fn test_efi_load_option_unknown_node_round_trip() {
+        let bytes = [
+            0x01, 0x00, 0x00, 0x00, // attributes
+            0x18, 0x00, // file path list length
+            b'A', 0x00, 0x00, 0x00, // description
+            0x05, 0x01, 0x0A, 0x00, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00, // BIOS boot specification node
+            0x04, 0x04, 0x0A, 0x00, b'\\', 0x00, b'a', 0x00, 0x00, 0x00, // file path
+            0x7F, 0xFF, 0x04, 0x00, // end
+        ];
+
+        let load_option = EFILoadOption::parse(&mut Cursor::new(&bytes)).unwrap();
+        assert!(matches!(load_option.file_path_list()[0], EFIDevicePathProtocol::Unknown(_)));
+        assert_eq!(bytes.as_slice(), load_option.to_bytes().unwrap().as_slice());
+    }
+
+    #[test]
+    fn test_unknown_nodes_survive_edit() {
+        // the USB fixture contains a USB node, which isn't modeled
+        let mut load_option = fixture_load_option(BOOT_USB);
+        assert!(load_option.device_path_nodes().any(|node| matches!(node, EFIDevicePathProtocol::Unknown(_))));
+        let file_path_list = load_option.file_path_list().to_vec();
+
+        load_option.set_description("USB");
+        let mut written = vec![];
+        load_option.write(&mut written).unwrap();
+        let parsed = EFILoadOption::parse(&mut Cursor::new(&written)).unwrap();
+
+        assert_eq!(file_path_list, parsed.file_path_list());
+        assert_eq!("USB", parsed.description());
+    }
+
+    #[test]
+    fn test_display_file_path_list() {
+        assert_eq!(
+            "HD(1,GPT,eba9a856-dfdd-42eb-be76-31760ae90f55,0x800,0x1f4000)/File(EFI\\Linux\\arch-linux.efi)",
+            equivalent_load_option().display_file_path_list().to_string(),
+        );
+    }
+
+    #[test]
+    fn test_efi_load_option_new() {
+        let partition = EFIDevicePathProtocol::new_hard_drive_gpt(1, 0x800, 0x1F4000, Uuid::from_str("eba9a856-dfdd-42eb-be76-31760ae90f55").unwrap());
+        let attributes = LoadOptionAttributes::new(LoadOptionAttributeFlag::Active.into(), LoadOptionCategory::BOOT);
+        let mut load_option = EFILoadOption::new(attributes, "My Kernel", vec![partition.clone()], vec![]).unwrap();
+        load_option.push_device_path(EFIDevicePathProtocol::new_file_path("\\EFI\\Linux\\my-kernel.efi")).unwrap();
+        load_option.set_optional_data(vec![0x61, 0x00, 0x00, 0x00]);
+
+        let parsed = EFILoadOption::parse(&mut Cursor::new(load_option.to_bytes().unwrap())).unwrap();
+        assert_eq!(load_option, parsed);
+        assert_eq!(Some("a".to_owned()), parsed.command_line());
+
+        assert!(load_option.push_device_path(EFIDevicePathProtocol::new_end_entire()).is_err());
+        assert!(EFILoadOption::new(attributes, "My Kernel", vec![partition, EFIDevicePathProtocol::new_end_entire()], vec![]).is_err());
+    }
+
+    #[test]
+    fn test_longest_file_path() {
+        let attributes = LoadOptionAttributes::new(LoadOptionAttributeFlag::Active.into(), LoadOptionCategory::BOOT);
+        // the file path node and the end node appended when writing share the u16 list length
+        let longest = (usize::from(u16::MAX) - 4 - 4) / 2 - 1;
+
+        let load_option = EFILoadOption::new(attributes, "Long", vec![EFIDevicePathProtocol::new_file_path("A".repeat(longest))], vec![]).unwrap();
+        let bytes = load_option.to_bytes_with_attributes(BitFlags::empty()).unwrap();
+        let parsed = EFILoadOption::parse(&mut Cursor::new(&bytes[4..])).unwrap();
+        assert_eq!(load_option, parsed);
+
+        let too_long = vec![EFIDevicePathProtocol::new_file_path("A".repeat(longest + 1))];
+        assert!(matches!(EFILoadOption::new(attributes, "Long", too_long.clone(), vec![]), Err(InvalidFilePathListError::TooLong)));
+        let mut load_option = EFILoadOption::new(attributes, "Long", vec![], vec![]).unwrap();
+        assert!(matches!(load_option.push_device_path(too_long[0].clone()), Err(InvalidFilePathListError::TooLong)));
+        assert!(load_option.file_path_list().is_empty());
+
+        // a path that doesn't even fit a device path node
+        let too_long = vec![EFIDevicePathProtocol::new_file_path("A".repeat(40000))];
+        assert!(matches!(EFILoadOption::new(attributes, "Long", too_long, vec![]), Err(InvalidFilePathListError::TooLong)));
+    }
+
+    #[test]
+    fn test_file_path_list_accessors() {
+        let load_option = equivalent_load_option();
+        let [EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::HardDrive(hard_drive)), EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::FilePath(file_path))] = load_option.file_path_list() else {
+            panic!("unexpected file path list: {:?}", load_option.file_path_list());
+        };
+
+        assert_eq!(1, hard_drive.partition_number());
+        assert_eq!(0x800, hard_drive.partition_start());
+        assert_eq!(0x1F4000, hard_drive.partition_size());
+        assert_eq!(&Signature::GUID(Uuid::from_str("eba9a856-dfdd-42eb-be76-31760ae90f55").unwrap()), hard_drive.signature());
+        assert_eq!(PartitionTableType::GPT, hard_drive.partition_table());
+        assert_eq!("EFI\\Linux\\arch-linux.efi", file_path.path_name());
+    }
+
+    #[test]
+    fn test_human_summary() {
+        assert_eq!("NVMe Partition 1 → \\EFI\\BOOT\\BOOTX64.EFI", fixture_load_option(BOOT_NVME).human_summary());
+        assert_eq!("USB Drive", fixture_load_option(BOOT_USB).human_summary());
+        assert_eq!("GPT Partition 1 → EFI\\Linux\\arch-linux.efi", equivalent_load_option().human_summary());
+
+        let mut load_option = equivalent_load_option();
+        load_option.file_path_list = vec![];
+        assert_eq!("Unknown Device", load_option.human_summary());
+    }
+
+    #[test]
+    fn test_with_description_keeps_unknown_nodes() {
+        let variable = test_variable(boot_entry_name(BOOT_USB));
+        let load_option = EFILoadOption::parse(&mut Cursor::new(variable.data())).unwrap();
+        // attributes, file path list length and the NUL-terminated UTF-16 description
+        let header_length = |load_option: &EFILoadOption| 4 + 2 + (load_option.description().encode_utf16().count() + 1) * 2;
+        let original_header_length = header_length(&load_option);
+
+        let edited = load_option.with_description("USB");
+        let written = edited.to_bytes().unwrap();
+
+        assert!(edited.device_path_nodes().any(|node| matches!(node, EFIDevicePathProtocol::Unknown(_))));
+        assert_eq!(&variable.data()[original_header_length..], &written[header_length(&edited)..]);
+
+        let edited = edited.with_command_line(Some("quiet"));
+        assert_eq!(Some("quiet".to_owned()), edited.command_line());
+        assert!(edited.with_command_line(None).optional_data().is_empty());
+    }
+
+    #[test]
+    fn test_efi_load_option_parse_lenient() {
+        let mut buffer = vec![];
+        equivalent_load_option().write(&mut buffer).unwrap();
+        // the description starts after the attributes and file path list length
+        buffer[6..8].copy_from_slice(&0xDC00u16.to_le_bytes());
+
+        assert!(matches!(EFILoadOption::parse(&mut Cursor::new(&buffer)), Err(LoadOptionParseError::FromUtf16Error(_))));
+        let load_option = EFILoadOption::parse_lenient(&mut Cursor::new(&buffer)).unwrap();
+        assert_eq!("\u{FFFD}rch Linux", load_option.description());
+        assert_eq!(equivalent_load_option().file_path_list(), load_option.file_path_list());
+    }
+
+    #[test]
+    fn test_efi_load_option_parse_strict() {
+        let mut load_option = equivalent_load_option();
+        load_option.optional_data = "root=/dev/sda2 rw".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let mut buffer = vec![];
+        load_option.write(&mut buffer).unwrap();
+        assert_eq!(load_option, EFILoadOption::parse_strict(&mut Cursor::new(&buffer)).unwrap());
+
+        // garbage inside the file path list, after the end node
+        let file_path_list_length = u16::from_le_bytes([buffer[4], buffer[5]]);
+        let file_path_list_end = buffer.len() - load_option.optional_data.len();
+        let mut garbage = buffer.clone();
+        garbage[4..6].copy_from_slice(&(file_path_list_length + 3).to_le_bytes());
+        garbage.splice(file_path_list_end..file_path_list_end, [0xDE, 0xAD, 0xBE]);
+        let result = EFILoadOption::parse_strict(&mut Cursor::new(&garbage));
+        assert!(matches!(result, Err(LoadOptionParseError::FilePathListLengthMismatch { .. })), "{result:?}");
+
+        // garbage appended to the optional data
+        let mut garbage = buffer.clone();
+        garbage.resize(buffer.len() + MAX_STRICT_OPTIONAL_DATA_SIZE, 0xFF);
+        assert!(EFILoadOption::parse(&mut Cursor::new(&garbage)).is_ok());
+        let result = EFILoadOption::parse_strict(&mut Cursor::new(&garbage));
+        assert!(matches!(result, Err(LoadOptionParseError::OptionalDataTooLarge(_))), "{result:?}");
+    }
+
+    #[test]
+    fn test_efi_load_option_file_path_list_length_mismatch() {
+        let mut load_option = equivalent_load_option();
+        load_option.set_command_line("quiet");
+        let mut buffer = vec![];
+        load_option.write(&mut buffer).unwrap();
+
+        // declare two more bytes than the file path list has, taking them from the optional data
+        let file_path_list_length = u16::from_le_bytes([buffer[4], buffer[5]]);
+        buffer[4..6].copy_from_slice(&(file_path_list_length + 2).to_le_bytes());
+
+        let result = EFILoadOption::parse(&mut Cursor::new(&buffer));
+        assert!(matches!(
+            result,
+            Err(LoadOptionParseError::FilePathListLengthMismatch { declared, consumed })
+                if declared == file_path_list_length + 2 && consumed == file_path_list_length
+        ), "{result:?}");
+
+        // the optional data starts after the declared length, not after the end node
+        let lenient = EFILoadOption::parse_lenient(&mut Cursor::new(&buffer)).unwrap();
+        assert_eq!(load_option.file_path_list(), lenient.file_path_list());
+        assert_eq!(&load_option.optional_data()[2..], lenient.optional_data());
+    }
+
+    #[test]
+    fn test_efi_load_option_missing_end_node() {
+        let mut load_option = equivalent_load_option();
+        load_option.optional_data = vec![];
+        let mut buffer = vec![];
+        load_option.write(&mut buffer).unwrap();
+
+        // drop the end node from the file path list and shrink the list length to match
+        let file_path_list_length = u16::from_le_bytes([buffer[4], buffer[5]]) - 4;
+        buffer[4..6].copy_from_slice(&file_path_list_length.to_le_bytes());
+        buffer.truncate(buffer.len() - 4);
+
+        let result = EFILoadOption::parse(&mut Cursor::new(buffer));
+        assert!(matches!(result, Err(LoadOptionParseError::MissingEndNode)), "{result:?}");
+    }
+
+    #[test]
+    fn test_efi_load_option_zero_length_node() {
+        let mut load_option = equivalent_load_option();
+        load_option.optional_data = vec![];
+        let mut buffer = vec![];
+        load_option.write(&mut buffer).unwrap();
+
+        // declare a length of 0 in the end node, which can never advance past it
+        let end_length = buffer.len() - 2;
+        buffer[end_length..].copy_from_slice(&[0x00, 0x00]);
+
+        let result = EFILoadOption::parse(&mut Cursor::new(buffer));
+        assert!(matches!(
+            result,
+            Err(LoadOptionParseError::DevicePathProtocolParseError(DevicePathProtocolParseError::NodeTooShort { typ: 0x7F, sub_type: 0xFF, length: 0 }))
+        ), "{result:?}");
+    }
+
+    #[test]
+    fn test_efi_load_option_to_bytes() {
+        let expected = test_variable(boot_entry_name(1));
+
+        assert_eq!(expected.data(), equivalent_load_option().to_bytes().unwrap().as_slice());
+        let bytes = equivalent_load_option().to_bytes_with_attributes(expected.attributes()).unwrap();
+        assert_eq!((expected.attributes(), expected.data()), parse_efivar_payload(&bytes).unwrap());
     }
 }
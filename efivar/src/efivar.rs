@@ -2,15 +2,12 @@ use std::fmt::{Debug, Display, Formatter};
 use std::str::FromStr;
 use enumflags2::BitFlags;
 use thiserror::Error;
-use uuid::Uuid;
+use uuid::{Uuid, uuid};
 
-const EFI_GLOBAL_VENDOR_GID: &'static str = "8be4df61-93ca-11d2-aa0d-00e098032b8c";
+/// Vendor of the variables defined by the UEFI specification, such as `Boot####` and `BootOrder`.
+pub const EFI_GLOBAL_VARIABLE_GUID: Uuid = uuid!("8be4df61-93ca-11d2-aa0d-00e098032b8c");
 
-fn efi_global_vendor_uuid() -> Uuid {
-    Uuid::from_str(EFI_GLOBAL_VENDOR_GID).unwrap()
-}
-
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct VariableName {
     key: String,
     vendor: Uuid,
@@ -43,7 +40,11 @@ impl FromStr for VariableName {
     type Err = VariableNameFromStrError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (key, vendor) = s.split_once('-').ok_or(VariableNameFromStrError::InvalidFormat)?;
+        // the vendor is the 36 character UUID at the end, keys may contain dashes themselves
+        let separator = s.len().checked_sub(37)
+            .filter(|&separator| s.as_bytes()[separator] == b'-')
+            .ok_or(VariableNameFromStrError::InvalidFormat)?;
+        let (key, vendor) = (&s[..separator], &s[separator + 1..]);
         let vendor = Uuid::from_str(vendor)?;
 
         Ok(VariableName {
@@ -62,7 +63,7 @@ impl VariableName {
     }
 
     pub fn global_vendor_new(key: String) -> Self {
-        Self::new(key, efi_global_vendor_uuid())
+        Self::new(key, EFI_GLOBAL_VARIABLE_GUID)
     }
 
     pub fn key(&self) -> &str {
@@ -72,6 +73,13 @@ impl VariableName {
     pub fn vendor(&self) -> &Uuid {
         &self.vendor
     }
+
+    /// The name as efivarfs and efibootmgr spell it, the key followed by the lowercase vendor
+    /// UUID, e.g. `Boot0001-8be4df61-93ca-11d2-aa0d-00e098032b8c`. Parses back with
+    /// [VariableName::from_str].
+    pub fn file_name(&self) -> String {
+        format!("{}-{:x}", self.key, self.vendor)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -90,15 +98,134 @@ impl EFIVariable {
         }
     }
 
+    /// Builds the variable from an efivarfs style payload, the attributes followed by the data.
+    /// See [parse_efivar_payload].
+    pub fn from_raw(name: VariableName, bytes: &[u8]) -> Result<Self, PayloadTooShortError> {
+        let (attributes, data) = parse_efivar_payload(bytes)?;
+        Ok(Self::new(name, attributes, data.to_vec()))
+    }
+
     pub fn name(&self) -> &VariableName {
         &self.name
     }
 
+    pub fn attributes(&self) -> BitFlags<EFIVariableAttribute> {
+        self.attributes
+    }
+
     pub fn data(&self) -> &[u8] {
         &self.data
     }
 }
 
+/// Global variables defined by the UEFI specification.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WellKnownVariable {
+    BootOrder,
+    DriverOrder,
+    SysPrepOrder,
+    BootNext,
+    BootCurrent,
+    Timeout,
+    OsIndications,
+    OsIndicationsSupported,
+    SecureBoot,
+    SetupMode,
+    PlatformLang,
+    PlatformLangCodes,
+    ConIn,
+    ConOut,
+    ErrOut,
+    ConInDev,
+    ConOutDev,
+    ErrOutDev,
+}
+
+impl WellKnownVariable {
+    pub const ALL: [WellKnownVariable; 18] = [
+        WellKnownVariable::BootOrder,
+        WellKnownVariable::DriverOrder,
+        WellKnownVariable::SysPrepOrder,
+        WellKnownVariable::BootNext,
+        WellKnownVariable::BootCurrent,
+        WellKnownVariable::Timeout,
+        WellKnownVariable::OsIndications,
+        WellKnownVariable::OsIndicationsSupported,
+        WellKnownVariable::SecureBoot,
+        WellKnownVariable::SetupMode,
+        WellKnownVariable::PlatformLang,
+        WellKnownVariable::PlatformLangCodes,
+        WellKnownVariable::ConIn,
+        WellKnownVariable::ConOut,
+        WellKnownVariable::ErrOut,
+        WellKnownVariable::ConInDev,
+        WellKnownVariable::ConOutDev,
+        WellKnownVariable::ErrOutDev,
+    ];
+
+    /// Looks up the well-known variable with the name, which has to be in the global vendor
+    /// namespace.
+    pub fn from_name(name: &VariableName) -> Option<Self> {
+        if name.vendor() != &EFI_GLOBAL_VARIABLE_GUID {
+            return None;
+        }
+        Self::ALL.into_iter().find(|variable| variable.key() == name.key())
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            WellKnownVariable::BootOrder => "BootOrder",
+            WellKnownVariable::DriverOrder => "DriverOrder",
+            WellKnownVariable::SysPrepOrder => "SysPrepOrder",
+            WellKnownVariable::BootNext => "BootNext",
+            WellKnownVariable::BootCurrent => "BootCurrent",
+            WellKnownVariable::Timeout => "Timeout",
+            WellKnownVariable::OsIndications => "OsIndications",
+            WellKnownVariable::OsIndicationsSupported => "OsIndicationsSupported",
+            WellKnownVariable::SecureBoot => "SecureBoot",
+            WellKnownVariable::SetupMode => "SetupMode",
+            WellKnownVariable::PlatformLang => "PlatformLang",
+            WellKnownVariable::PlatformLangCodes => "PlatformLangCodes",
+            WellKnownVariable::ConIn => "ConIn",
+            WellKnownVariable::ConOut => "ConOut",
+            WellKnownVariable::ErrOut => "ErrOut",
+            WellKnownVariable::ConInDev => "ConInDev",
+            WellKnownVariable::ConOutDev => "ConOutDev",
+            WellKnownVariable::ErrOutDev => "ErrOutDev",
+        }
+    }
+
+    pub fn variable_name(&self) -> VariableName {
+        VariableName::global_vendor_new(self.key().to_owned())
+    }
+}
+
+/// Size of the attribute header preceding the data of variables in efivarfs and dumps of it.
+pub const EFIVAR_ATTRIBUTES_SIZE: usize = 4;
+
+#[derive(Debug, Error)]
+#[error("efi variable payload of {0} bytes is missing the {EFIVAR_ATTRIBUTES_SIZE}-byte attribute header")]
+pub struct PayloadTooShortError(pub(crate) usize);
+
+/// Encodes the string as UTF-16 code units including the NUL terminator, as strings are stored in
+/// load options, device paths and variables. Sizes are to be derived from the result, so they
+/// always match what is written.
+pub(crate) fn encode_utf16_nul(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain([0x0000]).collect()
+}
+
+/// Splits an efivarfs style payload into the attributes, stored as a little endian `u32` in the
+/// first [EFIVAR_ATTRIBUTES_SIZE] bytes, and the variable data following them. Unknown attribute
+/// bits are dropped.
+pub fn parse_efivar_payload(bytes: &[u8]) -> Result<(BitFlags<EFIVariableAttribute>, &[u8]), PayloadTooShortError> {
+    if bytes.len() < EFIVAR_ATTRIBUTES_SIZE {
+        return Err(PayloadTooShortError(bytes.len()));
+    }
+    let (attributes, data) = bytes.split_at(EFIVAR_ATTRIBUTES_SIZE);
+    let attributes = u32::from_le_bytes(attributes.try_into().unwrap());
+    Ok((BitFlags::from_bits_truncate(attributes), data))
+}
+
 #[enumflags2::bitflags]
 #[repr(u32)]
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -111,3 +238,76 @@ pub enum EFIVariableAttribute {
     TimeBasedAuthenticatedWriteAccess = 0x0000000000000020,
     AppendWrite = 0x0000000000000040,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use enumflags2::make_bitflags;
+    use uuid::Uuid;
+    use crate::efivar::{EFI_GLOBAL_VARIABLE_GUID, EFIVariable, EFIVariableAttribute, encode_utf16_nul, parse_efivar_payload, VariableName, WellKnownVariable};
+
+    #[test]
+    fn test_parse_efivar_payload() {
+        let (attributes, data) = parse_efivar_payload(&[0x07, 0x00, 0x00, 0x00, 0x01, 0x00]).unwrap();
+        assert_eq!(make_bitflags!(EFIVariableAttribute::{NonVolatile | BootServiceAccess | RuntimeAccess}), attributes);
+        assert_eq!(&[0x01, 0x00], data);
+
+        let (_, data) = parse_efivar_payload(&[0x07, 0x00, 0x00, 0x00]).unwrap();
+        assert!(data.is_empty());
+
+        assert!(parse_efivar_payload(&[0x07, 0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_encode_utf16_nul() {
+        assert_eq!(vec![0x0000], encode_utf16_nul(""));
+
+        let encoded = encode_utf16_nul("Arch 🐧");
+        assert_eq!("Arch 🐧".encode_utf16().count() + 1, encoded.len());
+        assert_eq!(Some(&0x0000), encoded.last());
+        assert_eq!("Arch 🐧", String::from_utf16(&encoded[..encoded.len() - 1]).unwrap());
+    }
+
+    #[test]
+    fn test_efivar_from_raw() {
+        let name = VariableName::global_vendor_new("BootNext".to_owned());
+
+        let variable = EFIVariable::from_raw(name.clone(), &[0x07, 0x00, 0x00, 0x00, 0x01, 0x00]).unwrap();
+        assert_eq!(("BootNext", &EFI_GLOBAL_VARIABLE_GUID), (variable.name().key(), variable.name().vendor()));
+        assert_eq!(make_bitflags!(EFIVariableAttribute::{NonVolatile | BootServiceAccess | RuntimeAccess}), variable.attributes());
+        assert_eq!(&[0x01, 0x00], variable.data());
+
+        assert!(EFIVariable::from_raw(name, &[0x07, 0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_file_name() {
+        let fixture = "Boot0001-8be4df61-93ca-11d2-aa0d-00e098032b8c";
+        let name = VariableName::global_vendor_new("Boot0001".to_owned());
+
+        assert_eq!(fixture, name.file_name());
+        assert_eq!(fixture, VariableName::from_str(fixture).unwrap().file_name());
+        assert!(std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test").join(name.file_name()).is_file());
+
+        // the vendor is always written lowercase, and parses back to an equal name
+        let name = VariableName::new("Custom".to_owned(), Uuid::from_str("3C6F5E2A-7B1D-4C8E-9F0A-1B2C3D4E5F60").unwrap());
+        assert_eq!("Custom-3c6f5e2a-7b1d-4c8e-9f0a-1b2c3d4e5f60", name.file_name());
+        assert_eq!(name, VariableName::from_str(&name.file_name()).unwrap());
+
+        // dashes in the key are kept in the key
+        let name = VariableName::new("boot-args".to_owned(), Uuid::from_str("7c436110-ab2a-4bbb-a880-fe41995c9f82").unwrap());
+        assert_eq!("boot-args-7c436110-ab2a-4bbb-a880-fe41995c9f82", name.file_name());
+        assert_eq!(name, VariableName::from_str(&name.file_name()).unwrap());
+
+        assert!(VariableName::from_str("8be4df61-93ca-11d2-aa0d-00e098032b8c").is_err());
+        assert!(VariableName::from_str("Boot0001_8be4df61-93ca-11d2-aa0d-00e098032b8c").is_err());
+    }
+
+    #[test]
+    fn test_well_known_variable_name() {
+        let name = WellKnownVariable::BootOrder.variable_name();
+
+        assert_eq!("BootOrder", name.key());
+        assert_eq!(&EFI_GLOBAL_VARIABLE_GUID, name.vendor());
+    }
+}
@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use crate::blocking::spawn_blocking;
+use crate::efidevicepath::{EFIDevicePathProtocol, ESP_MOUNT_POINTS};
+use crate::resolve::{BY_PARTUUID_DIR, find_partition_uuid, MOUNTS_FILE, parse_mounts};
+
+/// sysfs reports partition offsets and sizes in 512-byte sectors, regardless of the disk's block
+/// size.
+const SYSFS_SECTOR_SIZE: u64 = 512;
+
+/// The location of the mounted EFI system partition, as needed to build a
+/// [EFIDevicePathProtocol::new_hard_drive_gpt] node for new boot entries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EspInfo {
+    partition_number: u32,
+    partition_start: u64,
+    partition_size: u64,
+    partition_uuid: Uuid,
+    mount_point: PathBuf,
+}
+
+impl EspInfo {
+    /// Describes an ESP that wasn't detected with [detect_esp], with the partition start and size
+    /// in logical blocks.
+    pub fn new(partition_number: u32, partition_start: u64, partition_size: u64, partition_uuid: Uuid, mount_point: impl Into<PathBuf>) -> Self {
+        Self { partition_number, partition_start, partition_size, partition_uuid, mount_point: mount_point.into() }
+    }
+
+    pub fn partition_number(&self) -> u32 {
+        self.partition_number
+    }
+
+    /// First logical block of the partition.
+    pub fn partition_start(&self) -> u64 {
+        self.partition_start
+    }
+
+    /// Size of the partition in logical blocks.
+    pub fn partition_size(&self) -> u64 {
+        self.partition_size
+    }
+
+    pub fn partition_uuid(&self) -> &Uuid {
+        &self.partition_uuid
+    }
+
+    pub fn mount_point(&self) -> &Path {
+        &self.mount_point
+    }
+
+    pub fn device_path(&self) -> EFIDevicePathProtocol {
+        EFIDevicePathProtocol::new_hard_drive_gpt(self.partition_number, self.partition_start, self.partition_size, self.partition_uuid)
+    }
+}
+
+/// Detects the mounted EFI system partition of the running system. Returns `None` if no FAT
+/// filesystem is mounted at any of the usual ESP mount points, or it isn't on a GPT disk.
+pub async fn detect_esp() -> Option<EspInfo> {
+    spawn_blocking(detect_esp_blocking).await.ok().flatten()
+}
+
+fn detect_esp_blocking() -> Option<EspInfo> {
+    let (device, mount_point) = find_esp_mount(&fs::read_to_string(MOUNTS_FILE).ok()?)?;
+    let device = fs::canonicalize(device).ok()?;
+    let partition_uuid = find_partition_uuid(Path::new(BY_PARTUUID_DIR), &device)?;
+
+    // /sys/class/block/<partition> links into the disk's directory in /sys/devices
+    let partition_dir = fs::canonicalize(Path::new("/sys/class/block").join(device.file_name()?)).ok()?;
+    let block_size = read_sysfs_u64(&partition_dir.parent()?.join("queue/logical_block_size"))?;
+
+    Some(EspInfo {
+        partition_number: read_sysfs_u64(&partition_dir.join("partition"))?.try_into().ok()?,
+        partition_start: sectors_to_blocks(read_sysfs_u64(&partition_dir.join("start"))?, block_size),
+        partition_size: sectors_to_blocks(read_sysfs_u64(&partition_dir.join("size"))?, block_size),
+        partition_uuid,
+        mount_point,
+    })
+}
+
+/// Finds the device and mount point of the first FAT filesystem mounted at one of the
+/// [ESP_MOUNT_POINTS].
+fn find_esp_mount(mounts: &str) -> Option<(PathBuf, PathBuf)> {
+    let mounts = parse_mounts(mounts)
+        .filter(|mount| mount.fs_type == "vfat")
+        .collect::<Vec<_>>();
+
+    ESP_MOUNT_POINTS.iter().find_map(|mount_point| {
+        mounts.iter()
+            .find(|mount| mount.target == Path::new(mount_point))
+            .map(|mount| (mount.source.clone(), mount.target.clone()))
+    })
+}
+
+fn read_sysfs_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn sectors_to_blocks(sectors: u64, block_size: u64) -> u64 {
+    sectors * SYSFS_SECTOR_SIZE / block_size
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::esp::{find_esp_mount, sectors_to_blocks};
+
+    #[test]
+    fn test_find_esp_mount() {
+        let mounts = "/dev/nvme0n1p2 / ext4 rw,relatime 0 0\n\
+            /dev/sdb1 /boot vfat rw,relatime 0 0\n\
+            /dev/nvme0n1p1 /boot/efi vfat rw,relatime 0 0\n";
+
+        assert_eq!(Some((PathBuf::from("/dev/nvme0n1p1"), PathBuf::from("/boot/efi"))), find_esp_mount(mounts));
+        assert_eq!(None, find_esp_mount("/dev/nvme0n1p2 /boot ext4 rw,relatime 0 0\n"));
+    }
+
+    #[test]
+    fn test_sectors_to_blocks() {
+        assert_eq!(0x800, sectors_to_blocks(0x800, 512));
+        assert_eq!(0x100, sectors_to_blocks(0x800, 4096));
+    }
+}
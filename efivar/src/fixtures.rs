@@ -0,0 +1,24 @@
+//! Variables dumped from efivarfs into `src/test`, shared by the tests of several modules.
+
+use std::fs;
+use std::path::Path;
+use crate::efivar::{EFIVariable, VariableName};
+
+/// Ids of the synthetic `Boot####` fixtures, beside the `Boot0001` dump.
+pub(crate) const BOOT_NVME: u16 = 0x0002;
+pub(crate) const BOOT_USB: u16 = 0x0003;
+pub(crate) const BOOT_PXE_IPV4: u16 = 0x0004;
+pub(crate) const BOOT_CDROM: u16 = 0x0005;
+
+/// Reads the fixture of the variable, stored under its efivarfs file name.
+pub(crate) fn test_variable(name: VariableName) -> EFIVariable {
+    test_variable_as(&name.clone(), name)
+}
+
+/// Reads the fixture of the `fixture` variable, as a variable named `name`. For copies of an entry
+/// under a different id or vendor.
+pub(crate) fn test_variable_as(fixture: &VariableName, name: VariableName) -> EFIVariable {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test").join(fixture.file_name());
+    let bytes = fs::read(&path).unwrap_or_else(|err| panic!("error reading fixture {path:?}: {err}"));
+    EFIVariable::from_raw(name, &bytes).unwrap()
+}
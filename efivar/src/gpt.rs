@@ -0,0 +1,297 @@
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+#[cfg(target_os = "linux")]
+use std::fs;
+#[cfg(target_os = "linux")]
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use uuid::Uuid;
+use crate::crc32::crc32;
+use crate::efidevicepath::HardDriveDevicePath;
+
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+/// Logical block sizes tried when looking for the GPT header, which is always in LBA 1.
+const BLOCK_SIZES: [u64; 2] = [512, 4096];
+/// Size of the header fields defined by revision 1.0, larger headers are padded with zeros.
+const MIN_HEADER_SIZE: usize = 92;
+const MIN_ENTRY_SIZE: usize = 128;
+/// Upper bound for the partition entry array, far above the usual 128 entries of 128 bytes.
+const MAX_ENTRY_ARRAY_SIZE: usize = 1024 * 1024;
+const PROTECTIVE_MBR_TYPE: u8 = 0xEE;
+
+#[derive(Debug, Error)]
+pub enum GptError {
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+    #[error("disk has no protective MBR, it isn't partitioned with GPT")]
+    NoProtectiveMbr,
+    #[error("no GPT header found")]
+    NoHeader,
+    #[error("invalid GPT header: {0}")]
+    InvalidHeader(&'static str),
+    #[error("GPT header checksum doesn't match")]
+    HeaderChecksumMismatch,
+    #[error("GPT partition entry array checksum doesn't match")]
+    EntriesChecksumMismatch,
+    #[error("partition {0} doesn't exist in the GPT")]
+    NoSuchPartition(u32),
+    #[cfg(target_os = "linux")]
+    #[error("{0:?} is not a partition of a block device")]
+    NotAPartition(PathBuf),
+}
+
+/// A used entry of the partition entry array.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GptPartition {
+    number: u32,
+    type_guid: Uuid,
+    unique_guid: Uuid,
+    first_lba: u64,
+    last_lba: u64,
+    size: u64,
+}
+
+impl GptPartition {
+    /// 1-based index in the partition entry array, as used in device names like `/dev/sda1`.
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    pub fn type_guid(&self) -> &Uuid {
+        &self.type_guid
+    }
+
+    /// The partition's GUID, also known as its PARTUUID.
+    pub fn unique_guid(&self) -> &Uuid {
+        &self.unique_guid
+    }
+
+    pub fn first_lba(&self) -> u64 {
+        self.first_lba
+    }
+
+    /// Last logical block of the partition, inclusive.
+    pub fn last_lba(&self) -> u64 {
+        self.last_lba
+    }
+
+    /// Size of the partition in logical blocks.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn hard_drive_device_path(&self) -> HardDriveDevicePath {
+        HardDriveDevicePath::new_gpt(self.number, self.first_lba, self.size(), self.unique_guid)
+    }
+}
+
+/// The primary GUID partition table of a disk. The backup table at the end of the disk isn't read.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GuidPartitionTable {
+    block_size: u64,
+    disk_guid: Uuid,
+    partitions: Vec<GptPartition>,
+}
+
+impl GuidPartitionTable {
+    /// Reads the table from a disk image or block device, detecting 512 and 4096 byte logical
+    /// blocks. Both the header and the partition entry array have to match their checksums.
+    pub fn read(disk: &mut (impl Read + Seek)) -> Result<Self, GptError> {
+        let mut mbr = [0u8; 512];
+        disk.seek(SeekFrom::Start(0))?;
+        disk.read_exact(&mut mbr)?;
+        let protective = mbr[510..512] == [0x55, 0xAA]
+            && mbr[446..510].chunks_exact(16).any(|entry| entry[4] == PROTECTIVE_MBR_TYPE);
+        if !protective {
+            return Err(GptError::NoProtectiveMbr);
+        }
+
+        for block_size in BLOCK_SIZES {
+            let mut header = vec![0u8; block_size as usize];
+            disk.seek(SeekFrom::Start(block_size))?;
+            match disk.read_exact(&mut header) {
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => continue,
+                result => result?,
+            }
+            if &header[..8] == GPT_SIGNATURE {
+                return Self::read_with_header(disk, block_size, &header);
+            }
+        }
+
+        Err(GptError::NoHeader)
+    }
+
+    fn read_with_header(disk: &mut (impl Read + Seek), block_size: u64, header: &[u8]) -> Result<Self, GptError> {
+        let u32_at = |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+        let u64_at = |offset: usize| u64::from_le_bytes(header[offset..offset + 8].try_into().unwrap());
+
+        let header_size = u32_at(12) as usize;
+        if !(MIN_HEADER_SIZE..=header.len()).contains(&header_size) {
+            return Err(GptError::InvalidHeader("header size out of range"));
+        }
+        // the checksum covers the header with the checksum field zeroed
+        let mut checked = header[..header_size].to_vec();
+        checked[16..20].fill(0);
+        if crc32(&checked) != u32_at(16) {
+            return Err(GptError::HeaderChecksumMismatch);
+        }
+
+        let disk_guid = Uuid::from_bytes_le(header[56..72].try_into().unwrap());
+        let entries_lba = u64_at(72);
+        let entry_count = u32_at(80) as usize;
+        let entry_size = u32_at(84) as usize;
+        if entry_size < MIN_ENTRY_SIZE || !entry_size.is_multiple_of(8) {
+            return Err(GptError::InvalidHeader("partition entry size out of range"));
+        }
+        let array_size = entry_count.checked_mul(entry_size)
+            .filter(|size| *size <= MAX_ENTRY_ARRAY_SIZE)
+            .ok_or(GptError::InvalidHeader("partition entry array too large"))?;
+
+        let mut entries = vec![0u8; array_size];
+        let entries_offset = entries_lba.checked_mul(block_size)
+            .ok_or(GptError::InvalidHeader("partition entry array LBA out of range"))?;
+        disk.seek(SeekFrom::Start(entries_offset))?;
+        disk.read_exact(&mut entries)?;
+        if crc32(&entries) != u32_at(88) {
+            return Err(GptError::EntriesChecksumMismatch);
+        }
+
+        let partitions = entries.chunks_exact(entry_size)
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let type_guid = Uuid::from_bytes_le(entry[0..16].try_into().unwrap());
+                // unused entries are all zeros
+                if type_guid.is_nil() {
+                    return None;
+                }
+                let first_lba = u64::from_le_bytes(entry[32..40].try_into().unwrap());
+                let last_lba = u64::from_le_bytes(entry[40..48].try_into().unwrap());
+                let Some(size) = last_lba.checked_sub(first_lba).and_then(|size| size.checked_add(1)) else {
+                    return Some(Err(GptError::InvalidHeader("partition entry with invalid LBA range")));
+                };
+                Some(Ok(GptPartition {
+                    number: index as u32 + 1,
+                    type_guid,
+                    unique_guid: Uuid::from_bytes_le(entry[16..32].try_into().unwrap()),
+                    first_lba,
+                    last_lba,
+                    size,
+                }))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { block_size, disk_guid, partitions })
+    }
+
+    /// Size of the disk's logical blocks in bytes, which partition offsets and sizes are given in.
+    pub fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    pub fn disk_guid(&self) -> &Uuid {
+        &self.disk_guid
+    }
+
+    /// The used partition entries, ordered by number.
+    pub fn partitions(&self) -> &[GptPartition] {
+        &self.partitions
+    }
+
+    pub fn partition(&self, number: u32) -> Option<&GptPartition> {
+        self.partitions.iter().find(|partition| partition.number == number)
+    }
+}
+
+/// Finds the disk and partition number of a partition device like `/dev/nvme0n1p1` through sysfs,
+/// and reads the partition from the disk's GPT. Reading the disk usually requires root.
+#[cfg(target_os = "linux")]
+pub fn read_partition_device(partition: &Path) -> Result<HardDriveDevicePath, GptError> {
+    let not_a_partition = || GptError::NotAPartition(partition.to_owned());
+
+    let device = fs::canonicalize(partition)?;
+    // /sys/class/block/<partition> links into the disk's directory in /sys/devices
+    let partition_dir = fs::canonicalize(Path::new("/sys/class/block").join(device.file_name().ok_or_else(not_a_partition)?))
+        .map_err(|_| not_a_partition())?;
+    let number = fs::read_to_string(partition_dir.join("partition")).ok()
+        .and_then(|number| number.trim().parse().ok())
+        .ok_or_else(not_a_partition)?;
+    let disk = partition_dir.parent().and_then(Path::file_name).ok_or_else(not_a_partition)?;
+
+    HardDriveDevicePath::from_gpt_partition(&mut fs::File::open(Path::new("/dev").join(disk))?, number)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::str::FromStr;
+    use uuid::Uuid;
+    use crate::crc32::crc32;
+    use crate::efidevicepath::HardDriveDevicePath;
+    use crate::gpt::{GptError, GuidPartitionTable};
+
+    const GPT_DISK: &[u8] = include_bytes!("test/fixtures/gpt-disk");
+
+    /// Updates the fixture's entry array and header checksums after modifying them.
+    fn update_checksums(disk: &mut [u8]) {
+        let entries_crc = crc32(&disk[1024..1024 + 128 * 128]);
+        disk[512 + 88..512 + 92].copy_from_slice(&entries_crc.to_le_bytes());
+        disk[512 + 16..512 + 20].fill(0);
+        let header_crc = crc32(&disk[512..512 + 92]);
+        disk[512 + 16..512 + 20].copy_from_slice(&header_crc.to_le_bytes());
+    }
+
+    #[test]
+    fn test_read_gpt() {
+        let table = GuidPartitionTable::read(&mut Cursor::new(GPT_DISK)).unwrap();
+
+        assert_eq!(512, table.block_size());
+        assert_eq!(Uuid::from_str("5c1d2e3f-4a5b-4c6d-9e7f-8091a2b3c4d5").unwrap(), *table.disk_guid());
+        assert_eq!(vec![1, 2], table.partitions().iter().map(|partition| partition.number()).collect::<Vec<_>>());
+
+        let esp = table.partition(1).unwrap();
+        assert_eq!(Uuid::from_str("c12a7328-f81f-11d2-ba4b-00a0c93ec93b").unwrap(), *esp.type_guid());
+        assert_eq!(2048, esp.first_lba());
+        assert_eq!(67583, esp.last_lba());
+        assert_eq!(0x10000, esp.size());
+    }
+
+    #[test]
+    fn test_hard_drive_from_gpt_partition() {
+        let device_path = HardDriveDevicePath::from_gpt_partition(&mut Cursor::new(GPT_DISK), 1).unwrap();
+        let expected = HardDriveDevicePath::new_gpt(1, 2048, 0x10000, Uuid::from_str("eba9a856-dfdd-42eb-be76-31760ae90f55").unwrap());
+        assert_eq!(expected, device_path);
+
+        let result = HardDriveDevicePath::from_gpt_partition(&mut Cursor::new(GPT_DISK), 3);
+        assert!(matches!(result, Err(GptError::NoSuchPartition(3))), "{result:?}");
+    }
+
+    #[test]
+    fn test_read_gpt_corrupt() {
+        let mut disk = GPT_DISK.to_vec();
+        // a byte of the first partition entry's name
+        disk[1024 + 60] ^= 0xFF;
+        let result = GuidPartitionTable::read(&mut Cursor::new(&disk));
+        assert!(matches!(result, Err(GptError::EntriesChecksumMismatch)), "{result:?}");
+
+        let mut disk = GPT_DISK.to_vec();
+        disk[510] = 0x00;
+        let result = GuidPartitionTable::read(&mut Cursor::new(&disk));
+        assert!(matches!(result, Err(GptError::NoProtectiveMbr)), "{result:?}");
+    }
+
+    #[test]
+    fn test_read_gpt_overflowing_lbas() {
+        let mut disk = GPT_DISK.to_vec();
+        disk[512 + 72..512 + 80].copy_from_slice(&u64::MAX.to_le_bytes());
+        update_checksums(&mut disk);
+        let result = GuidPartitionTable::read(&mut Cursor::new(&disk));
+        assert!(matches!(result, Err(GptError::InvalidHeader(_))), "{result:?}");
+
+        let mut disk = GPT_DISK.to_vec();
+        disk[1024 + 32..1024 + 40].copy_from_slice(&0u64.to_le_bytes());
+        disk[1024 + 40..1024 + 48].copy_from_slice(&u64::MAX.to_le_bytes());
+        update_checksums(&mut disk);
+        let result = GuidPartitionTable::read(&mut Cursor::new(&disk));
+        assert!(matches!(result, Err(GptError::InvalidHeader(_))), "{result:?}");
+    }
+}
@@ -1,5 +1,24 @@
 pub mod backend;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod blocking;
+pub mod console;
+mod crc32;
+pub mod decode;
 pub mod efiboot;
 pub mod efidevicepath;
 pub mod efiloadoption;
 pub mod efivar;
+#[cfg(target_os = "linux")]
+pub mod esp;
+#[cfg(test)]
+mod fixtures;
+pub mod gpt;
+pub mod platform_lang;
+pub mod raw;
+#[cfg(all(target_os = "linux", feature = "gvfs"))]
+pub mod reboot;
+pub mod resolve;
+pub mod secure_boot;
+pub mod signature_list;
+pub mod systemd_boot;
+pub mod transaction;
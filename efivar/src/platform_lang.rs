@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use log::debug;
+use crate::backend::EFIVars;
+use crate::efivar::WellKnownVariable;
+
+#[async_trait(? Send)]
+pub trait PlatformLangExt: EFIVars {
+    /// Reads the `PlatformLang` variable, the RFC 4646 language code the firmware is set to, like
+    /// `en-US`. Returns `None` if the variable is missing or can't be read.
+    async fn platform_lang(&self) -> Option<String>;
+
+    /// Reads the `PlatformLangCodes` variable, listing the language codes the firmware supports.
+    /// Returns `None` if the variable is missing or can't be read.
+    async fn platform_lang_codes(&self) -> Option<Vec<String>>;
+}
+
+/// Reads a global NUL-terminated ASCII string variable, as used by `PlatformLang` and
+/// `PlatformLangCodes`.
+async fn read_ascii_variable<E: EFIVars + ?Sized>(efivars: &E, variable: WellKnownVariable) -> Option<String> {
+    let key = variable.key();
+    let variable = match efivars.read_variable(&variable.variable_name()).await? {
+        Ok(variable) => variable,
+        Err(err) => {
+            debug!("Failed to read {} variable: {}", key, err);
+            return None;
+        }
+    };
+
+    let data = variable.data();
+    let data = data.iter().position(|byte| *byte == 0x00).map_or(data, |end| &data[..end]);
+    if !data.is_ascii() {
+        debug!("{} variable is not ASCII", key);
+        return None;
+    }
+    Some(String::from_utf8_lossy(data).into_owned())
+}
+
+#[async_trait(? Send)]
+impl<E> PlatformLangExt for E
+    where E: EFIVars {
+    async fn platform_lang(&self) -> Option<String> {
+        read_ascii_variable(self, WellKnownVariable::PlatformLang).await
+    }
+
+    async fn platform_lang_codes(&self) -> Option<Vec<String>> {
+        let codes = read_ascii_variable(self, WellKnownVariable::PlatformLangCodes).await?;
+        Some(codes.split(';').filter(|code| !code.is_empty()).map(str::to_owned).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use enumflags2::make_bitflags;
+    use futures::executor::block_on;
+    use crate::backend::mock::MemoryEFIVars;
+    use crate::efivar::{EFIVariable, EFIVariableAttribute, VariableName};
+    use crate::platform_lang::PlatformLangExt;
+
+    #[test]
+    fn test_platform_lang() {
+        let attributes = make_bitflags!(EFIVariableAttribute::{NonVolatile | BootServiceAccess | RuntimeAccess});
+        let efivars = MemoryEFIVars::with_variables([
+            EFIVariable::new(VariableName::global_vendor_new("PlatformLang".to_owned()), attributes, b"en-US\0".to_vec()),
+            EFIVariable::new(VariableName::global_vendor_new("PlatformLangCodes".to_owned()), attributes, b"en-US;fr-FR\0".to_vec()),
+        ]);
+
+        assert_eq!(Some("en-US".to_owned()), block_on(efivars.platform_lang()));
+        assert_eq!(Some(vec!["en-US".to_owned(), "fr-FR".to_owned()]), block_on(efivars.platform_lang_codes()));
+        assert_eq!(None, block_on(MemoryEFIVars::new().platform_lang()));
+    }
+}
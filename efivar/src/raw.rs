@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+use enumflags2::BitFlags;
+use log::debug;
+use thiserror::Error;
+use crate::backend::EFIVars;
+use crate::efivar::{EFIVariable, EFIVariableAttribute, VariableName};
+
+/// Short attribute names as used by efivar and efibootmgr, e.g. `NV,BS,RT`.
+const ATTRIBUTE_ABBREVIATIONS: [(&str, EFIVariableAttribute); 7] = [
+    ("NV", EFIVariableAttribute::NonVolatile),
+    ("BS", EFIVariableAttribute::BootServiceAccess),
+    ("RT", EFIVariableAttribute::RuntimeAccess),
+    ("HR", EFIVariableAttribute::HardwareErrorRecord),
+    ("AT", EFIVariableAttribute::AuthenticatedWriteAccess),
+    ("TA", EFIVariableAttribute::TimeBasedAuthenticatedWriteAccess),
+    ("AP", EFIVariableAttribute::AppendWrite),
+];
+
+#[derive(Debug, Error)]
+#[error("unknown efi variable attribute {0:?}")]
+pub struct UnknownAttributeError(pub String);
+
+/// Parses a comma separated list of [ATTRIBUTE_ABBREVIATIONS], like `NV,BS,RT`. Case and
+/// whitespace around the names are ignored, an empty list means no attributes.
+pub fn parse_attributes(list: &str) -> Result<BitFlags<EFIVariableAttribute>, UnknownAttributeError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| ATTRIBUTE_ABBREVIATIONS.iter()
+            .find(|(abbreviation, _)| abbreviation.eq_ignore_ascii_case(name))
+            .map(|(_, attribute)| BitFlags::from(*attribute))
+            .ok_or_else(|| UnknownAttributeError(name.to_owned())))
+        .collect()
+}
+
+#[derive(Debug, Error)]
+pub enum WriteRawVariableError<E: EFIVars> {
+    #[error("the efi variable backend does not support writing variables")]
+    Unsupported,
+    #[error("error writing efi variable: {0}")]
+    WriteVariableError(#[source] E::WriteError),
+}
+
+#[async_trait(? Send)]
+pub trait RawVariableExt: EFIVars + Sized {
+    /// Writes arbitrary data to any variable, for browsing and testing variables this crate has no
+    /// dedicated helpers for. Nothing is validated, prefer the typed helpers like
+    /// [ListBootEntriesExt](crate::efiboot::ListBootEntriesExt) where they exist. Fails up front
+    /// with [WriteRawVariableError::Unsupported] if the backend's capabilities don't allow the write.
+    async fn write_variable_raw(&self, name: &VariableName, attributes: BitFlags<EFIVariableAttribute>, data: &[u8]) -> Result<(), WriteRawVariableError<Self>>;
+}
+
+#[async_trait(? Send)]
+impl<E> RawVariableExt for E
+    where E: EFIVars {
+    async fn write_variable_raw(&self, name: &VariableName, attributes: BitFlags<EFIVariableAttribute>, data: &[u8]) -> Result<(), WriteRawVariableError<E>> {
+        let capabilities = self.capabilities();
+        let supported = if attributes.contains(EFIVariableAttribute::AppendWrite) { capabilities.append } else { capabilities.write };
+        if !supported {
+            return Err(WriteRawVariableError::Unsupported);
+        }
+
+        debug!("Writing {} bytes to {:?} with attributes {:?}", data.len(), name, attributes);
+
+        self.write_variable(&EFIVariable::new(name.clone(), attributes, data.to_vec())).await
+            .map_err(WriteRawVariableError::WriteVariableError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use enumflags2::{BitFlags, make_bitflags};
+    use futures::executor::block_on;
+    use crate::backend::EFIVars;
+    use crate::backend::mock::MemoryEFIVars;
+    use crate::backend::readonly::ReadOnlyBackend;
+    use crate::efivar::{EFIVariableAttribute, VariableName};
+    use crate::raw::{parse_attributes, RawVariableExt, WriteRawVariableError};
+
+    #[test]
+    fn test_parse_attributes() {
+        assert_eq!(make_bitflags!(EFIVariableAttribute::{NonVolatile | BootServiceAccess | RuntimeAccess}), parse_attributes("NV,BS,RT").unwrap());
+        assert_eq!(make_bitflags!(EFIVariableAttribute::{NonVolatile | AppendWrite}), parse_attributes(" nv , AP ").unwrap());
+        assert_eq!(BitFlags::empty(), parse_attributes("").unwrap());
+        assert_eq!("XX", parse_attributes("NV,XX").unwrap_err().0);
+    }
+
+    #[test]
+    fn test_write_variable_raw() {
+        let name = VariableName::from_str("Custom-3c6f5e2a-7b1d-4c8e-9f0a-1b2c3d4e5f60").unwrap();
+        let attributes = make_bitflags!(EFIVariableAttribute::{NonVolatile | BootServiceAccess});
+        let efivars = MemoryEFIVars::new();
+
+        block_on(efivars.write_variable_raw(&name, attributes, &[0xDE, 0xAD])).unwrap();
+
+        let variable = block_on(efivars.read_variable(&name)).unwrap().unwrap();
+        assert_eq!(attributes, variable.attributes());
+        assert_eq!(&[0xDE, 0xAD], variable.data());
+    }
+
+    #[test]
+    fn test_write_variable_raw_read_only() {
+        let name = VariableName::global_vendor_new("Custom".to_owned());
+        let efivars = ReadOnlyBackend::new(MemoryEFIVars::new());
+
+        let result = block_on(efivars.write_variable_raw(&name, BitFlags::empty(), &[0x01]));
+        assert!(matches!(result, Err(WriteRawVariableError::Unsupported)));
+        assert!(block_on(efivars.read_variable(&name)).is_none());
+    }
+}
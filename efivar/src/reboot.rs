@@ -0,0 +1,74 @@
+use gio::{glib, BusType, DBusCallFlags, DBusError};
+use gio::glib::ToVariant;
+use thiserror::Error;
+
+const LOGIN1_BUS_NAME: &str = "org.freedesktop.login1";
+const LOGIN1_OBJECT_PATH: &str = "/org/freedesktop/login1";
+const LOGIN1_MANAGER_INTERFACE: &str = "org.freedesktop.login1.Manager";
+
+/// Remote D-Bus errors meaning the caller isn't allowed to reboot the system.
+const DENIED_ERRORS: [&str; 2] = [
+    "org.freedesktop.DBus.Error.AccessDenied",
+    "org.freedesktop.DBus.Error.InteractiveAuthorizationRequired",
+];
+
+#[derive(Debug, Error)]
+pub enum RebootError {
+    #[error("glib produced an error while requesting a reboot: {0}")]
+    GLibError(#[from] glib::Error),
+    #[error("reboot was denied: {0}")]
+    Denied(String),
+}
+
+/// Asks logind to reboot the system, which lets polkit prompt the user for authorization if
+/// needed. Returns once logind has accepted the request.
+pub async fn request_reboot() -> Result<(), RebootError> {
+    let connection = gio::bus_get_future(BusType::System).await?;
+    connection.call_future(
+        Some(LOGIN1_BUS_NAME),
+        LOGIN1_OBJECT_PATH,
+        LOGIN1_MANAGER_INTERFACE,
+        "Reboot",
+        // interactive, allowing polkit to ask for authentication
+        Some(&(true,).to_variant()),
+        None,
+        DBusCallFlags::ALLOW_INTERACTIVE_AUTHORIZATION,
+        -1,
+    ).await.map_err(reboot_error)?;
+
+    Ok(())
+}
+
+/// Checks the name of the remote D-Bus error, the message is kept without the name gio prefixes
+/// it with.
+fn reboot_error(mut err: glib::Error) -> RebootError {
+    match DBusError::remote_error(&err) {
+        Some(name) if DENIED_ERRORS.contains(&name.as_str()) => {
+            DBusError::strip_remote_error(&mut err);
+            RebootError::Denied(err.message().to_owned())
+        }
+        _ => RebootError::GLibError(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gio::{glib, DBusError};
+    use crate::reboot::{reboot_error, RebootError};
+
+    #[test]
+    fn test_reboot_error() {
+        let err = DBusError::new_for_dbus_error("org.freedesktop.DBus.Error.AccessDenied", "Permission denied");
+        assert!(matches!(reboot_error(err), RebootError::Denied(message) if message == "Permission denied"));
+
+        let err = DBusError::new_for_dbus_error("org.freedesktop.DBus.Error.InteractiveAuthorizationRequired", "Interactive authentication required.");
+        assert!(matches!(reboot_error(err), RebootError::Denied(message) if message == "Interactive authentication required."));
+
+        let err = DBusError::new_for_dbus_error("org.freedesktop.DBus.Error.ServiceUnknown", "Not found");
+        assert!(matches!(reboot_error(err), RebootError::GLibError(_)));
+
+        // a local error mentioning a denied error isn't one
+        let err = glib::Error::new(gio::IOErrorEnum::Failed, "org.freedesktop.DBus.Error.AccessDenied: Permission denied");
+        assert!(matches!(reboot_error(err), RebootError::GLibError(_)));
+    }
+}
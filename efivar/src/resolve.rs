@@ -0,0 +1,293 @@
+#[cfg(target_os = "linux")]
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use crate::efidevicepath::{EFIDevicePathProtocol, MediaDevicePath, Signature};
+
+#[cfg(target_os = "linux")]
+pub(crate) const BY_PARTUUID_DIR: &str = "/dev/disk/by-partuuid";
+#[cfg(target_os = "linux")]
+pub(crate) const MOUNTS_FILE: &str = "/proc/self/mounts";
+
+/// How well a device path matches the currently running system.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EntryHealth {
+    /// The file the device path points at exists.
+    Ok,
+    /// The partition is mounted, but the file doesn't exist on it.
+    FileMissing,
+    /// No mounted partition has the GPT UUID of the device path.
+    PartitionMissing,
+    /// The device path doesn't consist of a GPT hard drive and file path, e.g. firmware
+    /// applications and network boot entries.
+    Unresolvable,
+}
+
+/// Filesystem access needed to resolve device paths, injectable for testing.
+pub trait FileSystemProbe {
+    /// Mount point of the partition with the GPT UUID, or `None` if it isn't mounted.
+    fn partition_mount_point(&self, partition_uuid: &Uuid) -> Option<PathBuf>;
+
+    /// Whether a partition with the GPT UUID exists on the system, mounted or not.
+    fn partition_exists(&self, partition_uuid: &Uuid) -> bool {
+        self.partition_mount_point(partition_uuid).is_some()
+    }
+
+    fn file_exists(&self, path: &Path) -> bool;
+}
+
+/// Probes the running system through `/dev/disk/by-partuuid` and `/proc/self/mounts`.
+#[cfg(target_os = "linux")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemProbe;
+
+#[cfg(target_os = "linux")]
+impl FileSystemProbe for SystemProbe {
+    fn partition_mount_point(&self, partition_uuid: &Uuid) -> Option<PathBuf> {
+        let device = find_partition_device(Path::new(BY_PARTUUID_DIR), partition_uuid)?;
+        find_mount_point(&fs::read_to_string(MOUNTS_FILE).ok()?, &device)
+    }
+
+    fn partition_exists(&self, partition_uuid: &Uuid) -> bool {
+        find_partition_device(Path::new(BY_PARTUUID_DIR), partition_uuid).is_some()
+    }
+
+    fn file_exists(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+}
+
+/// Classifies a device path consisting of a GPT [MediaDevicePath::HardDrive] and a
+/// [MediaDevicePath::FilePath].
+pub fn device_path_health(device_path: &[EFIDevicePathProtocol], probe: &impl FileSystemProbe) -> EntryHealth {
+    let Some((partition_uuid, file_path)) = gpt_file_path(device_path) else {
+        return EntryHealth::Unresolvable;
+    };
+    let Some(mount_point) = probe.partition_mount_point(&partition_uuid) else {
+        return EntryHealth::PartitionMissing;
+    };
+
+    if probe.file_exists(&mount_point.join(efi_path_to_relative(file_path))) {
+        EntryHealth::Ok
+    } else {
+        EntryHealth::FileMissing
+    }
+}
+
+/// The first GPT partition the device path references that doesn't exist on the system. Catches
+/// entries written with a wrong partition UUID, which the firmware silently fails to boot.
+pub fn find_missing_partition(device_path: &[EFIDevicePathProtocol], probe: &(impl FileSystemProbe + ?Sized)) -> Option<Uuid> {
+    device_path.iter()
+        .filter_map(|node| match node {
+            EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::HardDrive(hard_drive)) => match hard_drive.signature() {
+                Signature::GUID(uuid) => Some(*uuid),
+                _ => None,
+            },
+            _ => None,
+        })
+        .find(|uuid| !probe.partition_exists(uuid))
+}
+
+/// Resolves a device path consisting of a GPT [MediaDevicePath::HardDrive] and a
+/// [MediaDevicePath::FilePath] to the file on the currently mounted system. Returns `None` if the
+/// partition isn't mounted or the file doesn't exist.
+#[cfg(target_os = "linux")]
+pub fn resolve_device_path(device_path: &[EFIDevicePathProtocol]) -> Option<PathBuf> {
+    let (partition_uuid, file_path) = gpt_file_path(device_path)?;
+    let path = SystemProbe.partition_mount_point(&partition_uuid)?.join(efi_path_to_relative(file_path));
+
+    SystemProbe.file_exists(&path).then_some(path)
+}
+
+/// The first GPT partition UUID and file path in the device path.
+fn gpt_file_path(device_path: &[EFIDevicePathProtocol]) -> Option<(Uuid, &str)> {
+    let mut partition_uuid = None;
+    let mut file_path = None;
+    for node in device_path {
+        match node {
+            EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::HardDrive(hard_drive)) => {
+                if let Signature::GUID(uuid) = hard_drive.signature() {
+                    partition_uuid.get_or_insert(*uuid);
+                }
+            }
+            EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::FilePath(path)) => {
+                file_path.get_or_insert(path.path_name());
+            }
+            _ => {}
+        }
+    }
+
+    Some((partition_uuid?, file_path?))
+}
+
+/// Finds the canonical device node of the partition among the `by-partuuid` symlinks.
+#[cfg(target_os = "linux")]
+fn find_partition_device(by_partuuid: &Path, uuid: &Uuid) -> Option<PathBuf> {
+    fs::read_dir(by_partuuid).ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| entry.file_name().to_str().is_some_and(|name| matches_partition_uuid(name, uuid)))
+        .and_then(|entry| fs::canonicalize(entry.path()).ok())
+}
+
+/// Finds the GPT UUID of the partition whose `by-partuuid` symlink points at the device node.
+#[cfg(target_os = "linux")]
+pub(crate) fn find_partition_uuid(by_partuuid: &Path, device: &Path) -> Option<Uuid> {
+    fs::read_dir(by_partuuid).ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| fs::canonicalize(entry.path()).is_ok_and(|path| path == device))
+        .find_map(|entry| Uuid::parse_str(entry.file_name().to_str()?).ok())
+}
+
+/// udev names GPT partitions by their lowercase UUID, but the comparison is done on the parsed
+/// value to be safe. MBR partitions are named `<disk signature>-<partition>` and never match.
+#[cfg(target_os = "linux")]
+fn matches_partition_uuid(name: &str, uuid: &Uuid) -> bool {
+    Uuid::parse_str(name).is_ok_and(|name| name == *uuid)
+}
+
+/// An entry of `/proc/self/mounts`.
+#[cfg(target_os = "linux")]
+pub(crate) struct MountEntry {
+    pub(crate) source: PathBuf,
+    pub(crate) target: PathBuf,
+    pub(crate) fs_type: String,
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn parse_mounts(mounts: &str) -> impl Iterator<Item=MountEntry> + '_ {
+    mounts.lines().filter_map(|line| {
+        let mut fields = line.split_whitespace();
+        Some(MountEntry {
+            source: PathBuf::from(unescape_mount_field(fields.next()?)),
+            target: PathBuf::from(unescape_mount_field(fields.next()?)),
+            fs_type: fields.next()?.to_owned(),
+        })
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn find_mount_point(mounts: &str, device: &Path) -> Option<PathBuf> {
+    parse_mounts(mounts)
+        .find(|mount| mount.source == device)
+        .map(|mount| mount.target)
+}
+
+/// `/proc/self/mounts` escapes whitespace and backslashes as octal sequences.
+#[cfg(target_os = "linux")]
+fn unescape_mount_field(field: &str) -> String {
+    field.replace("\\040", " ")
+        .replace("\\011", "\t")
+        .replace("\\012", "\n")
+        .replace("\\134", "\\")
+}
+
+/// Converts a `\EFI\BOOT\BOOTX64.EFI` style path to a path relative to the ESP mount point.
+fn efi_path_to_relative(path: &str) -> PathBuf {
+    path.split('\\').filter(|component| !component.is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+    use uuid::Uuid;
+    use crate::efidevicepath::EFIDevicePathProtocol;
+    use crate::resolve::{device_path_health, efi_path_to_relative, EntryHealth, FileSystemProbe, find_missing_partition};
+    #[cfg(target_os = "linux")]
+    use crate::resolve::{find_mount_point, matches_partition_uuid};
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_matches_partition_uuid() {
+        let uuid = Uuid::from_str("5b9e3a4c-1f2d-4e6a-9b8c-7d0e1f2a3b4c").unwrap();
+
+        assert!(matches_partition_uuid("5b9e3a4c-1f2d-4e6a-9b8c-7d0e1f2a3b4c", &uuid));
+        assert!(matches_partition_uuid("5B9E3A4C-1F2D-4E6A-9B8C-7D0E1F2A3B4C", &uuid));
+        assert!(!matches_partition_uuid("5b9e3a4c-1f2d-4e6a-9b8c-7d0e1f2a3b4d", &uuid));
+        assert!(!matches_partition_uuid("d4c3b2a1-01", &uuid));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_find_mount_point() {
+        let mounts = "/dev/nvme0n1p2 / ext4 rw,relatime 0 0\n\
+            /dev/nvme0n1p1 /boot/efi\\040partition vfat rw,relatime 0 0\n";
+
+        assert_eq!(Some(PathBuf::from("/boot/efi partition")), find_mount_point(mounts, Path::new("/dev/nvme0n1p1")));
+        assert_eq!(None, find_mount_point(mounts, Path::new("/dev/sda1")));
+    }
+
+    struct FakeProbe {
+        mount_points: Vec<(Uuid, PathBuf)>,
+        files: Vec<PathBuf>,
+    }
+
+    impl FileSystemProbe for FakeProbe {
+        fn partition_mount_point(&self, partition_uuid: &Uuid) -> Option<PathBuf> {
+            self.mount_points.iter()
+                .find(|(uuid, _)| uuid == partition_uuid)
+                .map(|(_, mount_point)| mount_point.clone())
+        }
+
+        fn file_exists(&self, path: &Path) -> bool {
+            self.files.iter().any(|file| file == path)
+        }
+    }
+
+    fn esp_uuid() -> Uuid {
+        Uuid::from_str("eba9a856-dfdd-42eb-be76-31760ae90f55").unwrap()
+    }
+
+    fn device_path(file_path: &str) -> Vec<EFIDevicePathProtocol> {
+        vec![
+            EFIDevicePathProtocol::new_hard_drive_gpt(1, 0x800, 0x1F4000, esp_uuid()),
+            EFIDevicePathProtocol::new_file_path(file_path),
+        ]
+    }
+
+    fn probe() -> FakeProbe {
+        FakeProbe {
+            mount_points: vec![(esp_uuid(), PathBuf::from("/boot"))],
+            files: vec![PathBuf::from("/boot/EFI/Linux/arch-linux.efi")],
+        }
+    }
+
+    #[test]
+    fn test_device_path_health_ok() {
+        assert_eq!(EntryHealth::Ok, device_path_health(&device_path("\\EFI\\Linux\\arch-linux.efi"), &probe()));
+    }
+
+    #[test]
+    fn test_device_path_health_file_missing() {
+        assert_eq!(EntryHealth::FileMissing, device_path_health(&device_path("\\EFI\\ubuntu\\shimx64.efi"), &probe()));
+    }
+
+    #[test]
+    fn test_device_path_health_partition_missing() {
+        let probe = FakeProbe { mount_points: vec![], files: vec![] };
+
+        assert_eq!(EntryHealth::PartitionMissing, device_path_health(&device_path("\\EFI\\Linux\\arch-linux.efi"), &probe));
+    }
+
+    #[test]
+    fn test_device_path_health_unresolvable() {
+        let file_path_only = vec![EFIDevicePathProtocol::new_file_path("\\EFI\\Linux\\arch-linux.efi")];
+
+        assert_eq!(EntryHealth::Unresolvable, device_path_health(&file_path_only, &probe()));
+        assert_eq!(EntryHealth::Unresolvable, device_path_health(&[], &probe()));
+    }
+
+    #[test]
+    fn test_find_missing_partition() {
+        let missing = FakeProbe { mount_points: vec![], files: vec![] };
+
+        assert_eq!(None, find_missing_partition(&device_path("\\EFI\\Linux\\arch-linux.efi"), &probe()));
+        assert_eq!(Some(esp_uuid()), find_missing_partition(&device_path("\\EFI\\Linux\\arch-linux.efi"), &missing));
+        assert_eq!(None, find_missing_partition(&[EFIDevicePathProtocol::new_file_path("\\EFI\\BOOT\\BOOTX64.EFI")], &missing));
+    }
+
+    #[test]
+    fn test_efi_path_to_relative() {
+        assert_eq!(PathBuf::from("EFI/BOOT/BOOTX64.EFI"), efi_path_to_relative("\\EFI\\BOOT\\BOOTX64.EFI"));
+    }
+}
@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use log::debug;
+use crate::backend::EFIVars;
+use crate::efivar::WellKnownVariable;
+
+#[async_trait(? Send)]
+pub trait SecureBootExt: EFIVars {
+    /// Reads the `SecureBoot` variable. Returns `None` if the variable is missing or can't be read.
+    async fn secure_boot_enabled(&self) -> Option<bool>;
+
+    /// Reads the `SetupMode` variable. Returns `None` if the variable is missing or can't be read.
+    async fn setup_mode(&self) -> Option<bool>;
+}
+
+/// Reads a global 1-byte boolean variable, as used by `SecureBoot` and `SetupMode`.
+async fn read_bool_variable<E: EFIVars + ?Sized>(efivars: &E, variable: WellKnownVariable) -> Option<bool> {
+    let key = variable.key();
+    let variable = match efivars.read_variable(&variable.variable_name()).await? {
+        Ok(variable) => variable,
+        Err(err) => {
+            debug!("Failed to read {} variable: {}", key, err);
+            return None;
+        }
+    };
+
+    match variable.data() {
+        [value] => Some(*value == 1),
+        data => {
+            debug!("Unexpected {} variable size: {}", key, data.len());
+            None
+        }
+    }
+}
+
+#[async_trait(? Send)]
+impl<E> SecureBootExt for E
+    where E: EFIVars {
+    async fn secure_boot_enabled(&self) -> Option<bool> {
+        read_bool_variable(self, WellKnownVariable::SecureBoot).await
+    }
+
+    async fn setup_mode(&self) -> Option<bool> {
+        read_bool_variable(self, WellKnownVariable::SetupMode).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use enumflags2::make_bitflags;
+    use futures::executor::block_on;
+    use crate::backend::mock::MemoryEFIVars;
+    use crate::efivar::{EFIVariable, EFIVariableAttribute, VariableName};
+    use crate::secure_boot::SecureBootExt;
+
+    #[test]
+    fn test_secure_boot_enabled() {
+        let efivars = MemoryEFIVars::with_variables([
+            EFIVariable::new(
+                VariableName::global_vendor_new("SecureBoot".to_owned()),
+                make_bitflags!(EFIVariableAttribute::{BootServiceAccess | RuntimeAccess}),
+                vec![0x01],
+            ),
+        ]);
+
+        assert_eq!(Some(true), block_on(efivars.secure_boot_enabled()));
+        assert_eq!(None, block_on(efivars.setup_mode()));
+    }
+
+    #[test]
+    fn test_setup_mode_disabled() {
+        let efivars = MemoryEFIVars::with_variables([
+            EFIVariable::new(
+                VariableName::global_vendor_new("SetupMode".to_owned()),
+                make_bitflags!(EFIVariableAttribute::{BootServiceAccess | RuntimeAccess}),
+                vec![0x00],
+            ),
+        ]);
+
+        assert_eq!(Some(false), block_on(efivars.setup_mode()));
+    }
+}
@@ -0,0 +1,267 @@
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::io::{Cursor, Read};
+use std::str::FromStr;
+use async_trait::async_trait;
+use byteorder::{LittleEndian, ReadBytesExt};
+use thiserror::Error;
+use uuid::Uuid;
+use crate::backend::EFIVars;
+use crate::efivar::VariableName;
+
+const EFI_IMAGE_SECURITY_DATABASE_GUID: &str = "d719b2cb-3d3a-4596-a3bc-dad00e67656f";
+const EFI_CERT_SHA256_GUID: &str = "c1c41626-504c-4092-aca9-41f936934328";
+const EFI_CERT_X509_GUID: &str = "a5c059a1-94e4-4aa7-87b5-ab155c2bf072";
+
+/// Size of the fixed part of an `EFI_SIGNATURE_LIST`, before the signature header.
+const SIGNATURE_LIST_HEADER_SIZE: u32 = 16 + 4 + 4 + 4;
+/// Size of the `SignatureOwner` guid at the start of every `EFI_SIGNATURE_DATA`.
+const SIGNATURE_OWNER_SIZE: u32 = 16;
+
+#[derive(Debug, Error)]
+pub enum SignatureListParseError {
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+    #[error("signature list size {list_size} is too small for header size {header_size}")]
+    InvalidListSize { list_size: u32, header_size: u32 },
+    #[error("signature size {0} is too small to hold the signature owner")]
+    InvalidSignatureSize(u32),
+    #[error("signature list data of {data_size} bytes is not a multiple of the signature size {signature_size}")]
+    MisalignedSignatures { data_size: u32, signature_size: u32 },
+}
+
+/// Modelled after [https://uefi.org/specs/UEFI/2.10/32_Secure_Boot_and_Driver_Signing.html#efi-signature-data](https://uefi.org/specs/UEFI/2.10/32_Secure_Boot_and_Driver_Signing.html#efi-signature-data)
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignatureList {
+    signature_type: Uuid,
+    header: Vec<u8>,
+    signatures: Vec<SignatureData>,
+}
+
+impl SignatureList {
+    /// Parses all signature lists in the variable data, as stored in the `db`, `dbx`, `KEK` and
+    /// `PK` variables.
+    pub fn parse_all(data: &[u8]) -> Result<Vec<SignatureList>, SignatureListParseError> {
+        let mut read = Cursor::new(data);
+        let mut lists = vec![];
+        while (read.position() as usize) < data.len() {
+            lists.push(SignatureList::parse(&mut read)?);
+        }
+        Ok(lists)
+    }
+
+    pub fn parse(read: &mut impl Read) -> Result<SignatureList, SignatureListParseError> {
+        use SignatureListParseError::*;
+
+        let signature_type = {
+            let mut buffer = [0u8; 16];
+            read.read_exact(&mut buffer)?;
+            Uuid::from_bytes_le(buffer)
+        };
+        let list_size = read.read_u32::<LittleEndian>()?;
+        let header_size = read.read_u32::<LittleEndian>()?;
+        let signature_size = read.read_u32::<LittleEndian>()?;
+
+        let data_size = list_size.checked_sub(SIGNATURE_LIST_HEADER_SIZE)
+            .and_then(|size| size.checked_sub(header_size))
+            .ok_or(InvalidListSize { list_size, header_size })?;
+        if signature_size < SIGNATURE_OWNER_SIZE {
+            return Err(InvalidSignatureSize(signature_size));
+        }
+        if data_size % signature_size != 0 {
+            return Err(MisalignedSignatures { data_size, signature_size });
+        }
+
+        let header = read_bytes(read, header_size)?;
+
+        let mut signatures = vec![];
+        for _ in 0..data_size / signature_size {
+            signatures.push(SignatureData::parse(read, signature_size)?);
+        }
+
+        Ok(SignatureList { signature_type, header, signatures })
+    }
+
+    pub fn signature_type(&self) -> &Uuid {
+        &self.signature_type
+    }
+
+    pub fn is_sha256(&self) -> bool {
+        self.signature_type == Uuid::from_str(EFI_CERT_SHA256_GUID).unwrap()
+    }
+
+    pub fn is_x509(&self) -> bool {
+        self.signature_type == Uuid::from_str(EFI_CERT_X509_GUID).unwrap()
+    }
+
+    pub fn header(&self) -> &[u8] {
+        &self.header
+    }
+
+    pub fn signatures(&self) -> &[SignatureData] {
+        &self.signatures
+    }
+}
+
+/// Reads exactly `size` bytes. The sizes come straight from the variable data, so the buffer
+/// grows with the bytes actually read instead of being allocated up front.
+fn read_bytes(read: &mut impl Read, size: u32) -> io::Result<Vec<u8>> {
+    let mut buffer = vec![];
+    read.take(size.into()).read_to_end(&mut buffer)?;
+    if buffer.len() != size as usize {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(buffer)
+}
+
+#[derive(Clone, PartialEq)]
+pub struct SignatureData {
+    owner: Uuid,
+    data: Vec<u8>,
+}
+
+impl Debug for SignatureData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignatureData")
+            .field("owner", &self.owner)
+            .field("size", &self.data.len())
+            .finish()
+    }
+}
+
+impl SignatureData {
+    fn parse(read: &mut impl Read, signature_size: u32) -> Result<SignatureData, SignatureListParseError> {
+        let owner = {
+            let mut buffer = [0u8; 16];
+            read.read_exact(&mut buffer)?;
+            Uuid::from_bytes_le(buffer)
+        };
+        let data = read_bytes(read, signature_size - SIGNATURE_OWNER_SIZE)?;
+
+        Ok(SignatureData { owner, data })
+    }
+
+    pub fn owner(&self) -> &Uuid {
+        &self.owner
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// The Secure Boot variables holding signature lists.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SignatureDatabase {
+    PK,
+    KEK,
+    Db,
+    Dbx,
+}
+
+impl SignatureDatabase {
+    pub fn variable_name(&self) -> VariableName {
+        match self {
+            SignatureDatabase::PK => VariableName::global_vendor_new("PK".to_owned()),
+            SignatureDatabase::KEK => VariableName::global_vendor_new("KEK".to_owned()),
+            SignatureDatabase::Db => VariableName::new("db".to_owned(), Uuid::from_str(EFI_IMAGE_SECURITY_DATABASE_GUID).unwrap()),
+            SignatureDatabase::Dbx => VariableName::new("dbx".to_owned(), Uuid::from_str(EFI_IMAGE_SECURITY_DATABASE_GUID).unwrap()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReadSignatureDatabaseError<E: EFIVars> {
+    #[error("error reading signature database variable: {0}")]
+    ReadVariableError(#[source] E::ReadError),
+    #[error("error parsing signature database: {0}")]
+    ParseError(#[from] SignatureListParseError),
+}
+
+#[async_trait(? Send)]
+pub trait SignatureDatabaseExt: EFIVars + Sized {
+    async fn read_signature_database(&self, database: SignatureDatabase) -> Option<Result<Vec<SignatureList>, ReadSignatureDatabaseError<Self>>>;
+
+    async fn read_db(&self) -> Option<Result<Vec<SignatureList>, ReadSignatureDatabaseError<Self>>> {
+        self.read_signature_database(SignatureDatabase::Db).await
+    }
+}
+
+#[async_trait(? Send)]
+impl<E> SignatureDatabaseExt for E
+    where E: EFIVars {
+    async fn read_signature_database(&self, database: SignatureDatabase) -> Option<Result<Vec<SignatureList>, ReadSignatureDatabaseError<E>>> {
+        Some(match self.read_variable(&database.variable_name()).await? {
+            Ok(variable) => SignatureList::parse_all(variable.data()).map_err(Into::into),
+            Err(err) => Err(ReadSignatureDatabaseError::ReadVariableError(err)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use futures::executor::block_on;
+    use uuid::Uuid;
+    use crate::backend::mock::MemoryEFIVars;
+    use crate::fixtures::test_variable;
+    use crate::signature_list::{SignatureDatabase, SignatureDatabaseExt, SignatureList, SignatureListParseError};
+
+    const DB_BYTES: &[u8] = include_bytes!("test/db-d719b2cb-3d3a-4596-a3bc-dad00e67656f");
+
+    #[test]
+    fn test_signature_list_parse() {
+        let lists = SignatureList::parse_all(&DB_BYTES[4..]).unwrap();
+        let owner = Uuid::from_str("77fa9abd-0359-4d32-bd60-28f4e78f784b").unwrap();
+
+        assert_eq!(2, lists.len());
+
+        assert!(lists[0].is_x509());
+        assert_eq!(1, lists[0].signatures().len());
+        assert_eq!(&owner, lists[0].signatures()[0].owner());
+        assert_eq!((0..48).collect::<Vec<u8>>(), lists[0].signatures()[0].data());
+
+        assert!(lists[1].is_sha256());
+        assert_eq!(2, lists[1].signatures().len());
+        assert!(lists[1].signatures().iter().all(|signature| signature.data().len() == 32));
+    }
+
+    #[test]
+    fn test_signature_list_truncated() {
+        let result = SignatureList::parse_all(&DB_BYTES[4..DB_BYTES.len() - 1]);
+
+        assert!(matches!(result, Err(SignatureListParseError::IoError(_))));
+    }
+
+    #[test]
+    fn test_signature_list_truncated_huge_sizes() {
+        let list = |list_size: u32, header_size: u32, signature_size: u32| {
+            let mut data = Uuid::from_str("c1c41626-504c-4092-aca9-41f936934328").unwrap().to_bytes_le().to_vec();
+            data.extend_from_slice(&list_size.to_le_bytes());
+            data.extend_from_slice(&header_size.to_le_bytes());
+            data.extend_from_slice(&signature_size.to_le_bytes());
+            data.extend_from_slice(&[0u8; 8]);
+            data
+        };
+
+        let huge_header = SignatureList::parse_all(&list(u32::MAX, u32::MAX - 28, 16));
+        let huge_signature = SignatureList::parse_all(&list(u32::MAX, 0, u32::MAX - 28));
+        let many_signatures = SignatureList::parse_all(&list(u32::MAX - 3, 0, 16));
+
+        assert!(matches!(huge_header, Err(SignatureListParseError::IoError(_))));
+        assert!(matches!(huge_signature, Err(SignatureListParseError::IoError(_))));
+        assert!(matches!(many_signatures, Err(SignatureListParseError::IoError(_))));
+    }
+
+    #[test]
+    fn test_read_db() {
+        let efivars = MemoryEFIVars::with_variables([
+            test_variable(SignatureDatabase::Db.variable_name()),
+        ]);
+
+        let lists = block_on(efivars.read_db()).unwrap().unwrap();
+
+        assert_eq!(SignatureList::parse_all(&DB_BYTES[4..]).unwrap(), lists);
+        assert!(block_on(efivars.read_signature_database(SignatureDatabase::Dbx)).is_none());
+    }
+}
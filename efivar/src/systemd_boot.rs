@@ -0,0 +1,153 @@
+use async_trait::async_trait;
+use enumflags2::{BitFlags, make_bitflags};
+use log::debug;
+use uuid::{Uuid, uuid};
+use crate::backend::EFIVars;
+use crate::efivar::{EFIVariable, EFIVariableAttribute, encode_utf16_nul, VariableName};
+
+/// Vendor of the variables systemd-boot uses to talk to the running system.
+pub const SYSTEMD_BOOT_VENDOR_GUID: Uuid = uuid!("4a67b082-0a4c-41cf-b6c7-440b29bb8c4f");
+
+const LOADER_ENTRY_ONESHOT: &str = "LoaderEntryOneShot";
+const LOADER_CONFIG_TIMEOUT: &str = "LoaderConfigTimeout";
+const LOADER_VARIABLE_ATTRIBUTES: BitFlags<EFIVariableAttribute> = make_bitflags!(EFIVariableAttribute::{NonVolatile | BootServiceAccess | RuntimeAccess});
+
+/// The menu timeout from `loader.conf`, as systemd-boot reports it in `LoaderConfigTimeout`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LoaderTimeout {
+    Seconds(u32),
+    /// Wait for the user to pick an entry.
+    MenuForce,
+    /// Boot the default entry right away, unless a key is pressed.
+    MenuHidden,
+    /// Boot the default entry right away.
+    MenuDisabled,
+}
+
+impl LoaderTimeout {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "menu-force" => Some(LoaderTimeout::MenuForce),
+            "menu-hidden" => Some(LoaderTimeout::MenuHidden),
+            "menu-disabled" => Some(LoaderTimeout::MenuDisabled),
+            seconds => seconds.parse().ok().map(LoaderTimeout::Seconds),
+        }
+    }
+}
+
+#[async_trait(? Send)]
+pub trait SystemdBootExt: EFIVars {
+    /// The systemd-boot entry, like `arch.conf`, booted once on the next boot. Returns `None` if
+    /// none is set or the variable can't be read.
+    async fn get_loader_entry_oneshot(&self) -> Option<String>;
+
+    /// Makes systemd-boot boot the entry once on the next boot, like `bootctl set-oneshot`.
+    async fn set_loader_entry_oneshot(&self, entry: &str) -> Result<(), Self::WriteError>;
+
+    /// The timeout set in `loader.conf`. Returns `None` if systemd-boot didn't report one or the
+    /// variable can't be read.
+    async fn get_loader_config_timeout(&self) -> Option<LoaderTimeout>;
+}
+
+fn loader_variable_name(key: &str) -> VariableName {
+    VariableName::new(key.to_owned(), SYSTEMD_BOOT_VENDOR_GUID)
+}
+
+/// Encodes the string as NUL-terminated UTF-16LE, the way systemd-boot stores strings.
+fn encode_utf16_string(value: &str) -> Vec<u8> {
+    encode_utf16_nul(value).into_iter().flat_map(u16::to_le_bytes).collect()
+}
+
+/// Decodes a UTF-16LE string up to the first NUL, or all of the data if it isn't terminated.
+fn decode_utf16_string(data: &[u8]) -> Option<String> {
+    let chars = data.chunks_exact(2)
+        .map(|char| u16::from_le_bytes([char[0], char[1]]))
+        .take_while(|char| *char != 0x0000)
+        .collect::<Vec<_>>();
+    String::from_utf16(&chars).ok()
+}
+
+async fn read_string_variable<E: EFIVars + ?Sized>(efivars: &E, key: &str) -> Option<String> {
+    let variable = match efivars.read_variable(&loader_variable_name(key)).await? {
+        Ok(variable) => variable,
+        Err(err) => {
+            debug!("Failed to read {} variable: {}", key, err);
+            return None;
+        }
+    };
+
+    let value = decode_utf16_string(variable.data());
+    if value.is_none() {
+        debug!("{} variable is not UTF-16", key);
+    }
+    value
+}
+
+#[async_trait(? Send)]
+impl<E> SystemdBootExt for E
+    where E: EFIVars {
+    async fn get_loader_entry_oneshot(&self) -> Option<String> {
+        read_string_variable(self, LOADER_ENTRY_ONESHOT).await.filter(|entry| !entry.is_empty())
+    }
+
+    async fn set_loader_entry_oneshot(&self, entry: &str) -> Result<(), Self::WriteError> {
+        debug!("Setting {} to {:?}", LOADER_ENTRY_ONESHOT, entry);
+
+        let variable = EFIVariable::new(loader_variable_name(LOADER_ENTRY_ONESHOT), LOADER_VARIABLE_ATTRIBUTES, encode_utf16_string(entry));
+        self.write_variable(&variable).await
+    }
+
+    async fn get_loader_config_timeout(&self) -> Option<LoaderTimeout> {
+        let timeout = read_string_variable(self, LOADER_CONFIG_TIMEOUT).await?;
+        let parsed = LoaderTimeout::parse(&timeout);
+        if parsed.is_none() {
+            debug!("Unrecognized {} value {:?}", LOADER_CONFIG_TIMEOUT, timeout);
+        }
+        parsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use crate::backend::EFIVars;
+    use crate::backend::mock::MemoryEFIVars;
+    use crate::efivar::EFIVariable;
+    use crate::systemd_boot::{decode_utf16_string, encode_utf16_string, LOADER_CONFIG_TIMEOUT, LOADER_VARIABLE_ATTRIBUTES, loader_variable_name, LoaderTimeout, SystemdBootExt};
+
+    #[test]
+    fn test_utf16_string() {
+        assert_eq!(vec![b'a', 0x00, b'.', 0x00, b'c', 0x00, 0x00, 0x00], encode_utf16_string("a.c"));
+        assert_eq!(Some("arch.conf".to_owned()), decode_utf16_string(&encode_utf16_string("arch.conf")));
+        assert_eq!(Some("ab".to_owned()), decode_utf16_string(&[b'a', 0x00, b'b', 0x00]));
+        assert_eq!(None, decode_utf16_string(&[0x00, 0xD8, 0x00, 0x00]));
+    }
+
+    #[test]
+    fn test_loader_entry_oneshot() {
+        let efivars = MemoryEFIVars::new();
+        assert_eq!(None, block_on(efivars.get_loader_entry_oneshot()));
+
+        block_on(efivars.set_loader_entry_oneshot("arch-lts.conf")).unwrap();
+
+        assert_eq!(Some("arch-lts.conf".to_owned()), block_on(efivars.get_loader_entry_oneshot()));
+        let variable = block_on(efivars.read_variable(&loader_variable_name("LoaderEntryOneShot"))).unwrap().unwrap();
+        assert_eq!(LOADER_VARIABLE_ATTRIBUTES, variable.attributes());
+    }
+
+    #[test]
+    fn test_loader_config_timeout() {
+        let timeout = |value: &str| {
+            let efivars = MemoryEFIVars::with_variables([
+                EFIVariable::new(loader_variable_name(LOADER_CONFIG_TIMEOUT), LOADER_VARIABLE_ATTRIBUTES, encode_utf16_string(value)),
+            ]);
+            block_on(efivars.get_loader_config_timeout())
+        };
+
+        assert_eq!(Some(LoaderTimeout::Seconds(5)), timeout("5"));
+        assert_eq!(Some(LoaderTimeout::MenuForce), timeout("menu-force"));
+        assert_eq!(Some(LoaderTimeout::MenuHidden), timeout("menu-hidden"));
+        assert_eq!(None, timeout("soon"));
+        assert_eq!(None, block_on(MemoryEFIVars::new().get_loader_config_timeout()));
+    }
+}
@@ -0,0 +1,255 @@
+use std::io;
+use log::{debug, warn};
+use thiserror::Error;
+use crate::backend::EFIVars;
+use crate::efiboot::{BOOT_VARIABLE_ATTRIBUTES, boot_entry_name, boot_entry_variable, BootOrder};
+use crate::efiloadoption::EFILoadOption;
+use crate::efivar::{EFIVariable, VariableName, WellKnownVariable};
+
+/// Collects changes to the boot configuration and applies them together. Entries are written and
+/// deleted in the order they were added, `BootOrder` is written last so it never references an
+/// entry that failed to be written.
+///
+/// If a change fails, the changes applied before it are rolled back by restoring the variables
+/// as they were read before applying. Rolling back can fail as well, in which case the error
+/// lists the variables left in the changed state.
+#[derive(Debug, Default)]
+pub struct BootConfigTransaction {
+    entries: Vec<(u16, Option<EFILoadOption>)>,
+    order: Option<BootOrder>,
+}
+
+#[derive(Debug, Error)]
+pub enum BootConfigTransactionError<E: EFIVars> {
+    #[error("error reading {0} before applying changes: {1}")]
+    ReadVariableError(VariableName, #[source] E::ReadError),
+    #[error("error serializing efi boot entry: {0}")]
+    SerializeError(#[from] io::Error),
+    #[error("error writing {name}, {} changes could not be rolled back: {source}", rollback_errors.len())]
+    WriteVariableError {
+        name: VariableName,
+        #[source]
+        source: E::WriteError,
+        /// Variables that were changed before the failure, and couldn't be restored.
+        rollback_errors: Vec<(VariableName, E::WriteError)>,
+    },
+}
+
+/// A change to a single variable, `None` deleting it.
+struct Change {
+    name: VariableName,
+    variable: Option<EFIVariable>,
+}
+
+impl BootConfigTransaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_entry(mut self, id: u16, load_option: EFILoadOption) -> Self {
+        self.entries.push((id, Some(load_option)));
+        self
+    }
+
+    pub fn delete_entry(mut self, id: u16) -> Self {
+        self.entries.push((id, None));
+        self
+    }
+
+    pub fn set_order(mut self, order: BootOrder) -> Self {
+        self.order = Some(order);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.order.is_none()
+    }
+
+    pub async fn apply<E: EFIVars>(self, efivars: &E) -> Result<(), BootConfigTransactionError<E>> {
+        self.apply_with_progress(efivars, None).await
+    }
+
+    /// Like [BootConfigTransaction::apply], calling `progress` with the number of variables
+    /// changed so far and the total after each one. Rolling back isn't reported.
+    pub async fn apply_with_progress<E: EFIVars>(self, efivars: &E, progress: Option<&dyn Fn(usize, usize)>) -> Result<(), BootConfigTransactionError<E>> {
+        use BootConfigTransactionError::*;
+
+        let mut changes = vec![];
+        let mut previous = vec![];
+        for (id, load_option) in self.entries {
+            let name = boot_entry_name(id);
+            let existing = efivars.read_variable(&name).await
+                .transpose().map_err(|err| ReadVariableError(name.clone(), err))?;
+            let variable = match &load_option {
+                // keep the attributes of existing entries, like set_boot_entry_flag
+                Some(load_option) => Some(boot_entry_variable(
+                    name.clone(),
+                    load_option,
+                    existing.as_ref().map_or(BOOT_VARIABLE_ATTRIBUTES, EFIVariable::attributes),
+                )?),
+                None => None,
+            };
+            changes.push(Change { name, variable });
+            previous.push(existing);
+        }
+        if let Some(order) = self.order {
+            let name = WellKnownVariable::BootOrder.variable_name();
+            let existing = efivars.read_variable(&name).await
+                .transpose().map_err(|err| ReadVariableError(name.clone(), err))?;
+            changes.push(Change { variable: Some(EFIVariable::new(name.clone(), BOOT_VARIABLE_ATTRIBUTES, order.to_bytes())), name });
+            previous.push(existing);
+        }
+
+        for (index, change) in changes.iter().enumerate() {
+            if let Err(source) = apply_change(efivars, &change.name, change.variable.as_ref()).await {
+                warn!("Failed to write {}, rolling back {} changes: {}", change.name, index, source);
+
+                let mut rollback_errors = vec![];
+                for (change, previous) in changes[..index].iter().zip(&previous).rev() {
+                    if let Err(err) = apply_change(efivars, &change.name, previous.as_ref()).await {
+                        rollback_errors.push((change.name.clone(), err));
+                    }
+                }
+                return Err(WriteVariableError { name: change.name.clone(), source, rollback_errors });
+            }
+            if let Some(progress) = progress {
+                progress(index + 1, changes.len());
+            }
+        }
+
+        debug!("Applied {} boot configuration changes", changes.len());
+
+        Ok(())
+    }
+}
+
+/// Writes the variable, or deletes it if `None`.
+async fn apply_change<E: EFIVars>(efivars: &E, name: &VariableName, variable: Option<&EFIVariable>) -> Result<(), E::WriteError> {
+    match variable {
+        Some(variable) => efivars.write_variable(variable).await,
+        None => efivars.delete_variable(name).await.map(|_| ()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::fmt::Debug;
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+    use thiserror::Error;
+    use crate::backend::EFIVars;
+    use crate::backend::mock::MemoryEFIVars;
+    use crate::efiboot::{BOOT_VARIABLE_ATTRIBUTES, boot_entry_name, BootOrder, ListBootEntriesExt};
+    use crate::efiloadoption::EFILoadOption;
+    use crate::efivar::{EFIVariable, VariableName};
+    use crate::fixtures::test_variable_as;
+    use crate::transaction::{BootConfigTransaction, BootConfigTransactionError};
+
+    #[derive(Debug, Error)]
+    #[error("simulated write failure")]
+    struct SimulatedWriteError;
+
+    /// Fails every write and delete of the variable with the key.
+    #[derive(Debug)]
+    struct FailingEFIVars {
+        inner: MemoryEFIVars,
+        fail_key: &'static str,
+    }
+
+    impl FailingEFIVars {
+        fn check(&self, name: &VariableName) -> Result<(), SimulatedWriteError> {
+            match name.key() == self.fail_key {
+                true => Err(SimulatedWriteError),
+                false => Ok(()),
+            }
+        }
+    }
+
+    #[async_trait(? Send)]
+    impl EFIVars for FailingEFIVars {
+        type ListError = <MemoryEFIVars as EFIVars>::ListError;
+        type ReadError = <MemoryEFIVars as EFIVars>::ReadError;
+        type WriteError = SimulatedWriteError;
+
+        async fn enumerate_variables(&self) -> Result<Vec<VariableName>, Self::ListError> {
+            self.inner.enumerate_variables().await
+        }
+
+        async fn read_variable(&self, name: &VariableName) -> Option<Result<EFIVariable, Self::ReadError>> {
+            self.inner.read_variable(name).await
+        }
+
+        async fn write_variable(&self, variable: &EFIVariable) -> Result<(), Self::WriteError> {
+            self.check(variable.name())?;
+            self.inner.write_variable(variable).await.map_err(|err| match err {})
+        }
+
+        async fn delete_variable(&self, name: &VariableName) -> Result<bool, Self::WriteError> {
+            self.check(name)?;
+            self.inner.delete_variable(name).await.map_err(|err| match err {})
+        }
+    }
+
+    fn seeded_efivars() -> MemoryEFIVars {
+        let boot_entry = |id| test_variable_as(&boot_entry_name(1), boot_entry_name(id));
+        MemoryEFIVars::with_variables([
+            boot_entry(1),
+            boot_entry(2),
+            EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BOOT_VARIABLE_ATTRIBUTES, vec![0x01, 0x00, 0x02, 0x00]),
+        ])
+    }
+
+    fn renamed_entry(efivars: &(impl EFIVars + Debug), id: u16) -> EFILoadOption {
+        let mut load_option = block_on(efivars.read_boot_entry(&boot_entry_name(id))).unwrap().unwrap().into_load_option();
+        load_option.set_description("Renamed");
+        load_option
+    }
+
+    fn transaction(efivars: &(impl EFIVars + Debug)) -> BootConfigTransaction {
+        BootConfigTransaction::new()
+            .write_entry(1, renamed_entry(efivars, 1))
+            .delete_entry(2)
+            .set_order(BootOrder::new(vec![1]))
+    }
+
+    #[test]
+    fn test_apply() {
+        let efivars = seeded_efivars();
+
+        block_on(transaction(&efivars).apply(&efivars)).unwrap();
+
+        let entries = block_on(efivars.list_boot_entries()).unwrap();
+        assert_eq!(vec![1], entries.order().iter().copied().collect::<Vec<_>>());
+        assert_eq!(vec!["Renamed"], entries.iter().map(|entry| entry.description()).collect::<Vec<_>>());
+        assert!(block_on(efivars.read_variable(&boot_entry_name(2))).is_none());
+    }
+
+    #[test]
+    fn test_apply_with_progress() {
+        let efivars = seeded_efivars();
+        let calls = RefCell::new(vec![]);
+
+        block_on(transaction(&efivars).apply_with_progress(&efivars, Some(&|current, total| calls.borrow_mut().push((current, total))))).unwrap();
+
+        assert_eq!(vec![(1, 3), (2, 3), (3, 3)], calls.into_inner());
+    }
+
+    #[test]
+    fn test_apply_rolls_back_on_failure() {
+        // the entry write and delete succeed, writing BootOrder fails
+        let efivars = FailingEFIVars { inner: seeded_efivars(), fail_key: "BootOrder" };
+        let before = block_on(efivars.list_boot_entries()).unwrap();
+
+        let result = block_on(transaction(&efivars).apply(&efivars));
+
+        match result {
+            Err(BootConfigTransactionError::WriteVariableError { name, rollback_errors, .. }) => {
+                assert_eq!("BootOrder", name.key());
+                assert!(rollback_errors.is_empty());
+            }
+            result => panic!("unexpected result: {result:?}"),
+        }
+        assert!(before == block_on(efivars.list_boot_entries()).unwrap());
+    }
+}
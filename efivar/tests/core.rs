@@ -0,0 +1,38 @@
+//! The parsing code and the in-memory backend must build without gio. The CI workflow in
+//! `.github/workflows/ci.yml` runs these with `cargo test -p efivar --no-default-features` as
+//! well as with the default features.
+
+use std::io::Cursor;
+use enumflags2::BitFlags;
+use futures::executor::block_on;
+use efivar::backend::EFIVars;
+use efivar::backend::mock::MemoryEFIVars;
+use efivar::efiboot::ListBootEntriesExt;
+use efivar::efidevicepath::{EFIDevicePathProtocol, MediaDevicePath};
+use efivar::efiloadoption::EFILoadOption;
+use efivar::efivar::{EFIVariable, parse_efivar_payload, VariableName};
+
+const BOOT_ENTRY: &[u8] = include_bytes!("../src/test/Boot0001-8be4df61-93ca-11d2-aa0d-00e098032b8c");
+
+#[test]
+fn test_parse_load_option() {
+    let (_, data) = parse_efivar_payload(BOOT_ENTRY).unwrap();
+    let load_option = EFILoadOption::parse(&mut Cursor::new(data)).unwrap();
+
+    assert_eq!("Arch Linux", load_option.description());
+    assert!(load_option.device_path_nodes().any(|node| matches!(node, EFIDevicePathProtocol::MediaDevicePath(MediaDevicePath::FilePath(_)))));
+    assert_eq!(data, load_option.to_bytes().unwrap().as_slice());
+}
+
+#[test]
+fn test_list_boot_entries() {
+    let (attributes, data) = parse_efivar_payload(BOOT_ENTRY).unwrap();
+    let efivars = MemoryEFIVars::with_variables([
+        EFIVariable::new(VariableName::global_vendor_new("Boot0001".to_owned()), attributes, data.to_vec()),
+        EFIVariable::new(VariableName::global_vendor_new("BootOrder".to_owned()), BitFlags::empty(), vec![0x01, 0x00]),
+    ]);
+
+    let entries = block_on(efivars.list_boot_entries()).unwrap();
+    assert_eq!(vec!["Arch Linux"], entries.iter().map(|entry| entry.description()).collect::<Vec<_>>());
+    assert!(block_on(efivars.read_variable(&VariableName::global_vendor_new("Timeout".to_owned()))).is_none());
+}
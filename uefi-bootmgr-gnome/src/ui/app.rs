@@ -1,10 +1,17 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
 use adw::prelude::*;
-use adw::gtk::{Align, Box, Label, ListBox, Orientation, SelectionMode};
-use adw::{ActionRow, Clamp, HeaderBar, StatusPage, WindowTitle};
+use adw::gtk::{ActionBar, Align, Box, Button, Image, Label, ListBox, Orientation, SelectionMode, Switch, Widget, Window};
+use adw::{ActionRow, Clamp, HeaderBar, MessageDialog, ResponseAppearance, StatusPage, WindowTitle};
 use adw::gio::File;
-use adw::glib::MainContext;
+use adw::glib::{clone, MainContext};
 use efivar::backend::{EFIVars, platform_backend};
-use efivar::efiboot::ListBootEntriesExt;
+use efivar::efiboot::{BootEntry, ListBootEntriesExt, PendingBootOrder};
+use efivar::efiloadoption::{EFILoadOption, LoadOptionAttributeFlag};
+#[cfg(target_os = "linux")]
+use efivar::reboot::request_reboot;
+use efivar::transaction::BootConfigTransaction;
 
 pub fn main_window() -> Box {
     let container = Box::new(Orientation::Vertical, 0);
@@ -17,52 +24,300 @@ pub fn main_window() -> Box {
 
     container.append(&clamp);
 
-    {
-        let content = content.clone();
-        MainContext::default().spawn_local(async move {
-            match platform_backend().await {
-                Ok(efivars) => main_page(efivars, content).await,
-                Err(err) => {
-                    content.append(&StatusPage::builder()
-                        .description(format!("<b>Failed to initialize EFI backend</b>\r\r{}", err))
-                        .icon_name("dialog-warning-symbolic")
-                        .build());
-                }
-            }
-        });
-    }
+    load_backend(content);
 
     container
 }
 
-async fn main_page(efivars: impl EFIVars, content: Box) {
-    match efivars.list_boot_entries().await {
-        Ok(entries) => {
-            content.append(&Label::builder()
-                .label("Boot order")
-                .halign(Align::Start)
-                .css_classes(["heading"])
-                .margin_top(10)
-                .build());
-            let list = ListBox::builder()
-                .selection_mode(SelectionMode::None)
-                .css_classes(vec!["boxed-list"])
-                .build();
-            content.append(&list);
-
-            for entry in entries.iter() {
-                let active = if entry.is_active() { "Active" } else { "Inactive" };
-                list.append(&ActionRow::builder()
-                    .title(entry.description())
-                    .subtitle(format!("Boot{:04X} - {}", entry.id(), active))
-                    .build());
+fn load_backend(content: Box) {
+    MainContext::default().spawn_local(async move {
+        match platform_backend().await {
+            Ok(efivars) => main_page(Rc::new(efivars), content).await,
+            Err(err) => {
+                let retry = Button::builder()
+                    .label("Retry")
+                    .halign(Align::Center)
+                    .css_classes(["pill"])
+                    .build();
+                let status = StatusPage::builder()
+                    .description(format!("<b>Failed to initialize EFI backend</b>\r\r{}", err))
+                    .icon_name("dialog-warning-symbolic")
+                    .child(&retry)
+                    .build();
+                content.append(&status);
+
+                retry.connect_clicked(clone!(@weak content, @weak status => move |_| {
+                    content.remove(&status);
+                    load_backend(content);
+                }));
             }
         }
+    });
+}
+
+/// Edits made in the UI that haven't been written to the firmware yet. Nothing is written until
+/// they are applied together as a [BootConfigTransaction], reverting reads the firmware again.
+struct PendingChanges {
+    original: HashMap<u16, EFILoadOption>,
+    edited: RefCell<BTreeMap<u16, EFILoadOption>>,
+    /// The order of the rows, matching the listed entries.
+    order: RefCell<PendingBootOrder>,
+    bar: ActionBar,
+}
+
+impl PendingChanges {
+    fn is_changed(&self) -> bool {
+        !self.edited.borrow().is_empty() || self.order.borrow().is_changed()
+    }
+
+    fn update_bar(&self) {
+        self.bar.set_revealed(self.is_changed());
+    }
+
+    /// Swaps the entry with the row above or below it. Returns its new row index, `None` if it's
+    /// already first or last.
+    fn move_entry(&self, id: u16, up: bool) -> Option<usize> {
+        let target = self.order.borrow_mut().move_entry(id, up)?;
+        self.update_bar();
+        Some(target)
+    }
+
+    fn set_flag(&self, id: u16, flag: LoadOptionAttributeFlag, enabled: bool) {
+        let original = match self.original.get(&id) {
+            Some(original) => original,
+            None => return,
+        };
+        let mut edited = self.edited.borrow_mut();
+        let mut load_option = edited.get(&id).unwrap_or(original).clone();
+        load_option.set_flag(flag, enabled);
+
+        // toggling back to how the entry was read leaves nothing to write
+        if &load_option == original {
+            edited.remove(&id);
+        } else {
+            edited.insert(id, load_option);
+        }
+        drop(edited);
+        self.update_bar();
+    }
+
+    fn transaction(&self) -> BootConfigTransaction {
+        let transaction = self.edited.borrow().iter()
+            .fold(BootConfigTransaction::new(), |transaction, (id, load_option)| transaction.write_entry(*id, load_option.clone()));
+        let order = self.order.borrow();
+        match order.is_changed() {
+            true => transaction.set_order(order.order()),
+            false => transaction,
+        }
+    }
+}
+
+/// Throws away the page, including pending changes, and builds it again from the firmware.
+fn reload_page<E: EFIVars + 'static>(efivars: Rc<E>, content: Box) {
+    while let Some(child) = content.first_child() {
+        content.remove(&child);
+    }
+    MainContext::default().spawn_local(main_page(efivars, content));
+}
+
+async fn main_page<E: EFIVars + 'static>(efivars: Rc<E>, content: Box) {
+    let (entries, errors) = match efivars.list_boot_entries_lossy().await {
+        Ok(result) => result,
         Err(err) => {
             content.append(&StatusPage::builder()
                 .description(format!("<b>Failed to list EFI boot entries</b>\r\r{}", err))
                 .icon_name("dialog-warning-symbolic")
                 .build());
+            return;
         }
+    };
+
+    // show the entries that could be read, and which ones couldn't above them
+    if !errors.is_empty() {
+        let failed = errors.iter()
+            .map(|(id, err)| format!("Boot{:04X}: {}", id, err))
+            .collect::<Vec<_>>();
+        content.append(&Label::builder()
+            .label(format!("Some boot entries could not be read:\n{}", failed.join("\n")))
+            .halign(Align::Start)
+            .wrap(true)
+            .css_classes(["warning"])
+            .margin_top(10)
+            .build());
     }
+
+    if entries.iter().next().is_none() {
+        content.append(&StatusPage::builder()
+            .title("No boot entries found")
+            .description("The firmware's BootOrder is empty.")
+            .icon_name("drive-harddisk-symbolic")
+            .build());
+        return;
+    }
+
+    content.append(&Label::builder()
+        .label("Boot order")
+        .halign(Align::Start)
+        .css_classes(["heading"])
+        .margin_top(10)
+        .build());
+    let list = ListBox::builder()
+        .selection_mode(SelectionMode::None)
+        .css_classes(vec!["boxed-list"])
+        .build();
+    content.append(&list);
+
+    let pending = Rc::new(PendingChanges {
+        original: entries.iter().map(|entry| (entry.id(), entry.load_option().clone())).collect(),
+        edited: RefCell::new(BTreeMap::new()),
+        order: RefCell::new(PendingBootOrder::new(&entries)),
+        bar: ActionBar::builder().revealed(false).build(),
+    });
+    let rows = Rc::new(entries.iter()
+        .map(|entry| (entry.id(), entry_row(&efivars, &pending, entry)))
+        .collect::<Vec<_>>());
+    let writable = efivars.capabilities().write;
+    for (id, (row, up, down)) in rows.iter() {
+        list.append(row);
+
+        let id = *id;
+        for (button, up) in [(up, true), (down, false)] {
+            button.connect_clicked(clone!(@strong pending, @weak rows, @weak list, @weak row => move |_| {
+                // only the staged order changes, rows move without rebuilding the list
+                if let Some(index) = pending.move_entry(id, up) {
+                    list.remove(&row);
+                    list.insert(&row, index as i32);
+                    update_move_buttons(&rows, &pending, writable);
+                }
+            }));
+        }
+    }
+    update_move_buttons(&rows, &pending, writable);
+
+    let apply = Button::builder()
+        .label("Apply Changes")
+        .css_classes(["suggested-action"])
+        .build();
+    let revert = Button::with_label("Revert");
+    pending.bar.pack_start(&Label::new(Some("Unsaved changes")));
+    pending.bar.pack_end(&apply);
+    pending.bar.pack_end(&revert);
+    content.append(&pending.bar);
+
+    apply.connect_clicked(clone!(@strong efivars, @weak pending, @weak content => move |apply| {
+        apply.set_sensitive(false);
+        let transaction = pending.transaction();
+        MainContext::default().spawn_local(clone!(@strong efivars, @weak content => async move {
+            // a failed transaction is rolled back, so reloading shows what the firmware has
+            if let Err(err) = transaction.apply(&*efivars).await {
+                show_message(content.root().and_downcast::<Window>().as_ref(), "Failed to apply changes", &err.to_string());
+            }
+            reload_page(efivars, content);
+        }));
+    }));
+    revert.connect_clicked(clone!(@strong efivars, @weak content => move |_| {
+        reload_page(efivars.clone(), content);
+    }));
+}
+
+/// Allows moving rows up unless they're first, and down unless they're last.
+fn update_move_buttons(rows: &[(u16, (ActionRow, Button, Button))], pending: &PendingChanges, writable: bool) {
+    let order = pending.order.borrow();
+    for (id, (_, up, down)) in rows {
+        up.set_sensitive(writable && order.can_move(*id, true));
+        down.set_sensitive(writable && order.can_move(*id, false));
+    }
+}
+
+/// The row of the entry, along with its move up and move down buttons.
+fn entry_row<E: EFIVars + 'static>(efivars: &Rc<E>, pending: &Rc<PendingChanges>, entry: &BootEntry) -> (ActionRow, Button, Button) {
+    let writable = efivars.capabilities().write;
+    let row = ActionRow::builder()
+        .title(entry.description())
+        .subtitle(format!("Boot{:04X} · {}", entry.id(), entry.load_option().human_summary()))
+        // the full device path for those who want to compare it with efibootmgr -v
+        .tooltip_text(entry.load_option().display_file_path_list().to_string())
+        .build();
+    row.add_prefix(&Image::from_icon_name(entry.suggested_icon()));
+    let up = Button::builder()
+        .icon_name("go-up-symbolic")
+        .tooltip_text("Move up in the boot order")
+        .valign(Align::Center)
+        .css_classes(["flat"])
+        .build();
+    let down = Button::builder()
+        .icon_name("go-down-symbolic")
+        .tooltip_text("Move down in the boot order")
+        .valign(Align::Center)
+        .css_classes(["flat"])
+        .build();
+    row.add_suffix(&up);
+    row.add_suffix(&down);
+    let active = Switch::builder()
+        .active(entry.is_active())
+        .tooltip_text("Allow the firmware to boot this entry")
+        .valign(Align::Center)
+        .sensitive(writable)
+        .build();
+    row.add_suffix(&active);
+    let boot_next = Button::builder()
+        .icon_name("media-skip-forward-symbolic")
+        .tooltip_text("Boot this entry next time")
+        .valign(Align::Center)
+        .css_classes(["flat"])
+        .sensitive(writable)
+        .build();
+    row.add_suffix(&boot_next);
+
+    let id = entry.id();
+    active.connect_active_notify(clone!(@strong pending => move |active| {
+        pending.set_flag(id, LoadOptionAttributeFlag::Active, active.is_active());
+    }));
+    boot_next.connect_clicked(clone!(@strong efivars, @weak row => move |_| {
+        MainContext::default().spawn_local(clone!(@strong efivars, @weak row => async move {
+            match efivars.set_boot_next(id).await {
+                Ok(()) => offer_reboot(&row),
+                Err(err) => show_message(row.root().and_downcast::<Window>().as_ref(), "Failed to set BootNext", &err.to_string()),
+            }
+        }));
+    }));
+
+    (row, up, down)
+}
+
+/// Asks whether to restart right away, now that the next boot target is set.
+#[cfg(target_os = "linux")]
+fn offer_reboot(widget: &impl IsA<Widget>) {
+    let parent = widget.root().and_downcast::<Window>();
+    let dialog = MessageDialog::new(
+        parent.as_ref(),
+        Some("Restart now?"),
+        Some("The selected entry will be booted on the next restart."),
+    );
+    dialog.add_response("later", "Later");
+    dialog.add_response("restart", "Restart");
+    dialog.set_response_appearance("restart", ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("restart"));
+    dialog.set_close_response("later");
+
+    dialog.connect_response(Some("restart"), move |_, _| {
+        let parent = parent.clone();
+        MainContext::default().spawn_local(async move {
+            if let Err(err) = request_reboot().await {
+                show_message(parent.as_ref(), "Failed to restart", &format!("{}\r\rRestart manually to boot the selected entry.", err));
+            }
+        });
+    });
+    dialog.present();
+}
+
+#[cfg(not(target_os = "linux"))]
+fn offer_reboot(widget: &impl IsA<Widget>) {
+    show_message(widget.root().and_downcast::<Window>().as_ref(), "Boot entry selected", "The selected entry will be booted on the next restart.");
+}
+
+fn show_message(parent: Option<&Window>, heading: &str, body: &str) {
+    let dialog = MessageDialog::new(parent, Some(heading), Some(body));
+    dialog.add_response("close", "Close");
+    dialog.present();
 }
@@ -0,0 +1,196 @@
+use std::io;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use efivar::backend::{EFIVars, platform_backend};
+use efivar::efiboot::{BootEntry, BootOrder, ListBootEntriesExt};
+use efivar::efivar::WellKnownVariable;
+use gio::glib::MainContext;
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+
+const HELP: &str = "↑/↓ select  shift+↑/↓ move  space toggle active  n boot next  q quit";
+
+struct App<E: EFIVars> {
+    efivars: E,
+    entries: Vec<BootEntry>,
+    state: ListState,
+    status: String,
+}
+
+impl<E: EFIVars> App<E> {
+    fn new(efivars: E) -> Self {
+        Self { efivars, entries: vec![], state: ListState::default(), status: HELP.to_owned() }
+    }
+
+    async fn reload(&mut self) {
+        match self.efivars.list_boot_entries().await {
+            Ok(entries) => {
+                self.entries = entries.iter().cloned().collect();
+                let selected = self.state.selected().unwrap_or(0).min(self.entries.len().saturating_sub(1));
+                self.state.select((!self.entries.is_empty()).then_some(selected));
+                if !entries.errors().is_empty() {
+                    let failed = entries.errors().iter()
+                        .map(|(id, _)| format!("Boot{:04X}", id))
+                        .collect::<Vec<_>>();
+                    self.status = format!("Failed to read {}", failed.join(", "));
+                }
+            }
+            Err(err) => self.status = format!("Failed to list boot entries: {}", err),
+        }
+    }
+
+    fn selected(&self) -> Option<&BootEntry> {
+        self.entries.get(self.state.selected()?)
+    }
+
+    fn select_offset(&mut self, offset: isize) {
+        if let Some(selected) = self.state.selected() {
+            let selected = selected.saturating_add_signed(offset).min(self.entries.len().saturating_sub(1));
+            self.state.select(Some(selected));
+        }
+    }
+
+    async fn move_selected(&mut self, up: bool) {
+        let Some(id) = self.selected().map(BootEntry::id) else { return };
+
+        // only the order changes, there's no need to read every entry again before writing it
+        let mut order = match self.efivars.read_variable(&WellKnownVariable::BootOrder.variable_name()).await {
+            Some(Ok(variable)) => match BootOrder::from_bytes(variable.data()) {
+                Ok(order) => order,
+                Err(err) => return self.status = format!("Failed to parse boot order: {}", err),
+            },
+            Some(Err(err)) => return self.status = format!("Failed to read boot order: {}", err),
+            None => return,
+        };
+        let moved = if up { order.move_up(id) } else { order.move_down(id) };
+        if !moved {
+            return;
+        }
+
+        self.status = match self.efivars.write_boot_order(&order).await {
+            Ok(()) => format!("Moved Boot{:04X}", id),
+            Err(err) => format!("Failed to write boot order: {}", err),
+        };
+        self.reload().await;
+        // the entries are listed in boot order, follow the moved entry to its new place
+        if let Some(index) = self.entries.iter().position(|entry| entry.id() == id) {
+            self.state.select(Some(index));
+        }
+    }
+
+    async fn toggle_selected(&mut self) {
+        let Some((id, active)) = self.selected().map(|entry| (entry.id(), entry.is_active())) else { return };
+
+        self.status = match self.efivars.set_boot_entry_active(id, !active).await {
+            Ok(()) => format!("Boot{:04X} is now {}", id, if active { "inactive" } else { "active" }),
+            Err(err) => format!("Failed to update Boot{:04X}: {}", id, err),
+        };
+        self.reload().await;
+    }
+
+    async fn set_boot_next_selected(&mut self) {
+        let Some(id) = self.selected().map(BootEntry::id) else { return };
+
+        self.status = match self.efivars.set_boot_next(id).await {
+            Ok(()) => format!("Boot{:04X} will be booted next", id),
+            Err(err) => format!("Failed to set BootNext: {}", err),
+        };
+    }
+}
+
+/// Keeps the terminal in raw mode on the alternate screen, restoring it when dropped so errors
+/// returned from [run] don't leave the shell unusable.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> io::Result<Self> {
+        enable_raw_mode()?;
+        let guard = TerminalGuard;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = restore_terminal();
+    }
+}
+
+fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)
+}
+
+fn ui<B: Backend, E: EFIVars>(frame: &mut Frame<B>, app: &mut App<E>) {
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(frame.size());
+
+    let items = app.entries.iter()
+        .map(|entry| {
+            let active = if entry.is_active() { "*" } else { " " };
+            ListItem::new(format!("{} Boot{:04X}  {}", active, entry.id(), entry.description()))
+        })
+        .collect::<Vec<_>>();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("UEFI Boot Manager"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    frame.render_stateful_widget(list, layout[0], &mut app.state);
+    frame.render_widget(Paragraph::new(app.status.as_str()), layout[1]);
+}
+
+fn run<B: Backend, E: EFIVars>(terminal: &mut Terminal<B>, app: &mut App<E>) -> io::Result<()> {
+    let context = MainContext::default();
+    context.block_on(app.reload());
+
+    loop {
+        terminal.draw(|frame| ui(frame, app))?;
+
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Up if shift => context.block_on(app.move_selected(true)),
+            KeyCode::Down if shift => context.block_on(app.move_selected(false)),
+            KeyCode::Up => app.select_offset(-1),
+            KeyCode::Down => app.select_offset(1),
+            KeyCode::Char(' ') => context.block_on(app.toggle_selected()),
+            KeyCode::Char('n') => context.block_on(app.set_boot_next_selected()),
+            _ => {}
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let efivars = match MainContext::default().block_on(platform_backend()) {
+        Ok(efivars) => efivars,
+        Err(err) => {
+            eprintln!("Failed to initialize EFI backend: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    // the guard only restores the terminal after the panic message was printed to the alternate
+    // screen, where it would be lost
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        hook(info);
+    }));
+
+    let _guard = TerminalGuard::new()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    run(&mut terminal, &mut App::new(efivars))
+}